@@ -1,4 +1,5 @@
 use leptos_ethereum_provider::{base_currency, Chain};
+use web3::types::{Address, H256};
 
 
 pub fn ethereum() -> Chain {
@@ -20,3 +21,109 @@ pub fn sepolia_testnet() -> Chain {
         block_explorer_urls: Some([String::from("https://sepolia.etherscan.io")]),
     }
 }
+
+
+/// Chain the contracts are deployed on, selected by its id through the `CHAIN_ID` env variable, Sepolia by default.
+pub fn expected_chain() -> Chain {
+    let chain_id = option_env!("CHAIN_ID").and_then(parse_chain_id);
+    [ethereum(), sepolia_testnet()].into_iter()
+        .find(|chain| chain_id.is_some() && parse_chain_id(&chain.chain_id) == chain_id)
+        .unwrap_or_else(sepolia_testnet)
+}
+
+/// Chain id given either in hex with a `0x` prefix, as wallets report it, or in decimal.
+fn parse_chain_id(chain_id: &str) -> Option<u64> {
+    match chain_id.strip_prefix("0x").or_else(|| chain_id.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => chain_id.parse().ok(),
+    }
+}
+
+/// Whether the wallet is connected to the chain the contracts are deployed on.
+pub fn is_expected_chain(chain_id: u64) -> bool {
+    parse_chain_id(&expected_chain().chain_id) == Some(chain_id)
+}
+
+
+/// Block explorer page of `path` on `chain`, `None` for chains without an explorer.
+fn explorer_url(chain: &Chain, path: String) -> Option<String> {
+    chain.block_explorer_urls.as_ref().map(|urls| format!("{}/{path}", urls[0].trim_end_matches('/')))
+}
+
+pub fn address_url(chain: &Chain, address: Address) -> Option<String> {
+    explorer_url(chain, format!("address/{address:?}"))
+}
+
+/// Page of the ERC1155 token `token_id` minted by `contract`.
+pub fn nft_url(chain: &Chain, contract: Address, token_id: u128) -> Option<String> {
+    explorer_url(chain, format!("nft/{contract:?}/{token_id}"))
+}
+
+/// Page of the ERC20 token deployed at `contract`.
+pub fn erc20_url(chain: &Chain, contract: Address) -> Option<String> {
+    explorer_url(chain, format!("token/{contract:?}"))
+}
+
+pub fn transaction_url(chain: &Chain, transaction_hash: H256) -> Option<String> {
+    explorer_url(chain, format!("tx/{transaction_hash:?}"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn chain_ids_parse_from_hex_and_decimal() {
+        assert_eq!(parse_chain_id("0x1"), Some(1));
+        assert_eq!(parse_chain_id("0xAA36A7"), Some(11155111));
+        assert_eq!(parse_chain_id("0Xaa36a7"), Some(11155111));
+        assert_eq!(parse_chain_id("11155111"), Some(11155111));
+    }
+
+    #[wasm_bindgen_test]
+    fn invalid_chain_ids_are_rejected() {
+        assert_eq!(parse_chain_id(""), None);
+        assert_eq!(parse_chain_id("0x"), None);
+        assert_eq!(parse_chain_id("0xzz"), None);
+        assert_eq!(parse_chain_id("sepolia"), None);
+        assert_eq!(parse_chain_id("-1"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn expected_chain_is_recognized() {
+        let chain_id = parse_chain_id(&expected_chain().chain_id).unwrap();
+        assert!(is_expected_chain(chain_id));
+        assert!(!is_expected_chain(chain_id + 1));
+    }
+
+    #[wasm_bindgen_test]
+    fn explorer_urls_point_to_the_chain_explorer() {
+        let chain = sepolia_testnet();
+        let address = Address::repeat_byte(0xab);
+        let hex = "0xabababababababababababababababababababab";
+        assert_eq!(address_url(&chain, address).unwrap(), format!("https://sepolia.etherscan.io/address/{hex}"));
+        assert_eq!(nft_url(&chain, address, 42).unwrap(), format!("https://sepolia.etherscan.io/nft/{hex}/42"));
+        assert_eq!(erc20_url(&chain, address).unwrap(), format!("https://sepolia.etherscan.io/token/{hex}"));
+        assert_eq!(
+            transaction_url(&chain, H256::repeat_byte(0x01)).unwrap(),
+            format!("https://sepolia.etherscan.io/tx/0x{}", "01".repeat(32)),
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn trailing_slash_of_the_explorer_is_ignored() {
+        let chain = Chain { block_explorer_urls: Some([String::from("https://etherscan.io/")]), ..ethereum() };
+        assert_eq!(address_url(&chain, Address::zero()).unwrap(), format!("https://etherscan.io/address/0x{}", "00".repeat(20)));
+    }
+
+    #[wasm_bindgen_test]
+    fn chains_without_an_explorer_have_no_urls() {
+        let chain = Chain { block_explorer_urls: None, ..ethereum() };
+        assert!(address_url(&chain, Address::zero()).is_none());
+        assert!(nft_url(&chain, Address::zero(), 1).is_none());
+        assert!(transaction_url(&chain, H256::zero()).is_none());
+    }
+}
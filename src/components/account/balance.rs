@@ -6,14 +6,18 @@ use leptos::*;
 use leptos_ethereum_provider::AccountLabel;
 
 use crate::{
-    components::state::Web3,
+    chain::{erc20_url, expected_chain, transaction_url},
+    components::{confirmation::ConfirmationEstimate, state::Web3},
     evm::contracts::{
         self,
         ERC1155Contract,
+        TxStatus,
         Wrapped1155FactoryContract,
         ERC20Contract
     },
-    state::State,
+    evm::{types::OMAmount, wallet::{self, WatchAsset}},
+    state::{LedgerAction, State},
+    util::{format_number, uniswap_link},
 };
 
 
@@ -22,7 +26,7 @@ async fn get_balance(
     erc1155_contract: ERC1155Contract,
     erc20_contract: ERC20Contract,
 ) -> Result<(f64, f64)> {
-    Ok((erc1155_contract.get_OM_balance(address).await?, erc20_contract.get_balance(address).await?))
+    Ok((erc1155_contract.get_OM_balance(address).await?.to_ether(), erc20_contract.get_balance(address).await?.to_ether()))
 }
 
 #[component]
@@ -36,12 +40,40 @@ pub fn Balance(
     let (wOM_balance, set_wOM_balance) = create_signal(0.0);
     let (wrap_amount, set_wrap_amount) = create_signal(0.0);
     let (unwrap_amount, set_unwrap_amount) = create_signal(0.0);
+    let (pending_transaction, set_pending_transaction) = create_signal(None);
 
-    let uniswap_link = format!("https://app.uniswap.org/#/swap?inputCurrency=ETH&outputCurrency={}", env!("ERC20_CONTRACT_ADDRESS"));
+    let uniswap_link = uniswap_link();
 
     let handle_error = Arc::new(move |error| handle_error.set(Some(error)));
-    let wrapper_contract = Wrapped1155FactoryContract::new(&web3, state.erc1155_contract.address(), handle_error);
-    let erc20_contract = ERC20Contract::new(&web3);
+    let wrapper_contract = Wrapped1155FactoryContract::new(&web3, state.erc1155_contract.address(), state.spectator, handle_error.clone());
+    let erc20_contract = ERC20Contract::new(&web3, state.spectator, handle_error);
+
+    let token_info = create_local_resource(|| (), {
+        let erc20_contract = erc20_contract.clone();
+        move |_| {
+            let erc20_contract = erc20_contract.clone();
+            async move {
+                Some((erc20_contract.get_symbol().await.ok()?, erc20_contract.get_decimals().await.ok()?))
+            }
+        }
+    });
+    let OM_total_supply = create_local_resource(|| (), {
+        let erc1155_contract = state.erc1155_contract.clone();
+        move |_| {
+            let erc1155_contract = erc1155_contract.clone();
+            async move { erc1155_contract.get_OM_total_supply().await.ok().map(|supply| supply.to_ether()) }
+        }
+    });
+    let (watch_asset_result, set_watch_asset_result) = create_signal(None);
+    let add_to_wallet = create_action({
+        let address = erc20_contract.address();
+        move |_| async move {
+            if let Some(Some((symbol, decimals))) = token_info.get_untracked() {
+                set_watch_asset_result.set(wallet::watch_asset(address, &symbol, decimals).await.ok());
+            }
+        }
+    });
+    let erc20_link = erc20_url(&expected_chain(), erc20_contract.address()).unwrap_or_default();
 
     let refresh_balance = create_action({
         let erc1155_contract = state.erc1155_contract.clone();
@@ -67,12 +99,24 @@ pub fn Balance(
     });
 
     let unwrap = create_action({
+        let state = state.clone();
         let wrapper_contract = wrapper_contract.clone();
         move |_| {
+            let state = state.clone();
+            let erc1155_contract = state.erc1155_contract.clone();
             let wrapper_contract = wrapper_contract.clone();
             async move {
-                if let Some(address) = state.address.get_untracked() {
-                    wrapper_contract.unwrap(address, unwrap_amount.get_untracked()).await;
+                if let Some(address) = state.sender().await {
+                    let amount = unwrap_amount.get_untracked();
+                    let before = erc1155_contract.get_OM_balance(address).await.ok();
+                    match wrapper_contract.unwrap(address, OMAmount::from_ether(amount)).await {
+                        Some(TxStatus::Confirmed(_)) => {
+                            state.ledger.record(LedgerAction::Transfer, None, amount);
+                            state.show_OM_change(address, before, amount).await;
+                        }
+                        Some(TxStatus::Pending(transaction_hash)) => set_pending_transaction.set(Some(transaction_hash)),
+                        None => {}
+                    }
                     refresh_balance.dispatch(());
                 }
             }
@@ -80,14 +124,24 @@ pub fn Balance(
     });
 
     let wrap = create_action({
-        let erc1155_contract = state.erc1155_contract.clone();
+        let state = state.clone();
         let wrapper_contract = wrapper_contract.clone();
         move |_| {
-            let erc1155_contract = erc1155_contract.clone();
+            let state = state.clone();
+            let erc1155_contract = state.erc1155_contract.clone();
             let wrapper_contract = wrapper_contract.clone();
             async move {
-                if let Some(address) = state.address.get_untracked() {
-                    erc1155_contract.transfer_OM(address, wrapper_contract.address(), wrap_amount.get_untracked()).await;
+                if let Some(address) = state.sender().await {
+                    let amount = wrap_amount.get_untracked();
+                    let before = erc1155_contract.get_OM_balance(address).await.ok();
+                    match wrapper_contract.wrap(&erc1155_contract, address, OMAmount::from_ether(amount)).await {
+                        Some(TxStatus::Confirmed(_)) => {
+                            state.ledger.record(LedgerAction::Transfer, None, -amount);
+                            state.show_OM_change(address, before, -amount).await;
+                        }
+                        Some(TxStatus::Pending(transaction_hash)) => set_pending_transaction.set(Some(transaction_hash)),
+                        None => {}
+                    }
                     refresh_balance.dispatch(());
                 }
             }
@@ -103,26 +157,67 @@ pub fn Balance(
                     <Button on_click=move |_| ()>"Buy wOM"</Button>
                 </a>
             </Stack>
+            <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                <span style="font-size: 0.8em">
+                    "wOM contract: "
+                    <a href=erc20_link target="_blank">{format!("{:?}", erc20_contract.address())}</a>
+                    {move || token_info.get().flatten().map(|(symbol, decimals)| format!(" ({symbol}, {decimals} decimals)"))}
+                </span>
+                <Button
+                    on_click=move |_| add_to_wallet.dispatch(())
+                    disabled=Signal::derive(move || token_info.get().flatten().is_none())
+                >
+                    "Add to wallet"
+                </Button>
+                {move || watch_asset_result.get().map(|result| match result {
+                    WatchAsset::Added => "Added",
+                    WatchAsset::Declined => "Not added",
+                    WatchAsset::Unsupported => "Your wallet does not support adding tokens, add the contract address manually",
+                })}
+            </Stack>
             <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
                 <strong>"wOM: "</strong>
                 {move || view! {
-                    {format!("{:.2}", wOM_balance.get())}
+                    {format_number(wOM_balance.get(), 2)}
                     <Slider style="width: 20em" min=0.0 max=wOM_balance.get() step=0.01
                         value=unwrap_amount set_value=set_unwrap_amount
                         value_display=move |v| format!("{v:.2}") />
                 }}
-                <Button on_click=move |_| unwrap.dispatch(())>"Unwrap"</Button>
+                <Button on_click=move |_| unwrap.dispatch(()) disabled=Signal::derive(move || !state.features.get().erc1155)>"Unwrap"</Button>
+                <ConfirmationEstimate pending=unwrap.pending()/>
             </Stack>
             <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
                 <strong>"OM: "</strong>
                 {move || view! {
-                    {format!("{:.2}", OM_balance.get())}
+                    {format_number(OM_balance.get(), 2)}
                     <Slider style="width: 20em" min=0.0 max=OM_balance.get() step=0.01
                         value=wrap_amount set_value=set_wrap_amount
                         value_display=move |v| format!("{v:.2}") />
                 }}
-                <Button on_click=move |_| wrap.dispatch(())>"Wrap"</Button>
+                <Button on_click=move |_| wrap.dispatch(()) disabled=Signal::derive(move || !state.features.get().erc1155)>"Wrap"</Button>
+                <ConfirmationEstimate pending=wrap.pending()/>
             </Stack>
+            {move || OM_total_supply.get().flatten().map(|supply| view! {
+                <p style="font-size: 0.8em">{format!("Total supply: {} OM", format_number(supply, 2))}</p>
+            })}
+            <Show when=move || !state.features.get().erc1155 fallback=|| {}>
+                <p>"Wrapping is unavailable, the contract does not support ERC1155"</p>
+            </Show>
+            {move || pending_transaction.get().map(|transaction_hash: web3::types::H256| {
+                let link = transaction_url(&expected_chain(), transaction_hash).unwrap_or_default();
+                view! {
+                    <p>
+                        "Transaction submitted but not yet confirmed, "
+                        <a href=link target="_blank">"track it here"</a>
+                    </p>
+                }
+            })}
+            {move || state.OM_change.get().map(|change| view! {
+                <p style:color=move || if change.unexpected() { "yellow" } else { "inherit" }>
+                    {format!("OM: {:.2} → {:.2} ({:+.2} OM)", change.before, change.after, change.delta())}
+                    {change.unexpected().then(|| format!(", expected {:+.2} OM", change.expected))}
+                </p>
+            })}
         </div>
     }
 }
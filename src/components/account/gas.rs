@@ -0,0 +1,58 @@
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::{
+    components::state::Web3,
+    evm::contracts::{default_gas_limit, gas_speed, set_gas_speed, GasSpeed},
+};
+
+
+#[component]
+pub fn GasSpeedSelector() -> impl IntoView {
+    let web3 = use_context::<Web3>().unwrap().0;
+    let (speed, set_speed) = create_signal(gas_speed());
+
+    let gas_price = create_local_resource(|| (), move |_| {
+        let web3 = web3.clone();
+        async move {
+            web3.eth().gas_price().await.ok()
+        }
+    });
+
+    let select = move |speed: GasSpeed| {
+        set_gas_speed(speed);
+        set_speed.set(speed);
+    };
+
+    view! {
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+            <strong>"Speed: "</strong>
+            {GasSpeed::ALL.into_iter().map(|speed_| view! {
+                <Button
+                    on_click=move |_| select(speed_)
+                    color=Signal::derive(move || if speed.get() == speed_ { ButtonColor::Primary } else { ButtonColor::Secondary })
+                >
+                    {speed_.label()}
+                </Button>
+            }).collect_view()}
+            <Button on_click=move |_| gas_price.refetch()>"↻"</Button>
+        </Stack>
+        {move || match gas_price.get().flatten() {
+            Some(gas_price) => GasSpeed::ALL.into_iter().map(|speed_| {
+                let price = speed_.gas_price(gas_price);
+                let cost = price * default_gas_limit();
+                view! {
+                    <p style="font-size: 0.8em">
+                        {format!(
+                            "{}: {:.2} gwei, at most {:.6} ETH per transaction",
+                            speed_.label(),
+                            price.as_u128() as f64 / 10_f64.powi(9),
+                            cost.as_u128() as f64 / 10_f64.powi(18),
+                        )}
+                    </p>
+                }
+            }).collect_view(),
+            None => view! { <p style="font-size: 0.8em">"Gas price unavailable"</p> }.into_view(),
+        }}
+    }
+}
@@ -0,0 +1,55 @@
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::state::{LedgerEntry, State};
+
+
+fn to_csv(entries: &[LedgerEntry]) -> String {
+    let mut csv = String::from("action,token_id,amount\n");
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            entry.action.label(),
+            entry.token_id.map(|token_id| token_id.to_string()).unwrap_or_default(),
+            entry.amount,
+        ));
+    }
+    csv
+}
+
+
+#[component]
+pub fn Ledger() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let ledger = state.ledger;
+
+    let export_link = move || format!(
+        "data:text/csv;charset=utf-8,{}",
+        String::from(js_sys::encode_uri_component(&ledger.0.with(|entries| to_csv(entries)))),
+    );
+
+    view! {
+        <Show when=move || ledger.0.with(|entries| !entries.is_empty()) fallback=|| {}>
+            <Stack orientation=StackOrientation::Vertical spacing=Size::Em(0.3)>
+                <strong>"Session:"</strong>
+                <For
+                    each=move || ledger.0.get().into_iter().enumerate()
+                    key=|(index, _)| *index
+                    children=move |(_, entry)| view! {
+                        <p>
+                            {entry.action.label()}
+                            {entry.token_id.map(|token_id| format!(" #{token_id}"))}
+                            {format!(": {:+.2} OM", entry.amount)}
+                        </p>
+                    }
+                />
+                <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                    <strong>{move || format!("Net: {:+.2} OM", ledger.total())}</strong>
+                    <a href=export_link download="session.csv">
+                        <Button on_click=move |_| ()>"Export"</Button>
+                    </a>
+                </Stack>
+            </Stack>
+        </Show>
+    }
+}
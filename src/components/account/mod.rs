@@ -1,10 +1,15 @@
 mod balance;
+mod gas;
+mod ledger;
 
 use leptonic::prelude::*;
 use leptos::*;
 use leptos_ethereum_provider::EthereumInterface;
 
+use crate::util::{format_number, locale, locale_setting, set_locale};
 use balance::Balance;
+use gas::GasSpeedSelector;
+use ledger::Ledger;
 
 
 #[component]
@@ -21,7 +26,7 @@ pub fn AccountButton(
             <button on:click=disconnect class="btn btn-primary connected">
                 <strong>"Balance: "</strong>
                 {
-                    move || format!("{:.2}", balance.get())
+                    move || format_number(balance.get(), 2)
                 }
             </button>
         </div>
@@ -45,9 +50,28 @@ pub fn Account(
         }
     };
 
+    let (locale_, set_locale_) = create_signal(locale_setting());
+    create_effect(move |_| set_locale(&locale_.get()));
+
     view! {
         <Drawer side=DrawerSide::Right shown=Signal::derive(move || open.get()) style="padding: 0.5em; height: 19.5em; overflow: scroll; position: absolute; top: 3em; right: 0; background-color: var(--brand-color); border-left: 1px solid gray;">
             <Balance OM_balance/>
+            <GasSpeedSelector/>
+            <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                <strong>"Number format: "</strong>
+                <input
+                    placeholder="Browser default"
+                    prop:value=locale_
+                    on:change=move |event| set_locale_.set(event_target_value(&event).trim().to_string())
+                />
+                <span style="font-size: 0.8em">
+                    {move || {
+                        locale_.track();
+                        format!("e.g. {} ({})", format_number(1234.5678, 4), locale())
+                    }}
+                </span>
+            </Stack>
+            <Ledger/>
             <button on:click=disconnect class="btn btn-primary connected">
                 "Disconnect"
             </button>
@@ -0,0 +1,155 @@
+use std::future::Future;
+
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::components::confirmation::ConfirmationEstimate;
+
+
+/// Dialog asking the user to confirm a transaction. It stays open while `on_confirm` is pending
+/// and closes once it resolves to `true`, the error itself is reported by the contract's error handler.
+#[component]
+pub fn ConfirmModal<F, Fut>(
+    #[prop(into)] show: Signal<bool>,
+    #[prop(into)] title: String,
+    #[prop(into, default = "Confirm".into())] confirm_label: String,
+    #[prop(into, default = "Cancel".into())] cancel_label: String,
+    on_confirm: F,
+    #[prop(into)] on_close: Callback<()>,
+    children: ChildrenFn,
+) -> impl IntoView
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = bool> + 'static,
+{
+    let (confirm, failed, close) = confirmation(on_confirm, on_close);
+
+    view! {
+        <Modal show_when=MaybeSignal::derive(move || show.get())>
+            <ModalHeader><ModalTitle>{title}</ModalTitle></ModalHeader>
+            <ModalBody>
+                {children()}
+                <Show when=move || failed.get() fallback=|| {}>
+                    <p style="color: red">"Action failed, you can try again"</p>
+                </Show>
+            </ModalBody>
+            <ModalFooter>
+                <ButtonWrapper>
+                    <Button
+                        on_click=move |_| confirm.dispatch(())
+                        disabled=Signal::derive(move || confirm.pending().get())
+                        color=ButtonColor::Primary
+                    >
+                        {move || if confirm.pending().get() { "Waiting…".to_string() } else { confirm_label.clone() }}
+                    </Button>
+                    <Button
+                        on_click=move |_| close.call(())
+                        disabled=Signal::derive(move || confirm.pending().get())
+                        color=ButtonColor::Secondary
+                    >
+                        {cancel_label}
+                    </Button>
+                </ButtonWrapper>
+                <ConfirmationEstimate pending=confirm.pending()/>
+            </ModalFooter>
+        </Modal>
+    }
+}
+
+/// Action running `on_confirm` and closing the dialog once it succeeds, whether it failed and the cancel handler,
+/// which closes the dialog without running anything.
+fn confirmation<F, Fut>(on_confirm: F, on_close: Callback<()>) -> (Action<(), ()>, ReadSignal<bool>, Callback<()>)
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = bool> + 'static,
+{
+    let (failed, set_failed) = create_signal(false);
+    let confirm = create_action(move |_: &()| {
+        let result = on_confirm();
+        async move {
+            let confirmed = result.await;
+            set_failed.set(!confirmed);
+            if confirmed {
+                on_close.call(());
+            }
+        }
+    });
+    let close = Callback::new(move |_| {
+        set_failed.set(false);
+        on_close.call(());
+    });
+    (confirm, failed, close)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::util::sleep;
+
+    fn counter() -> (Rc<Cell<usize>>, impl Fn() -> usize) {
+        let count = Rc::new(Cell::new(0));
+        (count.clone(), move || count.get())
+    }
+
+    #[wasm_bindgen_test]
+    async fn confirming_invokes_the_handler_and_closes() {
+        let runtime = create_runtime();
+        let (confirmed, confirmations) = counter();
+        let (closed, closings) = counter();
+        let (confirm, failed, _) = confirmation(
+            move || {
+                confirmed.set(confirmed.get() + 1);
+                async { true }
+            },
+            Callback::new(move |_| closed.set(closed.get() + 1)),
+        );
+
+        confirm.dispatch(());
+        sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(confirmations(), 1);
+        assert_eq!(closings(), 1);
+        assert!(!failed.get_untracked());
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn failed_confirmation_stays_open() {
+        let runtime = create_runtime();
+        let (closed, closings) = counter();
+        let (confirm, failed, _) = confirmation(|| async { false }, Callback::new(move |_| closed.set(closed.get() + 1)));
+
+        confirm.dispatch(());
+        sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(closings(), 0);
+        assert!(failed.get_untracked());
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn canceling_dismisses_without_invoking_the_handler() {
+        let runtime = create_runtime();
+        let (confirmed, confirmations) = counter();
+        let (closed, closings) = counter();
+        let (_, failed, close) = confirmation(
+            move || {
+                confirmed.set(confirmed.get() + 1);
+                async { true }
+            },
+            Callback::new(move |_| closed.set(closed.get() + 1)),
+        );
+
+        close.call(());
+
+        assert_eq!(confirmations(), 0);
+        assert_eq!(closings(), 1);
+        assert!(!failed.get_untracked());
+        runtime.dispose();
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use leptos::*;
+
+use crate::{
+    components::state::Web3,
+    evm::{blocks::{average_block_time, estimate_confirmation_time}, contracts::default_confirmations},
+    util::sleep,
+};
+
+
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(1);
+/// Countdown ticks between checks for newly mined blocks.
+const BLOCK_POLL_TICKS: u32 = 4;
+
+
+/// Countdown to the expected confirmation of a transaction, shown while `pending` is set.
+#[component]
+pub fn ConfirmationEstimate(
+    #[prop(into)] pending: Signal<bool>,
+) -> impl IntoView {
+    let web3 = use_context::<Web3>().unwrap().0;
+    let (remaining, set_remaining) = create_signal(None::<Duration>);
+
+    create_effect(move |_| {
+        if !pending.get() {
+            set_remaining.set(None);
+            return
+        }
+        let web3 = web3.clone();
+        spawn_local(async move {
+            let Ok((start, average)) = average_block_time(&web3).await else {
+                return
+            };
+            let mut eta = js_sys::Date::now() + estimate_confirmation_time(average, default_confirmations() as u64 + 1).as_millis() as f64;
+            let mut tick = 0;
+            while pending.get_untracked() {
+                if tick % BLOCK_POLL_TICKS == 0 {
+                    // re-estimate as blocks arrive, block time varies so the countdown can move both ways
+                    if let Ok(latest) = web3.eth().block_number().await {
+                        let mined = (latest - start).as_u64();
+                        let remaining_confirmations = (default_confirmations() as u64 + 1).saturating_sub(mined).max(1);
+                        if mined > 0 {
+                            eta = js_sys::Date::now() + estimate_confirmation_time(average, remaining_confirmations).as_millis() as f64;
+                        }
+                    }
+                }
+                set_remaining.set(Some(Duration::from_millis((eta - js_sys::Date::now()).max(0.0) as u64)));
+                sleep(COUNTDOWN_INTERVAL).await;
+                tick += 1;
+            }
+            set_remaining.set(None);
+        });
+    });
+
+    move || remaining.get().map(|remaining| view! {
+        <span style="font-size: 0.8em">
+            {if remaining.is_zero() {
+                "Confirmation any moment now".to_string()
+            } else {
+                format!("Confirmation in ~{}s", remaining.as_secs() + 1)
+            }}
+        </span>
+    })
+}
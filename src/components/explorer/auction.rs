@@ -1,59 +1,483 @@
+use std::{sync::Arc, time::Duration};
+
 use leptonic::prelude::*;
 use leptos::*;
 
 use crate::{
-    evm::types::{Field, Metadata},
-    state::State,
+    components::{confirm::ConfirmModal, query::use_contract_query},
+    evm::{contracts::{Deadline, TxStage}, types::{bid_increment, root_bids_allowed, Field, Metadata, MinimumPricePolicy, OMAmount, SubdivisionRule, ROOT_TOKEN_ID}},
+    state::{LedgerAction, State},
+    util::{copy_to_clipboard, format_amount, uniswap_link},
 };
 
 
+/// Problem with the combination of amounts entered into the bid form.
+#[derive(Clone, PartialEq)]
+enum BidIssue {
+    Invalid(String),
+    /// Not enough OM to bid at all.
+    Insufficient(String),
+    Warning(String),
+}
+
+fn validate_bid(minimum_price: f64, bid_amount: f64, bids_minimum_price: f64, OM_balance: f64) -> Option<BidIssue> {
+    if OM_balance < minimum_price {
+        Some(BidIssue::Insufficient(format!("Insufficient OM for the minimum bid (have {} OM, need {minimum_price} OM)", format_amount(OM_balance))))
+    } else if bid_amount < minimum_price {
+        Some(BidIssue::Invalid(format!("Bid amount has to be at least {minimum_price} OM, the minimum bid price of this NFT")))
+    } else if bids_minimum_price < minimum_price {
+        Some(BidIssue::Invalid(format!("Minimum bid price has to be at least {minimum_price} OM, the minimum bid price of this NFT")))
+    } else if bids_minimum_price > bid_amount {
+        Some(BidIssue::Warning("Minimum bid price exceeds your bid amount, others will have to pay more than you to mint inside of your NFT".into()))
+    } else {
+        None
+    }
+}
+
+fn validate_token(token_id: u128, root_bids_allowed: bool) -> Option<BidIssue> {
+    if token_id == ROOT_TOKEN_ID && !root_bids_allowed {
+        Some(BidIssue::Invalid("Bidding on the root NFT is not allowed, navigate into one of its NFTs to bid".into()))
+    } else {
+        None
+    }
+}
+
+/// A bid has no deadline unless the user sets one, 0 minutes meaning none.
+fn bid_deadline(minutes: f64) -> Option<Deadline> {
+    Some(minutes)
+        .filter(|minutes| *minutes > 0.0)
+        .map(|minutes| Deadline::after(Duration::from_secs_f64(minutes * 60.0)))
+}
+
+/// Bid amount and minimum bid price of the form, both start at the minimum price of the token and are reset to it
+/// whenever the snapshot of token id and minimum price changes.
+fn bid_amounts(snapshot: Memo<(u128, f64)>) -> (RwSignal<f64>, RwSignal<f64>) {
+    let minimum_price = snapshot.with_untracked(|(_, minimum_price)| *minimum_price);
+    let bid_amount = create_rw_signal(minimum_price);
+    let bids_minimum_price = create_rw_signal(minimum_price);
+
+    create_effect(move |previous: Option<(u128, f64)>| {
+        let current = snapshot.get();
+        if previous.is_some_and(|previous| previous != current) {
+            bid_amount.set(current.1);
+            bids_minimum_price.set(current.1);
+        }
+        current
+    });
+
+    (bid_amount, bids_minimum_price)
+}
+
+
+/// Bid amount moved by `steps` increments, never going below the minimum price.
+fn step_bid(bid_amount: f64, steps: i32, minimum_price: f64) -> f64 {
+    // round so that repeated steps don't accumulate floating point noise
+    let stepped = ((bid_amount + steps as f64 * bid_increment()) * 1e9).round() / 1e9;
+    stepped.max(minimum_price)
+}
+
+
+/// Kind of `MinimumPricePolicy` picked in the bid form.
+#[derive(Clone, Copy, PartialEq)]
+enum PolicyKind {
+    Flat,
+    PerArea,
+    PerDepth,
+}
+
+impl PolicyKind {
+    const ALL: [Self; 3] = [Self::Flat, Self::PerArea, Self::PerDepth];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Flat => "Flat",
+            Self::PerArea => "Per area",
+            Self::PerDepth => "Per depth",
+        }
+    }
+
+    fn price_label(&self) -> &'static str {
+        match self {
+            Self::Flat => "Minimum bid price:",
+            Self::PerArea => "Minimum bid price of the whole NFT:",
+            Self::PerDepth => "Base minimum bid price:",
+        }
+    }
+}
+
+
 #[component]
 pub fn Auction(
-    token: Metadata,
+    #[prop(into)] token: MaybeSignal<Metadata>,
+    /// Moves keyboard focus to the bid amount when set.
+    focus: RwSignal<bool>,
 ) -> impl IntoView {
     let state = use_context::<State>().unwrap();
 
-    let (bid_amount, set_bid_amount) = create_signal(token.minimum_price);
-    let (bids_minimum_price, set_bids_minimum_price) = create_signal(token.minimum_price);
+    let token = Signal::derive(move || token.get());
+    // every read goes through a single snapshot, so that the bounds and amounts of the form always belong to the same token
+    let snapshot = create_memo(move |_| token.with(|token| (token.token_id, token.minimum_price.to_ether())));
+    let token_id = move || snapshot.get().0;
+    let minimum_price = move || snapshot.get().1;
+
+    let (bid_amount, bids_minimum_price) = bid_amounts(snapshot);
+    let (bid_amount, set_bid_amount) = bid_amount.split();
+    let (bids_minimum_price, set_bids_minimum_price) = bids_minimum_price.split();
+    let (policy_kind, set_policy_kind) = create_signal(PolicyKind::Flat);
+    let (depth_factor, set_depth_factor) = create_signal(2.0);
+    let (deadline_minutes, set_deadline_minutes) = create_signal(0.0);
+
+    let mint_fee = use_contract_query(|| (), |erc1155_contract, _| async move {
+        Ok(erc1155_contract.get_mint_fee().await?.to_ether())
+    }).data;
+    let subdivision_rule = use_contract_query(move || token_id(), |erc1155_contract, token_id| async move {
+        erc1155_contract.get_subdivision_rule(token_id).await
+    }).data;
+
+    let current_field = {
+        let mandelbrot = state.mandelbrot.clone();
+        move || {
+            let bounds = mandelbrot.lock().unwrap().sample.borrow().get_bounds();
+            let field = Field {
+                x_min: bounds.x_min,
+                y_min: bounds.y_min,
+                x_max: bounds.x_max,
+                y_max: bounds.y_max,
+            };
+            let rule = subdivision_rule.get_untracked().unwrap_or_default();
+            token.with_untracked(|token| rule.snap(field, &token.field))
+        }
+    };
+
+    let policy = move || match policy_kind.get() {
+        PolicyKind::Flat => MinimumPricePolicy::Flat(bids_minimum_price.get()),
+        PolicyKind::PerArea => MinimumPricePolicy::PerArea(bids_minimum_price.get()),
+        PolicyKind::PerDepth => MinimumPricePolicy::PerDepth { base: bids_minimum_price.get(), factor: depth_factor.get() },
+    };
+    // the view isn't reactive, so area based prices are resolved for the view at the time of reading
+    let resolved_minimum_price = {
+        let current_field = current_field.clone();
+        move || token.with(|token| policy().minimum_price(&current_field(), token))
+    };
+
+    let bid_issue = create_memo({
+        let resolved_minimum_price = resolved_minimum_price.clone();
+        move |_| {
+            validate_token(token_id(), root_bids_allowed())
+                .or_else(|| policy().validate().err().map(BidIssue::Invalid))
+                .or_else(|| validate_bid(minimum_price(), bid_amount.get(), resolved_minimum_price(), state.OM_balance.get()))
+        }
+    });
 
+    let (show_calldata, set_show_calldata) = create_signal(false);
+    let calldata = {
+        let state = state.clone();
+        let current_field = current_field.clone();
+        let resolved_minimum_price = resolved_minimum_price.clone();
+        move || {
+            let address = state.address.get()?;
+            let calldata = state.erc1155_contract.bid_calldata(
+                address,
+                token_id(),
+                current_field(),
+                OMAmount::from_ether(bid_amount.get()),
+                OMAmount::from_ether(resolved_minimum_price()),
+            ).ok()?;
+            Some(format!("0x{}", calldata.iter().map(|byte| format!("{byte:02x}")).collect::<String>()))
+        }
+    };
+
+    let (bid_stage, set_bid_stage) = create_signal(None::<TxStage>);
     let create_bid = create_action({
+        let state = state.clone();
+        let current_field = current_field.clone();
         move |token_id| {
+            let state = state.clone();
             let erc1155_contract = state.erc1155_contract.clone();
-            let mandelbrot = state.mandelbrot.clone();
+            let field = current_field();
+            let bids_minimum_price = untrack(|| token.with(|token| policy().minimum_price(&field, token)));
             let token_id = *token_id;
             async move {
-                if let Some(address) = state.address.get_untracked() {
-                    let bounds = mandelbrot.lock().unwrap().sample.borrow().get_bounds();
-                    erc1155_contract.bid(
+                if let Some(address) = state.sender().await {
+                    let deadline = bid_deadline(deadline_minutes.get_untracked());
+                    let amount = bid_amount.get_untracked();
+                    // the displayed fee is what the user agreed to, a fee raised in the meantime stops the bid
+                    let fee = mint_fee.get_untracked();
+                    let max_amount = fee
+                        .map(|fee| OMAmount::from_ether(amount) + OMAmount::from_ether(fee));
+                    // the fee leaves the balance along with the bid
+                    let spent = amount + fee.unwrap_or_default();
+                    let before = erc1155_contract.get_OM_balance(address).await.ok();
+                    let result = erc1155_contract.bid(
                         address,
                         token_id,
-                        Field {
-                            x_min: bounds.x_min,
-                            y_min: bounds.y_min,
-                            x_max: bounds.x_max,
-                            y_max: bounds.y_max,
-                        },
-                        bid_amount.get_untracked(),
-                        bids_minimum_price.get_untracked(),
+                        field,
+                        OMAmount::from_ether(amount),
+                        OMAmount::from_ether(bids_minimum_price),
+                        max_amount,
+                        deadline,
+                        Some(Arc::new({
+                            let state = state.clone();
+                            move |stage| {
+                                // bids are reloaded right after submission too, but only show up once mined
+                                state.ledger.record_when_confirmed(&stage, LedgerAction::Bid, Some(token_id), -spent);
+                                if stage == TxStage::Confirmed {
+                                    let state = state.clone();
+                                    spawn_local(async move {
+                                        state.reload_bids(token_id).await;
+                                        state.show_OM_change(address, before, -spent).await;
+                                    });
+                                }
+                                set_bid_stage.set(Some(stage));
+                            }
+                        })),
                     ).await;
+                    if result.is_some() {
+                        state.refresh_OM_balance();
+                        state.reload_bids(token_id).await;
+                    }
+                };
+            }
+        }
+    });
+
+    // transactions still pending in the wallet would delay the bid, so the user gets to decide whether to wait
+    let (pending_transactions, set_pending_transactions) = create_signal(None::<u64>);
+    let submit_bid = create_action({
+        let erc1155_contract = state.erc1155_contract.clone();
+        move |token_id: &u128| {
+            let erc1155_contract = erc1155_contract.clone();
+            let token_id = *token_id;
+            async move {
+                let pending = match state.address.get_untracked() {
+                    Some(address) => erc1155_contract.get_pending_transactions(address).await.unwrap_or_default(),
+                    None => 0,
                 };
+                if pending > 0 {
+                    set_pending_transactions.set(Some(pending));
+                } else {
+                    create_bid.dispatch(token_id);
+                }
             }
         }
     });
 
     view! {
+        <ConfirmModal
+            show=Signal::derive(move || pending_transactions.get().is_some())
+            title="Pending transactions"
+            confirm_label="Proceed"
+            cancel_label="Wait"
+            on_confirm=move || {
+                // the bid reports its own progress and failure below the form, so the dialog closes right away
+                if !create_bid.pending().get_untracked() {
+                    create_bid.dispatch(token_id());
+                }
+                async { true }
+            }
+            on_close=move |_| set_pending_transactions.set(None)
+        >
+            <p>
+                {move || format!("You have {} pending transactions, ", pending_transactions.get().unwrap_or_default())}
+                "your bid will only be processed after they are mined or replaced in your wallet."
+            </p>
+        </ConfirmModal>
         <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
             <Stack orientation=StackOrientation::Vertical spacing=Size::Em(0.6)>
+                {move || {
+                    let minimum_price = minimum_price();
+                    view! {
+                        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                            "Bid amount:"
+                            <NumberInput
+                                min=minimum_price
+                                get=bid_amount
+                                set=set_bid_amount
+                                placeholder="Bid amount"
+                                should_be_focused=Signal::derive(move || focus.get())
+                                on_focus_change=move |focused: bool| focus.set(focused)
+                            />
+                            <Button on_click=move |_| set_bid_amount.update(|amount| *amount = step_bid(*amount, -1, minimum_price)) size=ButtonSize::Small>"-"</Button>
+                            <Button on_click=move |_| set_bid_amount.update(|amount| *amount = step_bid(*amount, 1, minimum_price)) size=ButtonSize::Small>"+"</Button>
+                        </Stack>
+                        <p style="font-size: 0.8em">"OM that you are willing to spend on minting, it will be locked inside of the new NFT."</p>
+                        {move || mint_fee.get().filter(|fee| *fee > 0.0).map(|fee| view! {
+                            <p style="font-size: 0.8em">{format!("Minting fee: {} OM", format_amount(fee))}</p>
+                        })}
+                    }
+                }}
                 <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
-                    "Bid amount:"
-                    <NumberInput min=token.minimum_price get=bid_amount set=set_bid_amount placeholder="Bid amount"/>
+                    "Minimum bid price policy:"
+                    <select on:change=move |event| {
+                        let kind = PolicyKind::ALL.into_iter().find(|kind| kind.label() == event_target_value(&event));
+                        set_policy_kind.set(kind.unwrap_or(PolicyKind::Flat));
+                    }>
+                        {PolicyKind::ALL.into_iter().map(|kind| view! {
+                            <option value=kind.label() selected=move || policy_kind.get() == kind>{kind.label()}</option>
+                        }).collect_view()}
+                    </select>
                 </Stack>
                 <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
-                    "Minimum bid price:"
-                    <NumberInput min=token.minimum_price get=bids_minimum_price set=set_bids_minimum_price placeholder="Minimum bid price"/>
+                    {move || policy_kind.get().price_label()}
+                    <NumberInput min=0.0 get=bids_minimum_price set=set_bids_minimum_price placeholder="Minimum bid price"/>
                 </Stack>
+                <Show when=move || policy_kind.get() == PolicyKind::PerDepth fallback=|| {}>
+                    <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                        "Factor per layer:"
+                        <NumberInput min=0.0 step=0.1 get=depth_factor set=set_depth_factor/>
+                    </Stack>
+                </Show>
+                <p style="font-size: 0.8em">
+                    "OM that others will have to bid in order to mint NFTs inside of yours"
+                    {
+                        let resolved_minimum_price = resolved_minimum_price.clone();
+                        move || (policy_kind.get() != PolicyKind::Flat).then(|| format!(", {:.4} OM for the current view", resolved_minimum_price()))
+                    }
+                    "."
+                </p>
+                {move || match subdivision_rule.get() {
+                    Some(SubdivisionRule::Grid { columns, rows }) => Some(view! {
+                        <p style="font-size: 0.8em">{format!("This NFT is divided into a {columns}×{rows} grid, your bid will snap to its cells.")}</p>
+                    }),
+                    _ => None,
+                }}
+                <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                    "Deadline (minutes):"
+                    <NumberInput min=0.0 get=deadline_minutes set=set_deadline_minutes placeholder="No deadline"/>
+                </Stack>
+                {move || bid_issue.get().map(|issue| match issue {
+                    BidIssue::Invalid(reason) => view! { <p style="color: red">{reason}</p> },
+                    BidIssue::Insufficient(reason) => view! {
+                        <p style="color: red">
+                            {reason}", "<a href=uniswap_link() target="_blank">"buy wOM"</a>" and unwrap it in your account"
+                        </p>
+                    },
+                    BidIssue::Warning(reason) => view! { <p style="color: orange">{reason}</p> },
+                })}
+                <Button on_click=move |_| set_show_calldata.update(|show| *show = !*show)>
+                    {move || if show_calldata.get() { "Hide calldata" } else { "Show calldata" }}
+                </Button>
+                <Show when=move || show_calldata.get()>
+                    {
+                        let calldata = calldata.clone();
+                        move || match calldata() {
+                            Some(calldata) => view! {
+                                <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                                    <code style="word-break: break-all; font-size: 0.8em">{calldata.clone()}</code>
+                                    <Button on_click=move |_| copy_to_clipboard(&calldata)>"Copy"</Button>
+                                </Stack>
+                            }.into_view(),
+                            None => view! { <p>"Connect your wallet to see the calldata"</p> }.into_view(),
+                        }
+                    }
+                </Show>
             </Stack>
-            <Button on_click=move |_| create_bid.dispatch(token.token_id)>"Bid"</Button>
+            <Button
+                on_click=move |_| submit_bid.dispatch(token_id())
+                disabled=Signal::derive(move || {
+                    submit_bid.pending().get() || create_bid.pending().get()
+                        || matches!(bid_issue.get(), Some(BidIssue::Invalid(_) | BidIssue::Insufficient(_)))
+                })
+            >
+                "Bid"
+            </Button>
+            {move || bid_stage.get().map(|stage| match stage {
+                TxStage::Submitted(transaction_hash) => format!("Bid {transaction_hash:?} submitted, waiting for confirmation"),
+                TxStage::Confirmed => "Bid confirmed".into(),
+                TxStage::Failed => "Bid failed".into(),
+            })}
         </Stack>
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn valid_bid_has_no_issue() {
+        assert!(validate_bid(1.0, 2.0, 1.5, 10.0).is_none());
+        assert!(validate_bid(1.0, 1.0, 1.0, 1.0).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn balance_below_the_minimum_price_is_insufficient() {
+        assert!(matches!(validate_bid(1.0, 2.0, 1.0, 0.5), Some(BidIssue::Insufficient(_))));
+        assert!(!matches!(validate_bid(1.0, 2.0, 1.0, 1.0), Some(BidIssue::Insufficient(_))));
+    }
+
+    #[wasm_bindgen_test]
+    fn amounts_below_the_minimum_price_are_invalid() {
+        assert!(matches!(validate_bid(1.0, 0.5, 1.0, 10.0), Some(BidIssue::Invalid(_))));
+        assert!(matches!(validate_bid(1.0, 2.0, 0.5, 10.0), Some(BidIssue::Invalid(_))));
+    }
+
+    #[wasm_bindgen_test]
+    fn bids_have_no_deadline_unless_one_is_set() {
+        assert!(bid_deadline(0.0).is_none());
+        let deadline = bid_deadline(5.0).unwrap();
+        assert!(!deadline.expired());
+        assert!(deadline.remaining() <= Duration::from_secs(300));
+    }
+
+    #[wasm_bindgen_test]
+    fn minimum_price_above_the_bid_is_a_warning() {
+        assert!(matches!(validate_bid(1.0, 2.0, 3.0, 10.0), Some(BidIssue::Warning(_))));
+    }
+
+    #[wasm_bindgen_test]
+    fn amounts_start_at_the_minimum_price_of_the_same_snapshot() {
+        let runtime = create_runtime();
+        let token = create_rw_signal((2, 1.5));
+        let snapshot = create_memo(move |_| token.get());
+        let (bid_amount, bids_minimum_price) = bid_amounts(snapshot);
+        assert_eq!(bid_amount.get_untracked(), snapshot.get_untracked().1);
+        assert_eq!(bids_minimum_price.get_untracked(), snapshot.get_untracked().1);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn amounts_are_reset_when_the_token_changes() {
+        let runtime = create_runtime();
+        let token = create_rw_signal((2, 1.5));
+        let snapshot = create_memo(move |_| token.get());
+        let (bid_amount, bids_minimum_price) = bid_amounts(snapshot);
+
+        bid_amount.set(4.0);
+        bids_minimum_price.set(3.0);
+        token.set((2, 1.5));
+        assert_eq!((bid_amount.get_untracked(), bids_minimum_price.get_untracked()), (4.0, 3.0));
+
+        token.set((3, 2.5));
+        assert_eq!((bid_amount.get_untracked(), bids_minimum_price.get_untracked()), (2.5, 2.5));
+
+        bid_amount.set(4.0);
+        token.set((3, 2.0));
+        assert_eq!((bid_amount.get_untracked(), bids_minimum_price.get_untracked()), (2.0, 2.0));
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn bidding_on_the_root_is_disabled_unless_allowed() {
+        assert!(matches!(validate_token(ROOT_TOKEN_ID, false), Some(BidIssue::Invalid(_))));
+        assert!(validate_token(ROOT_TOKEN_ID, true).is_none());
+        assert!(validate_token(ROOT_TOKEN_ID + 1, false).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn steps_move_the_bid_by_whole_increments() {
+        let increment = bid_increment();
+        assert!((step_bid(1.0, 1, 0.0) - (1.0 + increment)).abs() < 1e-9);
+        assert!((step_bid(1.0, -1, 0.0) - (1.0 - increment).max(0.0)).abs() < 1e-9);
+        let stepped = (0..10).fold(0.0, |amount, _| step_bid(amount, 1, 0.0));
+        assert_eq!(stepped, ((10.0 * increment) * 1e9).round() / 1e9);
+    }
+
+    #[wasm_bindgen_test]
+    fn steps_never_go_below_the_minimum_price() {
+        assert_eq!(step_bid(1.0, -100, 0.5), 0.5);
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::{
+    evm::types::Field,
+    state::State,
+};
+
+
+const VIEW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Allowed difference between the view and a bid's field, as a fraction of the view's width.
+const FIELD_TOLERANCE: f64 = 0.001;
+
+
+/// Tells the user when the current view coincides with the field of an open bid.
+#[component]
+pub fn BidMatch() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let matched_bid = create_rw_signal(None::<u128>);
+
+    // the view is not reactive, so it has to be polled
+    if let Ok(handle) = set_interval_with_handle({
+        let mandelbrot = state.mandelbrot.clone();
+        let bids = state.explorer.bids;
+        move || {
+            let bounds = mandelbrot.lock().unwrap().sample.borrow().get_bounds();
+            let view = Field {
+                x_min: bounds.x_min,
+                y_min: bounds.y_min,
+                x_max: bounds.x_max,
+                y_max: bounds.y_max,
+            };
+            let bid_id = bids.with_untracked(|bids| {
+                bids.values().find(|bid| view.approx_eq(&bid.field, FIELD_TOLERANCE)).map(|bid| bid.token_id)
+            });
+            if matched_bid.get_untracked() != bid_id {
+                matched_bid.set(bid_id);
+            }
+        }
+    }, VIEW_POLL_INTERVAL) {
+        on_cleanup(move || handle.clear());
+    }
+
+    let select_bid = move |bid_id| {
+        state.explorer.bids.update(|bids| {
+            if let Some(bid) = bids.get_mut(&bid_id) {
+                bid.selected = true;
+            }
+        });
+    };
+
+    move || matched_bid.get().map(|bid_id| view! {
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+            <p>{format!("You're viewing the region of open bid {bid_id}")}</p>
+            {(!state.spectator).then(|| view! {
+                <Button on_click=move |_| select_bid(bid_id) size=ButtonSize::Small>"Select"</Button>
+            })}
+        </Stack>
+    })
+}
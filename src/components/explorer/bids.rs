@@ -1,15 +1,31 @@
-use std::collections::HashMap;
+use std::{cell::Cell, collections::HashMap};
 
 use leptonic::prelude::*;
 use leptos::*;
 use mandelbrot_explorer::FrameColor;
+use web3::types::Address;
 
 use crate::{
-    evm::types::Metadata,
-    state::State,
+    components::confirm::ConfirmModal,
+    evm::{contracts::ERC1155Contract, types::{Metadata, OMAmount}},
+    state::{remove_bid_while, LedgerAction, State},
+    util::format_amount,
 };
 
 
+fn own_bids(bids: &HashMap<u128, Metadata>, address: Option<Address>) -> Vec<Metadata> {
+    bids.values().filter(|bid| Some(bid.owner) == address).cloned().collect()
+}
+
+/// Delete the bids one after the other, carrying on past failures, and report whether each one was withdrawn.
+async fn withdraw_bids(erc1155_contract: &ERC1155Contract, address: Address, bids: &[Metadata], report: impl Fn(&Metadata, bool)) {
+    for bid in bids {
+        let withdrawn = erc1155_contract.delete_bid(address, bid.token_id).await.is_some();
+        report(bid, withdrawn);
+    }
+}
+
+
 #[component]
 pub fn Bids(
     bids: RwSignal<HashMap<u128, Metadata>>,
@@ -17,22 +33,121 @@ pub fn Bids(
     let state = use_context::<State>().unwrap();
 
     let zoom_bid = {
+        let mandelbrot = state.mandelbrot.clone();
         move |bid_id| {
             if let Some(bid) = bids.get().get(&bid_id) {
                 let frame = bid.to_frame(FrameColor::Blue);
-                state.mandelbrot.lock().unwrap().move_into_bounds(&frame.bounds)
+                mandelbrot.lock().unwrap().move_into_bounds(&frame.bounds)
+            }
+        }
+    };
+
+    let own_bids = move || {
+        let address = state.address.get();
+        bids.with(|bids| own_bids(bids, address))
+    };
+
+    // outcome of every withdrawal, in the order they were attempted
+    let withdrawals = create_rw_signal(Vec::<(u128, bool)>::new());
+    let withdraw_all = create_action({
+        let state = state.clone();
+        move |_| {
+            let state = state.clone();
+            async move {
+                let Some(address) = state.sender().await else {
+                    return
+                };
+                let own_bids = own_bids();
+                withdrawals.set(Vec::new());
+                let before = state.erc1155_contract.get_OM_balance(address).await.ok();
+                let withdrawn_amount = Cell::new(0.0);
+                withdraw_bids(&state.erc1155_contract, address, &own_bids, |bid, withdrawn| {
+                    if withdrawn {
+                        state.ledger.record(LedgerAction::BidDeletion, Some(bid.token_id), bid.locked_OM.to_ether());
+                        withdrawn_amount.set(withdrawn_amount.get() + bid.locked_OM.to_ether());
+                    }
+                    withdrawals.update(|withdrawals| withdrawals.push((bid.token_id, withdrawn)));
+                }).await;
+                if let Some(bid) = own_bids.first() {
+                    state.reload_bids(bid.parent_id).await;
+                }
+                state.show_OM_change(address, before, withdrawn_amount.get()).await;
+                state.refresh_OM_balance();
+            }
+        }
+    });
+
+    let withdraw_bid = create_action({
+        let state = state.clone();
+        move |bid: &Metadata| {
+            let state = state.clone();
+            let bid = bid.clone();
+            async move {
+                let Some(address) = state.sender().await else {
+                    return
+                };
+                let before = state.erc1155_contract.get_OM_balance(address).await.ok();
+                let withdrawn = remove_bid_while(bid.token_id, &[bids], state.erc1155_contract.delete_bid(address, bid.token_id)).await;
+                if withdrawn.is_some() {
+                    state.ledger.record(LedgerAction::BidDeletion, Some(bid.token_id), bid.locked_OM.to_ether());
+                    state.show_OM_change(address, before, bid.locked_OM.to_ether()).await;
+                    state.refresh_OM_balance();
+                }
+                state.reload_bids(bid.parent_id).await;
+            }
+        }
+    });
+
+    let edited_bid = create_rw_signal(None::<Metadata>);
+    let (edited_amount, set_edited_amount) = create_signal(0.0);
+    let edit_bid = move |bid: Metadata| {
+        set_edited_amount.set(bid.locked_OM.to_ether());
+        edited_bid.set(Some(bid));
+    };
+    let increase_bid = {
+        let state = state.clone();
+        move || {
+            let state = state.clone();
+            async move {
+                let (Some(address), Some(bid)) = (state.sender().await, edited_bid.get_untracked()) else {
+                    return false
+                };
+                let amount = edited_amount.get_untracked();
+                let result = state.erc1155_contract.increase_bid(address, &bid, OMAmount::from_ether(amount)).await;
+                if result.is_some() {
+                    state.ledger.record(LedgerAction::BidDeletion, Some(bid.token_id), bid.locked_OM.to_ether());
+                    state.ledger.record(LedgerAction::Bid, Some(bid.parent_id), -amount);
+                }
+                state.reload_bids(bid.parent_id).await;
+                result.is_some()
             }
         }
     };
 
     let sorted_bids = create_memo(move |_| {
         let mut bids: Vec<Metadata> = bids.get().values().map(|bid| bid.clone()).collect();
-        bids.sort_by(|bid_a, bid_b| bid_b.locked_OM.partial_cmp(&bid_a.locked_OM).unwrap());
+        bids.sort_by(|bid_a, bid_b| bid_b.locked_OM.cmp(&bid_a.locked_OM));
         bids
     });
 
     view! {
         <p>"Bids:"</p>
+        <Show when=move || !state.spectator && !own_bids().is_empty() fallback=|| {}>
+            <Button on_click=move |_| withdraw_all.dispatch(()) disabled=Signal::derive(move || withdraw_all.pending().get())>
+                "Withdraw all my bids"
+            </Button>
+        </Show>
+        {move || {
+            let withdrawals = withdrawals.get();
+            (!withdrawals.is_empty()).then(|| view! {
+                <p style="font-size: 0.8em">
+                    {move || withdraw_all.pending().get().then(|| "Withdrawing: ")}
+                    {withdrawals.iter().map(|(bid_id, withdrawn)| {
+                        format!("{bid_id} {} ", if *withdrawn { "withdrawn" } else { "failed" })
+                    }).collect::<String>()}
+                </p>
+            })
+        }}
         <Box id="content">
             <For
                 each=move || sorted_bids.get()
@@ -40,12 +155,110 @@ pub fn Bids(
                 children={
                     move |bid| view! {
                         <p>
-                            {format!("{} {:?}", bid.locked_OM.to_string(), bid.owner)}
+                            {format!("{} {:?}", format_amount(bid.locked_OM.to_ether()), bid.owner)}
+                            <span style="font-size: 0.8em">{format!(" minimum bid price {} OM", format_amount(bid.minimum_price.to_ether()))}</span>
                             <Button on_click={let zoom_bid = zoom_bid.clone(); move |_| zoom_bid(bid.token_id)}>"Zoom"</Button>
+                            {
+                                let bid = bid.clone();
+                                move || (!state.spectator && Some(bid.owner) == state.address.get()).then(|| {
+                                    let bid = bid.clone();
+                                    view! {
+                                        <Button on_click={let bid = bid.clone(); move |_| edit_bid(bid.clone())}>"Edit bid"</Button>
+                                        <Button
+                                            on_click=move |_| withdraw_bid.dispatch(bid.clone())
+                                            disabled=Signal::derive(move || withdraw_bid.pending().get())
+                                        >
+                                            "Withdraw"
+                                        </Button>
+                                    }
+                                })
+                            }
                         </p>
                     }
                 }
             />
         </Box>
+        <ConfirmModal
+            show=Signal::derive(move || edited_bid.with(|bid| bid.is_some()))
+            title="Increase bid"
+            confirm_label="Increase"
+            on_confirm=increase_bid
+            on_close=move |_| edited_bid.set(None)
+        >
+            <p>"Your bid is withdrawn and placed again with the new amount, keeping its field and minimum bid price."</p>
+            {move || edited_bid.get().map(|bid| view! {
+                <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                    "New bid amount:"
+                    <NumberInput min=bid.locked_OM.to_ether() get=edited_amount set=set_edited_amount/>
+                </Stack>
+            })}
+        </ConfirmModal>
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use mandelbrot_explorer::BigFloat;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::evm::{mock::{confirmed_transaction, revert, selector, MockProvider}, types::Field};
+
+    fn bid(token_id: u128, owner: Address) -> Metadata {
+        Metadata {
+            token_id,
+            owner,
+            parent_id: 1,
+            field: Field {
+                x_min: BigFloat::from(0.0),
+                y_min: BigFloat::from(0.0),
+                x_max: BigFloat::from(0.1),
+                y_max: BigFloat::from(0.1),
+            },
+            locked_OM: OMAmount::from_ether(1.0),
+            minimum_price: OMAmount::default(),
+            layer: 2,
+            owned: false,
+            selected: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn only_the_bids_of_the_user_are_withdrawn() {
+        let (user, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let bids = HashMap::from([(3, bid(3, user)), (4, bid(4, other)), (5, bid(5, user))]);
+        let mut bid_ids: Vec<u128> = own_bids(&bids, Some(user)).iter().map(|bid| bid.token_id).collect();
+        bid_ids.sort();
+        assert_eq!(bid_ids, vec![3, 5]);
+        assert!(own_bids(&bids, None).is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn withdrawal_carries_on_past_a_failed_bid() {
+        let user = Address::repeat_byte(1);
+        // bid 3 was deleted in the meantime
+        let provider = MockProvider::new(|method, params| match method {
+            "eth_estimateGas" if params[0]["data"].as_str().unwrap().ends_with(&format!("{:064x}", 3)) => Err(revert("0x3f077648")),
+            _ => confirmed_transaction(method).unwrap(),
+        });
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new({
+            let errors = errors.clone();
+            move |error| errors.lock().unwrap().push(matches!(error, crate::evm::contracts::Error::BidNotFound))
+        }));
+
+        let outcomes = Mutex::new(Vec::new());
+        withdraw_bids(&erc1155_contract, user, &[bid(3, user), bid(5, user)], |bid, withdrawn| {
+            outcomes.lock().unwrap().push((bid.token_id, withdrawn));
+        }).await;
+        assert_eq!(*outcomes.lock().unwrap(), vec![(3, false), (5, true)]);
+        assert_eq!(*errors.lock().unwrap(), vec![true]);
+        let sent: Vec<String> = provider.params("eth_sendTransaction").iter()
+            .map(|params| params[0]["data"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(sent, vec![format!("{}{:064x}", selector("deleteBid"), 5)]);
     }
 }
@@ -0,0 +1,59 @@
+use leptonic::prelude::*;
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    evm::types::Metadata,
+    state::State,
+    util::preserve_query,
+};
+
+
+#[component]
+pub fn Breadcrumb() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+
+    let expand = create_action({
+        let state = state.clone();
+        move |_| {
+            let state = state.clone();
+            async move {
+                state.explorer.expand_ancestry(&state.erc1155_contract).await;
+            }
+        }
+    });
+
+    let zoom_token = {
+        let mandelbrot = state.mandelbrot.clone();
+        let navigate = use_navigate();
+        move |token: &Metadata| {
+            mandelbrot.lock().unwrap().move_into_bounds(&token.to_frame(mandelbrot_explorer::FrameColor::Blue).bounds);
+            navigate(&preserve_query(format!("/tokens/{}", token.token_id)), Default::default());
+        }
+    };
+
+    view! {
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.3) style="flex-wrap: wrap">
+            {move || {
+                let elided_ancestors = state.explorer.elided_ancestors.get();
+                (elided_ancestors > 0).then(|| view! {
+                    <Button on_click=move |_| expand.dispatch(()) size=ButtonSize::Small>
+                        {format!("… {elided_ancestors} more ancestors")}
+                    </Button>
+                })
+            }}
+            <For
+                each=move || state.explorer.nav_history.get()
+                key=|token| token.token_id
+                children=move |token| {
+                    let zoom_token = zoom_token.clone();
+                    view! {
+                        <Button on_click=move |_| zoom_token(&token) size=ButtonSize::Small>
+                            {token.token_id}
+                        </Button>
+                    }
+                }
+            />
+        </Stack>
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use leptos::*;
+use mandelbrot_explorer::FrameColor;
+
+use crate::state::State;
+
+
+const DEBUG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+
+/// Part of the explorer interface state shown in the debug panel.
+#[derive(Clone, Default, PartialEq)]
+struct InterfaceSnapshot {
+    frames: Vec<(&'static str, usize)>,
+    bounds: [String; 4],
+    max_iterations: i32,
+    redraw: bool,
+    frame_event_callback: bool,
+}
+
+fn color_name(color: &FrameColor) -> &'static str {
+    match color {
+        FrameColor::Red => "Red",
+        FrameColor::Pink => "Pink",
+        FrameColor::Yellow => "Yellow",
+        FrameColor::Lemon => "Lemon",
+        FrameColor::Blue => "Blue",
+        FrameColor::LightBlue => "LightBlue",
+        FrameColor::Green => "Green",
+    }
+}
+
+fn snapshot(interface: &mandelbrot_explorer::Interface) -> InterfaceSnapshot {
+    let mut frames: Vec<(&'static str, usize)> = Vec::new();
+    for frame in &interface.frames {
+        let name = color_name(&frame.color);
+        match frames.iter_mut().find(|(name_, _)| *name_ == name) {
+            Some((_, count)) => *count += 1,
+            None => frames.push((name, 1)),
+        }
+    }
+    frames.sort();
+    let bounds = interface.sample.borrow().get_bounds();
+    InterfaceSnapshot {
+        frames,
+        bounds: [bounds.x_min.to_string(), bounds.x_max.to_string(), bounds.y_min.to_string(), bounds.y_max.to_string()],
+        max_iterations: interface.coloring.max_iterations,
+        redraw: interface.redraw.is_some(),
+        frame_event_callback: interface.frame_event_callback.is_some(),
+    }
+}
+
+fn describe(interface: &InterfaceSnapshot) -> String {
+    let frames = interface.frames.iter()
+        .map(|(color, count)| format!("{color}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let [x_min, x_max, y_min, y_max] = &interface.bounds;
+    format!(
+        "frames: {}\nx: {x_min} .. {x_max}\ny: {y_min} .. {y_max}\nmax iterations: {}\nredraw wired: {}\nframe event callback wired: {}",
+        if frames.is_empty() { "none".into() } else { frames },
+        interface.max_iterations,
+        interface.redraw,
+        interface.frame_event_callback,
+    )
+}
+
+
+/// Internal state of the explorer interface, shown with `?debug=true`.
+#[component]
+pub fn DebugPanel() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let (interface, set_interface) = create_signal(InterfaceSnapshot::default());
+
+    if let Ok(handle) = set_interval_with_handle({
+        let mandelbrot = state.mandelbrot.clone();
+        move || {
+            let snapshot = snapshot(&mandelbrot.lock().unwrap());
+            if interface.with_untracked(|interface| *interface != snapshot) {
+                set_interface.set(snapshot);
+            }
+        }
+    }, DEBUG_POLL_INTERVAL) {
+        on_cleanup(move || handle.clear());
+    }
+
+    view! {
+        <pre style="font-size: 0.8em">
+            {move || interface.with(describe)}
+        </pre>
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    use mandelbrot_explorer::{Bounds, Frame};
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    fn frame(id: u128, color: FrameColor) -> Frame {
+        Frame { id, bounds: Bounds { x_min: 0.0.into(), x_max: 1.0.into(), y_min: 0.0.into(), y_max: 1.0.into() }, color }
+    }
+
+    #[wasm_bindgen_test]
+    fn panel_reflects_the_interface_state() {
+        let mut interface = mandelbrot_explorer::Interface::new(
+            Rc::new(RefCell::new(mandelbrot_explorer::PerturbationEngine::new(16, 16))),
+            mandelbrot_explorer::Coloring { max_iterations: 1600, offset: 0.0, length: 360.0 },
+        );
+        interface.frames = vec![frame(2, FrameColor::Red), frame(1, FrameColor::Blue), frame(3, FrameColor::Red)];
+        interface.frame_event_callback = Some(Arc::new(|_| {}));
+
+        let snapshot = snapshot(&interface);
+        assert!(snapshot.frames == vec![("Blue", 1), ("Red", 2)]);
+        assert_eq!(snapshot.max_iterations, 1600);
+        assert!(snapshot.frame_event_callback);
+
+        let description = describe(&snapshot);
+        assert!(description.starts_with("frames: Blue: 1, Red: 2\n"));
+        assert!(description.contains("max iterations: 1600"));
+        assert!(description.contains("frame event callback wired: true"));
+    }
+}
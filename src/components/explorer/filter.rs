@@ -0,0 +1,25 @@
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::{components::state::OWNED_ONLY_KEY, state::State, util::store_local};
+
+
+#[component]
+pub fn OwnedFilter() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let owned_only = state.explorer.owned_only;
+
+    view! {
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+            <Toggle
+                state=owned_only
+                set_state=move |owned: bool| {
+                    owned_only.set(owned);
+                    store_local(OWNED_ONLY_KEY, &owned.to_string());
+                }
+                variant=ToggleVariant::Stationary
+            />
+            "Show only my NFTs"
+        </Stack>
+    }
+}
@@ -0,0 +1,78 @@
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::{
+    components::state::Web3,
+    evm::types::{node_history_export, Metadata},
+    state::State,
+};
+
+
+/// Blocks covered by default, roughly a week on Sepolia.
+const DEFAULT_HISTORY_BLOCKS: f64 = 50_000.0;
+
+
+/// Exports how the children and bids of a token developed over a range of blocks.
+#[component]
+pub fn HistoryExport(
+    token: Metadata,
+) -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let web3 = use_context::<Web3>().unwrap().0;
+
+    let (from_block, set_from_block) = create_signal(0.0);
+    let (to_block, set_to_block) = create_signal(0.0);
+    spawn_local(async move {
+        if let Ok(latest) = web3.eth().block_number().await {
+            let latest = latest.as_u64() as f64;
+            set_to_block.set(latest);
+            set_from_block.set((latest - DEFAULT_HISTORY_BLOCKS).max(0.0));
+        }
+    });
+
+    let export = create_action({
+        let token = token.clone();
+        move |_| {
+            let erc1155_contract = state.erc1155_contract.clone();
+            let token = token.clone();
+            async move {
+                let (from_block, to_block) = (from_block.get_untracked() as u64, to_block.get_untracked() as u64);
+                let snapshots = state.progress.track(
+                    erc1155_contract.get_node_history(token.token_id, from_block.into(), to_block.into())
+                ).await;
+                match snapshots {
+                    Ok(snapshots) => Ok((snapshots.len(), format!(
+                        "data:application/json;charset=utf-8,{}",
+                        String::from(js_sys::encode_uri_component(&node_history_export(&token, &snapshots).to_string())),
+                    ))),
+                    Err(error) => {
+                        log::warn!("Failed to read the history of {}: {}", token.token_id, error);
+                        Err("Unable to read the history, the RPC node may not keep state that old".to_string())
+                    }
+                }
+            }
+        }
+    });
+    let token_id = token.token_id;
+
+    view! {
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+            "Blocks"
+            <NumberInput min=0.0 get=from_block set=set_from_block/>
+            "to"
+            <NumberInput min=0.0 get=to_block set=set_to_block/>
+            <Button
+                on_click=move |_| export.dispatch(())
+                disabled=Signal::derive(move || export.pending().get() || from_block.get() > to_block.get())
+            >
+                "Export history"
+            </Button>
+        </Stack>
+        {move || export.value().get().map(|result| match result {
+            Ok((count, link)) => view! {
+                <p><a href=link download=format!("node-{token_id}-history.json")>{format!("Download {count} snapshots")}</a></p>
+            }.into_view(),
+            Err(message) => view! { <p>{message}</p> }.into_view(),
+        })}
+    }
+}
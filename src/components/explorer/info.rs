@@ -1,17 +1,187 @@
 use leptos::*;
+use wasm_bindgen_futures::JsFuture;
+use web3::types::Address;
 
-use crate::evm::types::Metadata;
+use crate::{
+    chain::{address_url, expected_chain, nft_url},
+    components::query::use_contract_query,
+    evm::types::{highest_bid, node_export, Metadata},
+    state::State,
+    util::format_amount,
+};
+
+
+#[component]
+fn AddressLabel(
+    address: Address,
+) -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+
+    let name = create_local_resource(move || address, move |address| {
+        let erc1155_contract = state.erc1155_contract.clone();
+        async move {
+            erc1155_contract.lookup_name(address).await
+        }
+    });
+
+    view! {
+        <a href=address_url(&expected_chain(), address).unwrap_or_default() target="_blank">
+            {move || name.get().flatten().map_or(address.to_string(), |name| format!("{name} ({address})"))}
+        </a>
+    }
+}
+
+
+/// Label, address and highlighting of the owner row and, once it is found, the creator row.
+/// The creator is highlighted when the NFT changed hands since it was minted.
+fn ownership_rows(owner: Address, creator: Option<Address>) -> Vec<(&'static str, Address, bool)> {
+    let mut rows = vec![("Owned by: ", owner, false)];
+    rows.extend(creator.map(|creator| ("Created by: ", creator, creator != owner)));
+    rows
+}
+
+/// Count of the open bids, nothing when there are none.
+fn open_bids_label(count: usize) -> Option<String> {
+    match count {
+        0 => None,
+        1 => Some("1 open bid".into()),
+        count => Some(format!("{count} open bids")),
+    }
+}
+
+
+#[component]
+fn Note(
+    token_id: u128,
+) -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let notes = state.notes;
+    let (import_result, set_import_result) = create_signal(None::<String>);
+
+    let export_link = move || format!(
+        "data:application/json;charset=utf-8,{}",
+        String::from(js_sys::encode_uri_component(&notes.export())),
+    );
+
+    let import = move |event: ev::Event| {
+        let input: web_sys::HtmlInputElement = event_target(&event);
+        if let Some(file) = input.files().and_then(|files| files.get(0)) {
+            spawn_local(async move {
+                let json = JsFuture::from(file.text()).await.ok().and_then(|json| json.as_string());
+                set_import_result.set(Some(match json.map(|json| notes.import(&json)) {
+                    Some(Ok(count)) => format!("Imported {count} notes"),
+                    _ => "Unable to import notes from this file".into(),
+                }));
+            });
+        }
+    };
+
+    view! {
+        <p>"Note:"</p>
+        <textarea
+            style="width: 100%"
+            placeholder="Notes are kept in this browser only"
+            prop:value=move || notes.get(token_id).unwrap_or_default()
+            on:change=move |event| notes.set(token_id, event_target_value(&event))
+        />
+        <p style="font-size: 0.8em">
+            <a href=export_link download="notes.json">"Export notes"</a>
+            " | "
+            <label>"Import notes "<input type="file" accept="application/json" on:change=import/></label>
+            {move || import_result.get().map(|result| format!(" {result}"))}
+        </p>
+    }
+}
 
 
 #[component]
 pub fn Info(
     token: Metadata,
 ) -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let (token_id, owner) = (token.token_id, token.owner);
+
+    let creator = use_contract_query(move || token_id, |erc1155_contract, token_id| async move {
+        erc1155_contract.get_creator(token_id).await
+    }).data;
+
+    let metadata_uri = use_contract_query(move || (token_id, state.features.get().metadata_uri), |erc1155_contract, (token_id, supported)| async move {
+        if supported {
+            // ERC1155 clients substitute the id in its hex form
+            Ok(Some(erc1155_contract.get_uri(token_id).await?.replace("{id}", &format!("{:064x}", token_id))))
+        } else {
+            Ok(None)
+        }
+    }).data;
+
+    let export_link = {
+        let token = token.clone();
+        move || {
+            let mut children: Vec<_> = state.explorer.children.with(|children| children.values().cloned().collect());
+            let mut bids: Vec<_> = state.explorer.bids.with(|bids| bids.values().cloned().collect());
+            children.sort_by_key(|token| token.token_id);
+            bids.sort_by_key(|bid| bid.token_id);
+            format!(
+                "data:application/json;charset=utf-8,{}",
+                String::from(js_sys::encode_uri_component(&node_export(&token, &children, &bids).to_string())),
+            )
+        }
+    };
 
     view! {
-        <p>{format!("NFT id: {}", token.token_id)}</p>
-        <p>{format!("Owner: {}", token.owner)}</p>
-        <p>{format!("Locked OM: {}", token.locked_OM)}</p>
-        <p>{format!("Minimum bid: {}", token.minimum_price)}</p>
+        <p>
+            "NFT id: "
+            <a href=nft_url(&expected_chain(), state.erc1155_contract.address(), token_id).unwrap_or_default() target="_blank">
+                {token_id.to_string()}
+            </a>
+            {move || state.notes.get(token_id).map(|_| " 📝")}
+        </p>
+        {move || match state.explorer.bids.with(|bids| highest_bid(bids.values()).map(|bid| (bid.locked_OM, bid.owner))) {
+            Some((amount, bidder)) => view! {
+                <p><strong>{format!("Highest bid: {} OM by ", format_amount(amount.to_ether()))}<AddressLabel address=bidder/></strong></p>
+            }.into_view(),
+            None => view! { <p>"No bids yet"</p> }.into_view(),
+        }}
+        {move || open_bids_label(state.explorer.bids.with(|bids| bids.len())).map(|label| view! { <p>{label}</p> })}
+        {move || ownership_rows(owner, creator.get().flatten()).into_iter().map(|(label, address, highlighted)| view! {
+            <p style:color=if highlighted { "var(--brand-color)" } else { "inherit" }>
+                {label}<AddressLabel address/>
+            </p>
+        }).collect_view()}
+        <p>{format!("Locked OM: {}", format_amount(token.locked_OM.to_ether()))}</p>
+        <p>{format!("Minimum bid: {}", format_amount(token.minimum_price.to_ether()))}</p>
+        {move || metadata_uri.get().flatten().filter(|uri| !uri.is_empty()).map(|uri| view! {
+            <p><a href=uri target="_blank">"Metadata"</a></p>
+        })}
+        <p><a href=export_link download=format!("node-{token_id}.json")>"Export node JSON"</a></p>
+        <Note token_id/>
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn creator_row_follows_the_owner_row() {
+        let (owner, creator) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        assert_eq!(ownership_rows(owner, Some(creator)), vec![("Owned by: ", owner, false), ("Created by: ", creator, true)]);
+        assert_eq!(ownership_rows(owner, Some(owner)), vec![("Owned by: ", owner, false), ("Created by: ", owner, false)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn creator_row_is_left_out_until_found() {
+        let owner = Address::repeat_byte(1);
+        assert_eq!(ownership_rows(owner, None), vec![("Owned by: ", owner, false)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn open_bids_label_reflects_the_count() {
+        assert_eq!(open_bids_label(0), None);
+        assert_eq!(open_bids_label(1).as_deref(), Some("1 open bid"));
+        assert_eq!(open_bids_label(3).as_deref(), Some("3 open bids"));
     }
 }
@@ -0,0 +1,108 @@
+use leptonic::prelude::*;
+use leptos::*;
+use leptos_router::*;
+use mandelbrot_explorer::{BigFloat, Bounds, Radix};
+
+use crate::{state::State, util::copy_to_clipboard};
+
+
+const BOUNDS_PARAMS: [&str; 4] = ["x_min", "x_max", "y_min", "y_max"];
+
+
+/// View requested with `?x_min=..&x_max=..&y_min=..&y_max=..`, ignored unless all four are valid numbers
+/// describing a non-empty view.
+pub fn linked_bounds() -> Option<Bounds> {
+    parse_bounds(&use_query_map().get_untracked())
+}
+
+fn parse_bounds(query_map: &ParamsMap) -> Option<Bounds> {
+    let mut values = Vec::with_capacity(BOUNDS_PARAMS.len());
+    for key in BOUNDS_PARAMS {
+        let value = query_map.get(key)?.trim();
+        if !value.parse::<f64>().is_ok_and(f64::is_finite) {
+            return None
+        }
+        values.push(BigFloat::parse(value, Radix::Dec));
+    }
+    let [x_min, x_max, y_min, y_max]: [BigFloat; 4] = values.try_into().ok()?;
+    (x_min < x_max && y_min < y_max).then_some(Bounds { x_min, x_max, y_min, y_max })
+}
+
+/// Query that `linked_bounds` reads back as `bounds`.
+fn bounds_query(bounds: &Bounds) -> String {
+    let query: Vec<String> = BOUNDS_PARAMS.iter()
+        .zip([&bounds.x_min, &bounds.x_max, &bounds.y_min, &bounds.y_max])
+        .map(|(key, value)| format!("{key}={}", String::from(js_sys::encode_uri_component(&value.to_string()))))
+        .collect();
+    query.join("&")
+}
+
+/// Link to the current page that reopens the explorer at `bounds`.
+fn view_link(bounds: &Bounds) -> String {
+    let location = web_sys::window().unwrap().location();
+    let origin = location.origin().unwrap_or_default();
+    let path = location.pathname().unwrap_or_default();
+    format!("{origin}{path}?{}", bounds_query(bounds))
+}
+
+
+#[component]
+pub fn CopyViewLink() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let (copied, set_copied) = create_signal(false);
+
+    let copy = move |_| {
+        let bounds = state.mandelbrot.lock().unwrap().sample.borrow().get_bounds();
+        copy_to_clipboard(&view_link(&bounds));
+        set_copied.set(true);
+    };
+
+    view! {
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+            <Button on_click=copy>"Copy link to this view"</Button>
+            {move || copied.get().then_some("Copied")}
+        </Stack>
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    fn query_map(query: &str) -> ParamsMap {
+        let mut query_map = ParamsMap::new();
+        for pair in query.split('&') {
+            if let Some((key, value)) = pair.split_once('=') {
+                query_map.insert(key.to_string(), String::from(js_sys::decode_uri_component(value).unwrap()));
+            }
+        }
+        query_map
+    }
+
+    #[wasm_bindgen_test]
+    fn bounds_round_trip_through_the_query() {
+        let bounds = Bounds {
+            x_min: BigFloat::parse("-0.7436447860", Radix::Dec),
+            x_max: BigFloat::parse("-0.7436438870", Radix::Dec),
+            y_min: BigFloat::parse("0.1318259042", Radix::Dec),
+            y_max: BigFloat::parse("0.1318268032", Radix::Dec),
+        };
+        let parsed = parse_bounds(&query_map(&bounds_query(&bounds))).unwrap();
+        assert_eq!(
+            [parsed.x_min, parsed.x_max, parsed.y_min, parsed.y_max].map(|value| value.to_string()),
+            [bounds.x_min, bounds.x_max, bounds.y_min, bounds.y_max].map(|value| value.to_string()),
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn malformed_bounds_are_ignored() {
+        assert!(parse_bounds(&query_map("x_min=0&x_max=1&y_min=0")).is_none());
+        assert!(parse_bounds(&query_map("x_min=0&x_max=abc&y_min=0&y_max=1")).is_none());
+        assert!(parse_bounds(&query_map("x_min=0&x_max=inf&y_min=0&y_max=1")).is_none());
+        assert!(parse_bounds(&query_map("x_min=1&x_max=0&y_min=0&y_max=1")).is_none());
+        assert!(parse_bounds(&query_map("x_min=0&x_max=1&y_min=0&y_max=1")).is_some());
+    }
+}
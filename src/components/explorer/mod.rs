@@ -1,22 +1,47 @@
 mod auction;
+mod bid_match;
 mod bids;
+mod breadcrumb;
+mod debug;
+mod filter;
+mod history;
 mod info;
+mod link;
+mod owner;
+mod position;
+mod selection;
+mod shortcuts;
+mod siblings;
 mod visuals;
 
 use std::sync::Arc;
 
+use futures::future::{abortable, AbortHandle};
 use leptonic::prelude::*;
 use leptos::*;
 use leptos_router::*;
 
 use crate::{
-    state::State,
-    util::preserve_log_level,
+    components::mandelbrot::request_redraw,
+    evm::types::{default_token_id, ROOT_TOKEN_ID},
+    state::{Progress, State},
+    util::{format_amount, is_debug, load_local, preserve_query, store_local},
 };
 use {
     auction::Auction,
+    bid_match::BidMatch,
     bids::Bids,
+    breadcrumb::Breadcrumb,
+    debug::DebugPanel,
+    filter::OwnedFilter,
+    history::HistoryExport,
     info::Info,
+    link::{linked_bounds, CopyViewLink},
+    owner::OwnerActions,
+    position::Position,
+    selection::{collect_frames, pick_frame, selection, shown_children, Selection},
+    shortcuts::{shortcut, Shortcut, ShortcutsHelp},
+    siblings::Siblings,
     visuals::Visuals,
 };
 
@@ -33,6 +58,15 @@ pub fn Explorer() -> impl IntoView {
 }
 
 
+const LAST_TOKEN_KEY: &str = "last_token_id";
+
+
+/// Token explored when the page was last left, shown again when the URL doesn't point to one.
+fn last_token_id() -> Option<u128> {
+    load_local(LAST_TOKEN_KEY)?.parse().ok()
+}
+
+
 #[derive(Clone, Params, PartialEq)]
 struct ControllerParams {
     token_id: Option<u128>
@@ -53,41 +87,96 @@ fn Controller() -> impl IntoView {
         }
     };
 
-    // query tokens and bids
+    let refresh = create_trigger();
+    // own fetch of the explored token, other fetches in progress don't hold back a refresh
+    let fetching = Progress::new();
+    // re-read everything from the chain, for data changed by transactions the app doesn't know about
+    let resync = {
+        let cache = state.explorer.cache.clone();
+        move || {
+            cache.clear();
+            refresh.notify();
+        }
+    };
+
+    // query tokens and bids, a fetch superseded by the next one is dropped along with its requests, the HTTP
+    // transport then aborts their `fetch` through its `AbortController` while wallet requests, which can't be
+    // aborted, still run but whatever they return is discarded
     create_effect({
         let state = state.clone();
         let token_id = token_id.clone();
-        move |_| {
+        move |previous: Option<AbortHandle>| {
+            refresh.track();
+            if let Some(previous) = previous {
+                previous.abort();
+            }
             let state = state.clone();
-            let token_id = token_id().unwrap_or(1);
+            let token_id = token_id().or_else(last_token_id).unwrap_or_else(default_token_id);
+            let (fetch, abort_handle) = abortable(async move {
+                if state.explorer.load(&state.erc1155_contract, state.progress, token_id).await.is_ok() {
+                    store_local(LAST_TOKEN_KEY, &token_id.to_string());
+                } else {
+                    // also covers a misconfigured default token and a last token that was burned since
+                    use_navigate()(&preserve_query(format!("/tokens/{}", ROOT_TOKEN_ID)), Default::default());
+                }
+            });
             spawn_local(async move {
-                if let (Ok(tokens), Ok(children), Ok(bids)) = (
-                    state.erc1155_contract.get_ancestry_metadata(token_id).await,
-                    state.erc1155_contract.get_children_metadata(token_id).await,
-                    state.erc1155_contract.get_bids(token_id).await
-                ) {
+                let _ = fetching.track(fetch).await;
+            });
+            abort_handle
+        }
+    });
+
+    // children minted while the token is explored are added as they appear, there is no websocket transport
+    // to subscribe with, so the watch polls the node until the user navigates away
+    let watch = store_value(None::<AbortHandle>);
+    create_effect({
+        let state = state.clone();
+        let token_id = token_id.clone();
+        move |_| {
+            let token_id = token_id().or_else(last_token_id).unwrap_or_else(default_token_id);
+            if let Some(previous) = watch.get_value() {
+                previous.abort();
+            }
+            let explorer = state.explorer.clone();
+            let erc1155_contract = state.erc1155_contract.clone();
+            let (task, abort_handle) = abortable(async move {
+                erc1155_contract.watch_children(token_id, move |minted| {
+                    explorer.cache.invalidate(token_id);
                     batch(|| {
-                        state.explorer.nav_history.update(|nav_history| {
-                            nav_history.clear();
-                            nav_history.extend(tokens.into_iter().rev());
-                        });
-                        state.explorer.children.update(|children_| {
-                            children_.clear();
-                            children_.extend(children.into_iter().map(|m| (m.token_id, m)));
-                        });
-                        state.explorer.bids.update(|bids_| {
-                            bids_.clear();
-                            bids_.extend(bids.into_iter().map(|bid| (bid.token_id, bid)));
+                        // an approved bid is minted under the same id
+                        explorer.bids.update(|bids| bids.retain(|bid_id, _| !minted.iter().any(|token| token.token_id == *bid_id)));
+                        explorer.children.update(|children| {
+                            for token in minted {
+                                children.entry(token.token_id).or_insert(token);
+                            }
                         });
                     });
-                } else {
-                    use_navigate()(&preserve_log_level("/tokens/1".into()), Default::default());
-                }
+                }).await
             });
+            spawn_local(async move {
+                let _ = task.await;
+            });
+            watch.set_value(Some(abort_handle));
+        }
+    });
+    on_cleanup(move || {
+        if let Some(watch) = watch.get_value() {
+            watch.abort();
+        }
+    });
+
+    create_effect({
+        let state = state.clone();
+        move |_| {
+            if state.address.get().is_some() {
+                state.refresh_OM_balance();
+            }
         }
     });
 
     let first = store_value(true);
+    let linked_bounds = store_value(linked_bounds());
     create_effect({
         let state = state.clone();
         move |_| {
@@ -95,48 +184,63 @@ fn Controller() -> impl IntoView {
                 state.explorer.nav_history.with(|nav_history| {
                     if let Some(token) = nav_history.last() {
                         first.set_value(false);
-                        state.mandelbrot.lock().unwrap().move_into_bounds(&token.to_frame(mandelbrot_explorer::FrameColor::Blue).bounds);
+                        let bounds = linked_bounds.get_value()
+                            .unwrap_or_else(|| token.to_frame(mandelbrot_explorer::FrameColor::Blue).bounds);
+                        state.mandelbrot.lock().unwrap().move_into_bounds(&bounds);
                     }
                 });
             }
         }
     });
 
+    let owned_selection = create_rw_signal(None);
     let on_frame_event = Arc::new({
         let state = state.clone();
         move |frame_event: mandelbrot_explorer::FrameEvent| {
             match frame_event {
                 mandelbrot_explorer::FrameEvent::DoubleClicked(frame) => {
-                    match frame.color {
-                        mandelbrot_explorer::FrameColor::Red |
-                        mandelbrot_explorer::FrameColor::Pink |
-                        mandelbrot_explorer::FrameColor::Blue |
-                        mandelbrot_explorer::FrameColor::LightBlue => {
+                    // prefer the innermost frame near the pointer over the one hit exactly
+                    let frame = state.pointer.get_value().and_then(|pointer| {
+                        let mandelbrot = state.mandelbrot.lock().unwrap();
+                        let view = mandelbrot.sample.borrow().get_bounds();
+                        pick_frame(&mandelbrot.frames, &view, pointer, state.explorer.frame_tolerance.get_untracked())
+                    }).unwrap_or(frame);
+                    match selection(&frame.color, state.spectator) {
+                        Selection::Navigate => {
+                            state.mandelbrot.lock().unwrap().move_into_bounds(&frame.bounds);
+                            navigate(&preserve_query(format!("/tokens/{}", frame.id)), Default::default());
+                        }
+                        Selection::Own => {
                             state.mandelbrot.lock().unwrap().move_into_bounds(&frame.bounds);
-                            navigate(&preserve_log_level(format!("/tokens/{}", frame.id)), Default::default());
+                            navigate(&preserve_query(format!("/tokens/{}", frame.id)), Default::default());
+                            let token = state.explorer.children.with_untracked(|children| children.get(&frame.id).cloned())
+                                .or_else(|| state.explorer.nav_history.with_untracked(|nav_history| {
+                                    nav_history.iter().find(|token| token.token_id == frame.id).cloned()
+                                }));
+                            owned_selection.set(token);
                         }
-                        mandelbrot_explorer::FrameColor::Yellow |
-                        mandelbrot_explorer::FrameColor::Lemon => {
+                        Selection::SelectBid => {
                             state.explorer.bids.update(|bids| {
                                 if let Some(bid) = bids.get_mut(&frame.id) {
                                     bid.selected = true;
                                 }
                             });
                         }
-                        mandelbrot_explorer::FrameColor::Green => {
+                        Selection::DeselectBid => {
                             state.explorer.bids.update(|bids| {
                                 if let Some(bid) = bids.get_mut(&frame.id) {
                                     bid.selected = false;
                                 }
                             });
                         }
+                        Selection::Ignore => {}
                     }
                 }
                 mandelbrot_explorer::FrameEvent::Entered(frame) => {
                     match frame.color {
                         mandelbrot_explorer::FrameColor::Red |
                         mandelbrot_explorer::FrameColor::Pink => {
-                            navigate(&preserve_log_level(format!("/tokens/{}", frame.id)), Default::default());
+                            navigate(&preserve_query(format!("/tokens/{}", frame.id)), Default::default());
                         }
                         _ => {}
                     }
@@ -151,6 +255,39 @@ fn Controller() -> impl IntoView {
         move |frame_event| on_frame_event(frame_event)
     }));
 
+    // keyboard shortcuts
+    let bid_focus = create_rw_signal(false);
+    let show_shortcuts = create_rw_signal(false);
+    if !state.spectator {
+        let handle = window_event_listener(ev::keydown, {
+            let state = state.clone();
+            let navigate = use_navigate();
+            let resync = resync.clone();
+            move |event| {
+                match shortcut(&event) {
+                    Some(Shortcut::Bid) => bid_focus.set(true),
+                    Some(Shortcut::Up) => {
+                        if let Some(parent) = state.explorer.parent() {
+                            state.mandelbrot.lock().unwrap().move_into_bounds(&parent.to_frame(mandelbrot_explorer::FrameColor::Blue).bounds);
+                            navigate(&preserve_query(format!("/tokens/{}", parent.token_id)), Default::default());
+                        }
+                    }
+                    Some(Shortcut::Root) => {
+                        if let Some(root) = state.explorer.root() {
+                            state.mandelbrot.lock().unwrap().move_into_bounds(&root.to_frame(mandelbrot_explorer::FrameColor::Blue).bounds);
+                        }
+                        navigate(&preserve_query(format!("/tokens/{}", ROOT_TOKEN_ID)), Default::default());
+                    }
+                    Some(Shortcut::Refresh) => resync(),
+                    Some(Shortcut::Help) => show_shortcuts.update(|show| *show = !*show),
+                    None => return,
+                }
+                event.prevent_default();
+            }
+        });
+        on_cleanup(move || handle.remove());
+    }
+
     // check ownership
     create_effect(move |_| {
         batch(move || {
@@ -177,13 +314,13 @@ fn Controller() -> impl IntoView {
         move |_| {
             let mandelbrot = &mut state.mandelbrot.lock().unwrap();
             let frames = &mut mandelbrot.frames;
-            frames.clear();
-            frames.extend(state.explorer.children.get().values().map(|token| token.to_frame(mandelbrot_explorer::FrameColor::Red)));
-            frames.extend(state.explorer.bids.get().values().map(|token| token.to_frame(mandelbrot_explorer::FrameColor::Yellow)));
-            frames.extend(state.explorer.nav_history.get().iter().rev().map(|token| token.to_frame(mandelbrot_explorer::FrameColor::Blue)));
-            if let Some(redraw) = &mandelbrot.redraw {
-                redraw();
-            }
+            let owned_only = state.explorer.owned_only.get();
+            *frames = collect_frames(
+                &state.explorer.nav_history.get(),
+                &shown_children(state.explorer.children.get().into_values(), owned_only),
+                &state.explorer.bids.get().into_values().collect::<Vec<_>>(),
+            );
+            request_redraw(mandelbrot);
         }
     });
 
@@ -214,18 +351,38 @@ fn Controller() -> impl IntoView {
     // });
 
     view! {
+        <ShortcutsHelp show=show_shortcuts/>
+        <OwnerActions token=owned_selection/>
         <Visuals/>
+        {is_debug().then(|| view! { <DebugPanel/> })}
+        <Breadcrumb/>
+        <Siblings/>
+        <BidMatch/>
+        <Position/>
+        <OwnedFilter/>
+        <CopyViewLink/>
+        <Button on_click=move |_| resync() disabled=Signal::derive(move || fetching.fraction().is_some())>
+            {move || if fetching.fraction().is_some() { "Refreshing..." } else { "Refresh" }}
+        </Button>
+        <p>
+            "OM balance: "
+            {
+                let (address, OM_balance) = (state.address, state.OM_balance);
+                move || address.get().map_or("-".into(), |_| format_amount(OM_balance.get()))
+            }
+        </p>
         {
             move || if let Some(token) = state.explorer.nav_history.get().last().cloned() {
                 let state = state.clone();
                 view! {
                     <Info token=token.clone()/>
-                    <Show when=move || state.address.get().is_some() fallback=|| {}>
+                    <HistoryExport token=token.clone()/>
+                    <Show when=move || state.app_state.get().address().is_some() fallback=|| {}>
                         {
                             let token= token.clone();
                             view! {
                                 <Separator/>
-                                <Auction token/>
+                                <Auction token focus=bid_focus/>
                             }
                         }
                     </Show>
@@ -242,3 +399,21 @@ fn Controller() -> impl IntoView {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn last_token_is_restored_from_local_storage() {
+        let previous = load_local(LAST_TOKEN_KEY);
+        store_local(LAST_TOKEN_KEY, &12345678901234567890u128.to_string());
+        assert_eq!(last_token_id(), Some(12345678901234567890));
+        store_local(LAST_TOKEN_KEY, "not a token");
+        assert_eq!(last_token_id(), None);
+        store_local(LAST_TOKEN_KEY, &previous.unwrap_or_default());
+    }
+}
@@ -0,0 +1,53 @@
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::{components::confirm::ConfirmModal, evm::types::Metadata, state::State, util::format_amount};
+
+
+/// Actions offered right away when the user selects one of their own NFTs in the explorer.
+#[component]
+pub fn OwnerActions(
+    token: RwSignal<Option<Metadata>>,
+) -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+
+    let (bids_minimum_price, set_bids_minimum_price) = create_signal(0.0);
+    create_effect(move |_| {
+        if let Some(token) = token.get() {
+            set_bids_minimum_price.set(token.minimum_price.to_ether());
+        }
+    });
+
+    let set_minimum_bid = {
+        let state = state.clone();
+        move || {
+            let state = state.clone();
+            async move {
+                if let (Some(address), Some(token)) = (state.sender().await, token.get_untracked()) {
+                    state.erc1155_contract.set_minimum_bid(address, token.token_id, bids_minimum_price.get_untracked()).await.is_some()
+                } else {
+                    false
+                }
+            }
+        }
+    };
+
+    view! {
+        <ConfirmModal
+            show=Signal::derive(move || token.with(|token| token.is_some()))
+            title="Your NFT"
+            confirm_label="Save"
+            cancel_label="Close"
+            on_confirm=set_minimum_bid
+            on_close=move |_| token.set(None)
+        >
+            {move || token.get().map(|token| view! {
+                <p>{format!("Token Id: {} Locked OM: {}", token.token_id, format_amount(token.locked_OM.to_ether()))}</p>
+                <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+                    "Minimum bid price:"
+                    <NumberInput min=0.0 get=bids_minimum_price set=set_bids_minimum_price/>
+                </Stack>
+            })}
+        </ConfirmModal>
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use leptonic::prelude::*;
+use leptos::*;
+use mandelbrot_explorer::BigFloat;
+
+use crate::state::State;
+
+
+const VIEW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+
+/// Shows which token the center of the view is in, while the user pans around without navigating.
+#[component]
+pub fn Position() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+    let explorer = state.explorer.clone();
+
+    if let Ok(handle) = set_interval_with_handle({
+        let mandelbrot = state.mandelbrot.clone();
+        let explorer = explorer.clone();
+        move || {
+            if !explorer.track_position.get_untracked() {
+                return
+            }
+            let bounds = mandelbrot.lock().unwrap().sample.borrow().get_bounds();
+            let half = BigFloat::from(0.5);
+            let x = &bounds.x_min + (&bounds.x_max - &bounds.x_min) * half.clone();
+            let y = &bounds.y_min + (&bounds.y_max - &bounds.y_min) * half;
+            let token_id = explorer.node_at_point(x, y);
+            if explorer.position.get_untracked() != token_id {
+                explorer.position.set(token_id);
+            }
+        }
+    }, VIEW_POLL_INTERVAL) {
+        on_cleanup(move || handle.clear());
+    }
+
+    view! {
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+            <Toggle
+                state=explorer.track_position
+                set_state=move |track: bool| {
+                    explorer.track_position.set(track);
+                    if !track {
+                        explorer.position.set(None);
+                    }
+                }
+                variant=ToggleVariant::Stationary
+            />
+            "Track position"
+            {move || explorer.position.get().map(|token_id| format!(": you are inside NFT {token_id}"))}
+        </Stack>
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+    use web3::types::Address;
+
+    use super::*;
+    use crate::evm::types::{Field, OMAmount};
+
+    fn token(token_id: u128, layer: u128, [x_min, y_min, x_max, y_max]: [f64; 4]) -> Metadata {
+        Metadata {
+            token_id,
+            owner: Address::zero(),
+            parent_id: 0,
+            field: Field {
+                x_min: BigFloat::from(x_min),
+                y_min: BigFloat::from(y_min),
+                x_max: BigFloat::from(x_max),
+                y_max: BigFloat::from(y_max),
+            },
+            locked_OM: OMAmount::default(),
+            minimum_price: OMAmount::default(),
+            layer,
+            owned: false,
+            selected: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn deepest_of_nested_tokens_containing_the_point_is_found() {
+        let tokens = [
+            token(1, 1, [-2.0, -2.0, 2.0, 2.0]),
+            token(2, 2, [0.0, 0.0, 1.0, 1.0]),
+            token(3, 3, [0.25, 0.25, 0.5, 0.5]),
+            token(4, 3, [0.75, 0.75, 1.0, 1.0]),
+        ];
+        let at = |x: f64, y: f64| deepest_containing(tokens.iter(), &BigFloat::from(x), &BigFloat::from(y));
+        assert_eq!(at(0.3, 0.3), Some(3));
+        assert_eq!(at(0.6, 0.6), Some(2));
+        assert_eq!(at(-1.0, 1.0), Some(1));
+        assert_eq!(at(3.0, 0.0), None);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+
+use mandelbrot_explorer::{BigFloat, Bounds, Frame, FrameColor};
+
+use crate::{evm::types::Metadata, state::Pointer};
+
+
+/// Frames of the explored NFT in drawing order, children and bids first, ancestors on top.
+/// Selection looks tokens up by id, so an id coming from several sources keeps only a single frame,
+/// ancestors taking precedence over children and children over bids.
+pub fn collect_frames(ancestors: &[Metadata], children: &[Metadata], bids: &[Metadata]) -> Vec<Frame> {
+    let mut seen = HashSet::new();
+    let mut unique = |token: &&Metadata, source: &str| {
+        let unique = seen.insert(token.token_id);
+        if !unique {
+            log::warn!("Id {} appears in several frame sources, ignoring it among {}", token.token_id, source);
+        }
+        unique
+    };
+    let ancestors: Vec<_> = ancestors.iter().filter(|token| unique(token, "ancestors")).map(|token| token.to_frame(FrameColor::Blue)).collect();
+    let children: Vec<_> = children.iter().filter(|token| unique(token, "children")).map(|token| token.to_frame(FrameColor::Red)).collect();
+    let bids: Vec<_> = bids.iter().filter(|token| unique(token, "bids")).map(bid_frame).collect();
+    children.into_iter().chain(bids).chain(ancestors.into_iter().rev()).collect()
+}
+
+
+/// Children to draw, only the ones owned by the user with the owned filter on.
+pub fn shown_children(children: impl IntoIterator<Item = Metadata>, owned_only: bool) -> Vec<Metadata> {
+    children.into_iter().filter(|token| !owned_only || token.owned).collect()
+}
+
+
+/// Bids toggled for approval are Green whoever placed them, the rest Yellow, or Lemon when placed by the user.
+fn bid_frame(bid: &Metadata) -> Frame {
+    let mut frame = bid.to_frame(FrameColor::Yellow);
+    if bid.selected {
+        frame.color = FrameColor::Green;
+    }
+    frame
+}
+
+
+/// Frame under the pointer, where every frame is extended by `tolerance` pixels so that tiny frames stay clickable.
+/// The smallest matching frame wins, so a parent frame is picked when clicking further than `tolerance` from its children.
+pub fn pick_frame(frames: &[Frame], view: &Bounds, pointer: Pointer, tolerance: f64) -> Option<Frame> {
+    let width = &view.x_max - &view.x_min;
+    let height = &view.y_max - &view.y_min;
+    let x = &view.x_min + &width * BigFloat::from(pointer.x);
+    let y = &view.y_min + &height * BigFloat::from(pointer.y);
+    let tolerance_x = &width * BigFloat::from(tolerance / pointer.size);
+    let tolerance_y = &height * BigFloat::from(tolerance / pointer.size);
+
+    frames.iter()
+        .filter(|frame| {
+            &frame.bounds.x_min - &tolerance_x <= x && x <= &frame.bounds.x_max + &tolerance_x &&
+                &frame.bounds.y_min - &tolerance_y <= y && y <= &frame.bounds.y_max + &tolerance_y
+        })
+        .map(|frame| ((&frame.bounds.x_max - &frame.bounds.x_min) * (&frame.bounds.y_max - &frame.bounds.y_min), frame))
+        .min_by(|(area_a, _), (area_b, _)| area_a.partial_cmp(area_b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, frame)| frame.clone())
+}
+
+
+/// What double clicking a frame does, depending on its color.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Selection {
+    /// Move to the NFT.
+    Navigate,
+    /// Move to the NFT and offer the owner actions on it.
+    Own,
+    SelectBid,
+    DeselectBid,
+    Ignore,
+}
+
+/// Red and Blue are minted NFTs, children and ancestors of the current one respectively,
+/// Pink and LightBlue are the same NFTs owned by the user, Yellow and Lemon are bids, Green is a selected bid.
+/// Spectators can only move around.
+pub fn selection(color: &FrameColor, spectator: bool) -> Selection {
+    match color {
+        FrameColor::Red | FrameColor::Blue => Selection::Navigate,
+        FrameColor::Pink | FrameColor::LightBlue if spectator => Selection::Navigate,
+        FrameColor::Pink | FrameColor::LightBlue => Selection::Own,
+        _ if spectator => Selection::Ignore,
+        FrameColor::Yellow | FrameColor::Lemon => Selection::SelectBid,
+        FrameColor::Green => Selection::DeselectBid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+    use web3::types::Address;
+
+    use super::*;
+    use crate::evm::types::{Field, OMAmount};
+
+    fn frame(id: u128, [x_min, y_min, x_max, y_max]: [f64; 4]) -> Frame {
+        Frame {
+            id,
+            bounds: Bounds { x_min: x_min.into(), x_max: x_max.into(), y_min: y_min.into(), y_max: y_max.into() },
+            color: mandelbrot_explorer::FrameColor::Red,
+        }
+    }
+
+    fn pointer(x: f64, y: f64) -> Pointer {
+        Pointer { x, y, size: 100.0 }
+    }
+
+    fn view() -> Bounds {
+        Bounds { x_min: 0.0.into(), x_max: 1.0.into(), y_min: 0.0.into(), y_max: 1.0.into() }
+    }
+
+    #[wasm_bindgen_test]
+    fn smallest_frame_under_the_pointer_is_picked() {
+        let frames = [frame(1, [0.0, 0.0, 1.0, 1.0]), frame(2, [0.4, 0.4, 0.6, 0.6])];
+        assert_eq!(pick_frame(&frames, &view(), pointer(0.5, 0.5), 0.0).map(|frame| frame.id), Some(2));
+        assert_eq!(pick_frame(&frames, &view(), pointer(0.1, 0.1), 0.0).map(|frame| frame.id), Some(1));
+    }
+
+    #[wasm_bindgen_test]
+    fn tolerance_extends_frames() {
+        let frames = [frame(1, [0.0, 0.0, 1.0, 1.0]), frame(2, [0.4, 0.4, 0.6, 0.6])];
+        // 4 pixels on a 100 pixel canvas reach 0.04 past the frame
+        assert_eq!(pick_frame(&frames, &view(), pointer(0.63, 0.5), 4.0).map(|frame| frame.id), Some(2));
+        assert_eq!(pick_frame(&frames, &view(), pointer(0.65, 0.5), 4.0).map(|frame| frame.id), Some(1));
+        assert_eq!(pick_frame(&frames, &view(), pointer(0.63, 0.5), 0.0).map(|frame| frame.id), Some(1));
+    }
+
+    #[wasm_bindgen_test]
+    fn nothing_is_picked_outside_of_every_frame() {
+        let frames = [frame(2, [0.4, 0.4, 0.6, 0.6])];
+        assert!(pick_frame(&frames, &view(), pointer(0.9, 0.9), 4.0).is_none());
+        assert!(pick_frame(&[], &view(), pointer(0.5, 0.5), 4.0).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn owned_frames_offer_owner_actions() {
+        assert!(selection(&FrameColor::Red, false) == Selection::Navigate);
+        assert!(selection(&FrameColor::Blue, false) == Selection::Navigate);
+        assert!(selection(&FrameColor::Pink, false) == Selection::Own);
+        assert!(selection(&FrameColor::LightBlue, false) == Selection::Own);
+        assert!(selection(&FrameColor::Yellow, false) == Selection::SelectBid);
+        assert!(selection(&FrameColor::Lemon, false) == Selection::SelectBid);
+        assert!(selection(&FrameColor::Green, false) == Selection::DeselectBid);
+    }
+
+    #[wasm_bindgen_test]
+    fn spectators_only_navigate() {
+        assert!(selection(&FrameColor::Pink, true) == Selection::Navigate);
+        assert!(selection(&FrameColor::LightBlue, true) == Selection::Navigate);
+        assert!(selection(&FrameColor::Yellow, true) == Selection::Ignore);
+        assert!(selection(&FrameColor::Green, true) == Selection::Ignore);
+    }
+
+    fn token(token_id: u128, parent_id: u128, [x_min, y_min, x_max, y_max]: [f64; 4]) -> Metadata {
+        Metadata {
+            token_id,
+            owner: Address::zero(),
+            parent_id,
+            field: Field { x_min: x_min.into(), y_min: y_min.into(), x_max: x_max.into(), y_max: y_max.into() },
+            locked_OM: OMAmount::default(),
+            minimum_price: OMAmount::default(),
+            layer: 0,
+            owned: false,
+            selected: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn frames_are_drawn_children_first_and_ancestors_last() {
+        let ancestors = [token(1, 0, [0.0, 0.0, 1.0, 1.0]), token(2, 1, [0.0, 0.0, 0.5, 0.5])];
+        let children = [token(3, 2, [0.0, 0.0, 0.1, 0.1])];
+        let bids = [token(4, 2, [0.2, 0.2, 0.3, 0.3])];
+        let frames = collect_frames(&ancestors, &children, &bids);
+        assert_eq!(frames.iter().map(|frame| frame.id).collect::<Vec<_>>(), vec![3, 4, 2, 1]);
+        assert!(matches!(frames[0].color, FrameColor::Red));
+        assert!(matches!(frames[1].color, FrameColor::Yellow));
+        assert!(matches!(frames[2].color, FrameColor::Blue));
+    }
+
+    #[wasm_bindgen_test]
+    fn colliding_ids_keep_a_single_frame() {
+        let ancestors = [token(1, 0, [0.0, 0.0, 1.0, 1.0])];
+        let children = [token(1, 1, [0.0, 0.0, 0.1, 0.1]), token(2, 1, [0.2, 0.2, 0.3, 0.3])];
+        let bids = [token(2, 1, [0.4, 0.4, 0.5, 0.5]), token(3, 1, [0.6, 0.6, 0.7, 0.7])];
+        let frames = collect_frames(&ancestors, &children, &bids);
+        assert_eq!(frames.iter().map(|frame| frame.id).collect::<Vec<_>>(), vec![2, 3, 1]);
+        // the ancestor and the child keep their own frames, not the ones of the colliding tokens
+        assert!(matches!(frames[0].color, FrameColor::Red));
+        assert!(matches!(frames[2].color, FrameColor::Blue));
+    }
+
+    #[wasm_bindgen_test]
+    fn selected_bids_are_green_whoever_placed_them() {
+        let area = [0.0, 0.0, 0.5, 0.5];
+        let colors: Vec<FrameColor> = collect_frames(&[], &[], &[
+            token(3, 1, area),
+            Metadata { owned: true, ..token(4, 1, area) },
+            Metadata { selected: true, ..token(5, 1, area) },
+            Metadata { owned: true, selected: true, ..token(6, 1, area) },
+        ]).into_iter().map(|frame| frame.color).collect();
+        assert!(matches!(colors[..], [FrameColor::Yellow, FrameColor::Lemon, FrameColor::Green, FrameColor::Green]));
+    }
+
+    #[wasm_bindgen_test]
+    fn owned_filter_leaves_out_the_children_of_others_but_no_bids() {
+        let area = [0.0, 0.0, 0.5, 0.5];
+        let children = vec![Metadata { owned: true, ..token(3, 1, area) }, token(4, 1, area)];
+        let bids = vec![token(5, 1, area)];
+
+        let ids = |owned_only| collect_frames(&[token(1, 0, [0.0, 0.0, 1.0, 1.0])], &shown_children(children.clone(), owned_only), &bids)
+            .into_iter().map(|frame| frame.id).collect::<Vec<_>>();
+        assert_eq!(ids(true), vec![3, 5, 1]);
+        assert_eq!(ids(false), vec![3, 4, 5, 1]);
+    }
+}
@@ -0,0 +1,123 @@
+use leptonic::prelude::*;
+use leptos::*;
+use wasm_bindgen::JsCast;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Shortcut {
+    Bid,
+    Up,
+    Root,
+    Refresh,
+    Help,
+}
+
+impl Shortcut {
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Bid => "Open the bid form",
+            Self::Up => "Go up to the parent NFT",
+            Self::Root => "Go to the root NFT",
+            Self::Refresh => "Refresh NFTs and bids",
+            Self::Help => "Show keyboard shortcuts",
+        }
+    }
+}
+
+
+/// Keys bound to the shortcuts, matched against `KeyboardEvent.key`.
+pub const SHORTCUTS: [(&str, Shortcut); 6] = [
+    ("b", Shortcut::Bid),
+    ("Escape", Shortcut::Up),
+    ("Backspace", Shortcut::Up),
+    ("Home", Shortcut::Root),
+    ("r", Shortcut::Refresh),
+    ("?", Shortcut::Help),
+];
+
+
+/// Shortcut triggered by the key press, ignoring key combinations and anything typed into an editable element.
+pub fn shortcut(event: &web_sys::KeyboardEvent) -> Option<Shortcut> {
+    if event.ctrl_key() || event.meta_key() || event.alt_key() || is_typing(event) {
+        return None
+    }
+    let key = event.key();
+    SHORTCUTS.iter().find(|(key_, _)| *key_ == key).map(|(_, shortcut)| *shortcut)
+}
+
+fn is_typing(event: &web_sys::KeyboardEvent) -> bool {
+    if let Some(element) = event.target().and_then(|target| target.dyn_into::<web_sys::HtmlElement>().ok()) {
+        matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT") || element.is_content_editable()
+    } else {
+        false
+    }
+}
+
+
+#[component]
+pub fn ShortcutsHelp(
+    show: RwSignal<bool>,
+) -> impl IntoView {
+    view! {
+        <Modal show_when=MaybeSignal::derive(move || show.get())>
+            <ModalHeader><ModalTitle>"Keyboard shortcuts"</ModalTitle></ModalHeader>
+            <ModalBody>
+                {SHORTCUTS.iter().map(|(key, shortcut)| view! {
+                    <p><code>{*key}</code>" - "{shortcut.description()}</p>
+                }).collect_view()}
+            </ModalBody>
+            <ModalFooter>
+                <ButtonWrapper>
+                    <Button on_click=move |_| show.set(false) color=ButtonColor::Secondary>"Close"</Button>
+                </ButtonWrapper>
+            </ModalFooter>
+        </Modal>
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    /// Shortcut recognized when `key` is pressed on `target`.
+    fn press(target: &web_sys::HtmlElement, key: &str) -> Option<Shortcut> {
+        let pressed = Rc::new(Cell::new(None));
+        let listener = Closure::<dyn Fn(web_sys::KeyboardEvent)>::new({
+            let pressed = pressed.clone();
+            move |event: web_sys::KeyboardEvent| pressed.set(shortcut(&event))
+        });
+        target.add_event_listener_with_callback("keydown", listener.as_ref().unchecked_ref()).unwrap();
+
+        let init = web_sys::KeyboardEventInit::new();
+        init.set_key(key);
+        let event = web_sys::KeyboardEvent::new_with_keyboard_event_init_dict("keydown", &init).unwrap();
+        target.dispatch_event(&event).unwrap();
+        pressed.get()
+    }
+
+    fn element(tag_name: &str) -> web_sys::HtmlElement {
+        web_sys::window().unwrap().document().unwrap().create_element(tag_name).unwrap().unchecked_into()
+    }
+
+    #[wasm_bindgen_test]
+    fn shortcut_is_dispatched_outside_of_inputs() {
+        assert!(press(&element("div"), "b") == Some(Shortcut::Bid));
+        assert!(press(&element("div"), "Escape") == Some(Shortcut::Up));
+        assert!(press(&element("div"), "Backspace") == Some(Shortcut::Up));
+        assert!(press(&element("div"), "Home") == Some(Shortcut::Root));
+        assert!(press(&element("div"), "x").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn shortcut_is_ignored_while_typing() {
+        assert!(press(&element("input"), "b").is_none());
+        assert!(press(&element("textarea"), "r").is_none());
+        assert!(press(&element("input"), "Backspace").is_none());
+    }
+}
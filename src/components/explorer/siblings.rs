@@ -0,0 +1,136 @@
+use leptonic::prelude::*;
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    evm::types::Metadata,
+    state::State,
+    util::preserve_query,
+};
+
+
+const THUMBNAIL_SIZE: u32 = 48;
+
+
+/// Sketch of where `token` lies within its parent, drawn instead of rendering the set for every sibling.
+#[component]
+fn Thumbnail(
+    token: Metadata,
+    parent: Metadata,
+    current: bool,
+) -> impl IntoView {
+    let [x, y, width, height] = token.field.portion_of(&parent.field);
+    let size = THUMBNAIL_SIZE as f64;
+
+    view! {
+        <svg
+            width=THUMBNAIL_SIZE
+            height=THUMBNAIL_SIZE
+            style:border=if current { "2px solid white" } else { "1px solid gray" }
+        >
+            <rect
+                x=x * size
+                y=y * size
+                width=(width * size).max(1.0)
+                height=(height * size).max(1.0)
+                fill=if current { "white" } else { "red" }
+            />
+        </svg>
+    }
+}
+
+/// Siblings in the strip, each marked whether it's the explored token. A lone token has no strip.
+fn strip_entries(siblings: Vec<Metadata>, token_id: u128) -> Option<Vec<(Metadata, bool)>> {
+    (siblings.len() > 1).then(|| siblings.into_iter().map(|token| {
+        let current = token.token_id == token_id;
+        (token, current)
+    }).collect())
+}
+
+
+/// NFTs minted in the same parent as the explored one, the explored one is highlighted.
+#[component]
+pub fn Siblings() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+
+    let current = move || state.explorer.nav_history.with(|nav_history| {
+        let parent = nav_history.len().checked_sub(2).map(|index| nav_history[index].clone());
+        parent.zip(nav_history.last().map(|token| token.token_id))
+    });
+
+    let siblings = create_local_resource(move || current().map(|(parent, _)| parent.token_id), {
+        let erc1155_contract = state.erc1155_contract.clone();
+        move |parent_id| {
+            let erc1155_contract = erc1155_contract.clone();
+            async move {
+                let mut siblings = erc1155_contract.get_children_metadata(parent_id?).await.ok()?;
+                siblings.sort_by_key(|token| token.token_id);
+                Some(siblings)
+            }
+        }
+    });
+
+    let zoom_token = {
+        let mandelbrot = state.mandelbrot.clone();
+        let navigate = use_navigate();
+        move |token: &Metadata| {
+            mandelbrot.lock().unwrap().move_into_bounds(&token.to_frame(mandelbrot_explorer::FrameColor::Blue).bounds);
+            navigate(&preserve_query(format!("/tokens/{}", token.token_id)), Default::default());
+        }
+    };
+
+    view! {
+        {move || {
+            let (parent, token_id) = current()?;
+            let entries = strip_entries(siblings.get().flatten()?, token_id)?;
+            let zoom_token = zoom_token.clone();
+            Some(view! {
+                <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.3) style="overflow-x: auto">
+                    {entries.into_iter().map(|(token, current)| {
+                        let zoom_token = zoom_token.clone();
+                        view! {
+                            <div title=format!("NFT {}", token.token_id) on:click={let token = token.clone(); move |_| zoom_token(&token)}>
+                                <Thumbnail token parent=parent.clone() current/>
+                            </div>
+                        }
+                    }).collect_view()}
+                </Stack>
+            })
+        }}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+    use web3::types::Address;
+
+    use super::*;
+    use crate::evm::types::{Field, OMAmount};
+
+    fn sibling(token_id: u128) -> Metadata {
+        Metadata {
+            token_id,
+            owner: Address::zero(),
+            parent_id: 2,
+            field: Field { x_min: 0.0.into(), y_min: 0.0.into(), x_max: 0.1.into(), y_max: 0.1.into() },
+            locked_OM: OMAmount::default(),
+            minimum_price: OMAmount::default(),
+            layer: 2,
+            owned: false,
+            selected: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn strip_has_an_entry_per_sibling_with_the_current_one_marked() {
+        let entries = strip_entries(vec![sibling(3), sibling(4), sibling(5)], 4).unwrap();
+        assert_eq!(entries.iter().map(|(token, current)| (token.token_id, *current)).collect::<Vec<_>>(), vec![(3, false), (4, true), (5, false)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn lone_token_has_no_strip() {
+        assert!(strip_entries(vec![sibling(3)], 3).is_none());
+    }
+}
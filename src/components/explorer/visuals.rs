@@ -1,13 +1,73 @@
+use std::time::Duration;
+
 use leptonic::prelude::*;
 use leptos::*;
+use mandelbrot_explorer::{BigFloat, Bounds};
+
+use crate::{components::mandelbrot::request_redraw, state::State, util::{load_local, store_local}};
+
+
+const VIEW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const AUTO_ITERATIONS_KEY: &str = "auto_iterations";
+const MAX_ITERATIONS_KEY: &str = "max_iterations";
+const AUTO_BASE_ITERATIONS: f64 = 200.0;
+const AUTO_ITERATIONS_PER_LEVEL: f64 = 100.0;
+const AUTO_MAX_ITERATIONS: f64 = 40_000.0;
+/// Width of the whole set, the view at zoom level 0.
+const FULL_WIDTH: f64 = 4.0;
+const MAX_ZOOM_LEVEL: u32 = 1000;
+
+
+/// Number of times the view was halved relative to the whole set.
+fn zoom_level(bounds: &Bounds) -> u32 {
+    let width = &bounds.x_max - &bounds.x_min;
+    let mut level_width = BigFloat::from(FULL_WIDTH / 2.0);
+    let half = BigFloat::from(0.5);
+    let mut level = 0;
+    while width <= level_width && level < MAX_ZOOM_LEVEL {
+        level_width = level_width * half.clone();
+        level += 1;
+    }
+    level
+}
 
-use crate::state::State;
+/// Iterations needed to keep a view at `zoom_level` detailed, deeper views need more of them.
+fn auto_iterations(zoom_level: u32) -> f64 {
+    (AUTO_BASE_ITERATIONS + AUTO_ITERATIONS_PER_LEVEL * zoom_level as f64).min(AUTO_MAX_ITERATIONS)
+}
 
 
 #[component]
 pub fn Visuals() -> impl IntoView {
     let state = use_context::<State>().unwrap();
-    let (max_iterations, set_max_iterations) = create_signal(40.0);
+    // the slider works on the square root of the iterations
+    let (max_iterations, set_max_iterations) = create_signal(
+        load_local(MAX_ITERATIONS_KEY).and_then(|value| value.parse().ok()).unwrap_or(40.0)
+    );
+    let (auto_iterations_, set_auto_iterations) = create_signal(load_local(AUTO_ITERATIONS_KEY).as_deref() == Some("true"));
+
+    create_effect(move |_| {
+        store_local(AUTO_ITERATIONS_KEY, &auto_iterations_.get().to_string());
+        if !auto_iterations_.get() {
+            store_local(MAX_ITERATIONS_KEY, &max_iterations.get().to_string());
+        }
+    });
+
+    if let Ok(handle) = set_interval_with_handle({
+        let mandelbrot = state.mandelbrot.clone();
+        move || {
+            if !auto_iterations_.get_untracked() {
+                return
+            }
+            let bounds = mandelbrot.lock().unwrap().sample.borrow().get_bounds();
+            let iterations = auto_iterations(zoom_level(&bounds)).sqrt();
+            if max_iterations.get_untracked() != iterations {
+                set_max_iterations.set(iterations);
+            }
+        }
+    }, VIEW_POLL_INTERVAL) {
+        on_cleanup(move || handle.clear());
+    }
     let (offset, set_offset) = create_signal(0.0);
     let (length, set_length) = create_signal(360.0);
 
@@ -18,16 +78,18 @@ pub fn Visuals() -> impl IntoView {
             mandelbrot.coloring.max_iterations = (max_iterations.get() as f64).powi(2) as i32;
             mandelbrot.coloring.offset = offset.get() as f32;
             mandelbrot.coloring.length = length.get() as f32;
-            if let Some(redraw) = &mandelbrot.redraw {
-                redraw();
-            }
+            request_redraw(&mandelbrot);
         }
     });
 
     view! {
-        "Max iterations"
+        <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
+            "Max iterations"
+            <Toggle state=auto_iterations_ set_state=set_auto_iterations/>
+            "Auto"
+        </Stack>
         <Slider style="width: 35em" min=0.0 max=200.0
-            value=max_iterations set_value=set_max_iterations
+            value=max_iterations set_value=move |v| if !auto_iterations_.get_untracked() { set_max_iterations.set(v) }
             value_display=move |v: f64| format!("{:.0}", v.powi(2))/>
         "Color offset"
         <Slider style="width: 35em" min=0.0 max=1.0
@@ -37,5 +99,33 @@ pub fn Visuals() -> impl IntoView {
         <Slider style="width: 35em" min=0.0 max=10000.0
             value=length set_value=set_length
             value_display=move |v: f64| format!("{v:.4}")/>
+        "Selection tolerance"
+        <Slider style="width: 35em" min=0.0 max=20.0 step=1.0
+            value=state.explorer.frame_tolerance set_value=move |v| state.explorer.frame_tolerance.set(v)
+            value_display=move |v: f64| format!("{v:.0}px")/>
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    fn view(width: f64) -> Bounds {
+        Bounds { x_min: (-width / 2.0).into(), x_max: (width / 2.0).into(), y_min: (-width / 2.0).into(), y_max: (width / 2.0).into() }
+    }
+
+    #[wasm_bindgen_test]
+    fn auto_iterations_increase_as_the_zoom_deepens() {
+        assert_eq!(zoom_level(&view(FULL_WIDTH)), 0);
+        assert_eq!(zoom_level(&view(FULL_WIDTH / 2.0)), 1);
+        assert_eq!(zoom_level(&view(FULL_WIDTH / 1024.0)), 10);
+
+        let levels = [view(FULL_WIDTH), view(FULL_WIDTH / 16.0), view(FULL_WIDTH / 1e6)].map(|bounds| auto_iterations(zoom_level(&bounds)));
+        assert!(levels[0] < levels[1] && levels[1] < levels[2]);
+        assert_eq!(levels[0], AUTO_BASE_ITERATIONS);
+        assert_eq!(auto_iterations(MAX_ZOOM_LEVEL), AUTO_MAX_ITERATIONS);
     }
 }
@@ -5,8 +5,10 @@ use leptos::*;
 use mandelbrot_explorer::FrameColor;
 
 use crate::{
+    components::confirmation::ConfirmationEstimate,
     evm::types::Metadata,
-    state::State,
+    state::{remove_bid_while, LedgerAction, State},
+    util::format_amount,
 };
 
 
@@ -17,17 +19,24 @@ pub fn Bids(
     let state = use_context::<State>().unwrap();
 
     let delete_bid = create_action({
+        let state = state.clone();
         move |bid_id: &u128| {
+            let state = state.clone();
             let erc1155_contract = state.erc1155_contract.clone();
             let bid_id = bid_id.clone();
             async move {
-                if let Some(address) = state.address.get_untracked() {
-                    if let Some(_) = erc1155_contract.delete_bid(address, bid_id).await {
-                        bids.update(|bids| {
-                            bids.remove(&bid_id);
-                        });
-                    }
+                let (Some(address), Some(bid)) = (state.sender().await, bids.get_untracked().get(&bid_id).cloned()) else {
+                    return
+                };
+                let before = erc1155_contract.get_OM_balance(address).await.ok();
+                // the bid is shown in the explorer too when its parent is explored
+                let deleted = remove_bid_while(bid_id, &[bids, state.explorer.bids], erc1155_contract.delete_bid(address, bid_id)).await;
+                if deleted.is_some() {
+                    state.ledger.record(LedgerAction::BidDeletion, Some(bid_id), bid.locked_OM.to_ether());
+                    state.show_OM_change(address, before, bid.locked_OM.to_ether()).await;
+                    state.refresh_OM_balance();
                 }
+                state.reload_bids(bid.parent_id).await;
             }
         }
     });
@@ -47,14 +56,20 @@ pub fn Bids(
                 let zoom_bid = zoom_bid.clone();
                 view! {
                     <Box id="content">
+                        <ConfirmationEstimate pending=delete_bid.pending()/>
                         <For
                             each=move || bids.get().into_values()
                             key=|bid| bid.token_id
                             children=move |bid| view! {
                                 <p>
                                     <Button on_click={let zoom_bid = zoom_bid.clone(); move |_| zoom_bid(bid.token_id)}>"Zoom"</Button>
-                                    {format!("Bid Id: {} Proposed OM: {}", bid.token_id, bid.locked_OM.to_string())}
-                                    <Button on_click=move |_| delete_bid.dispatch(bid.token_id)>"Delete"</Button>
+                                    {format!("Bid Id: {} Proposed OM: {}", bid.token_id, format_amount(bid.locked_OM.to_ether()))}
+                                    <Button
+                                        on_click=move |_| delete_bid.dispatch(bid.token_id)
+                                        disabled=Signal::derive(move || delete_bid.pending().get())
+                                    >
+                                        "Delete"
+                                    </Button>
                                 </p>
                             }
                         />
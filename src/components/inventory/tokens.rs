@@ -5,39 +5,83 @@ use leptos::*;
 use leptos_router::*;
 use mandelbrot_explorer::FrameColor;
 
+use web3::types::Address;
+
 use crate::{
-    evm::types::Metadata,
+    components::{confirm::ConfirmModal, confirmation::ConfirmationEstimate},
+    evm::{contracts::ERC1155Contract, types::Metadata},
     state::State,
-    util::preserve_log_level,
+    util::{format_amount, preserve_query},
 };
 
 
+/// Reason why a token cannot be burned at the moment.
+#[derive(Clone, PartialEq)]
+enum BurnBlocker {
+    NotOwner,
+    HasChildren(usize),
+    HasOpenBids(usize),
+}
+
+impl BurnBlocker {
+    fn reason(&self) -> String {
+        match self {
+            Self::NotOwner => "Only the owner can burn this NFT".into(),
+            Self::HasChildren(count) => format!("Cannot burn while {count} NFTs are minted inside"),
+            Self::HasOpenBids(count) => format!("Cannot burn while {count} bids are open, approve or wait for them to be deleted"),
+        }
+    }
+}
+
+async fn burn_blocker(erc1155_contract: ERC1155Contract, address: Option<Address>, token: Metadata) -> Option<BurnBlocker> {
+    if address != Some(token.owner) {
+        return Some(BurnBlocker::NotOwner)
+    }
+    match erc1155_contract.get_children_metadata(token.token_id).await {
+        Ok(children) if !children.is_empty() => return Some(BurnBlocker::HasChildren(children.len())),
+        _ => {}
+    }
+    match erc1155_contract.get_bids(token.token_id).await {
+        Ok(bids) if !bids.is_empty() => return Some(BurnBlocker::HasOpenBids(bids.len())),
+        _ => {}
+    }
+    None
+}
+
+
 #[component]
 pub fn Tokens(
     tokens: RwSignal<HashMap<u128, Metadata>>,
 ) -> impl IntoView {
     let state = use_context::<State>().unwrap();
 
-    let burn_token = create_action({
-        let erc1155_contract = state.erc1155_contract.clone();
-        move |token_id: &u128| {
-            let erc1155_contract = erc1155_contract.clone();
-            let token_id = token_id.clone();
+    let burned_token = create_rw_signal(None::<u128>);
+    let burn_token = {
+        let state = state.clone();
+        move || {
+            let state = state.clone();
+            let token_id = burned_token.get_untracked();
             async move {
-                if let Some(address) = state.address.get_untracked() {
-                    if let Some(_) = erc1155_contract.burn(address, token_id).await {
+                if let (Some(address), Some(token_id)) = (state.sender().await, token_id) {
+                    if let Some(_) = state.erc1155_contract.burn(address, token_id).await {
+                        if let Some(parent_id) = tokens.with_untracked(|tokens| tokens.get(&token_id).map(|token| token.parent_id)) {
+                            state.explorer.cache.invalidate(parent_id);
+                        }
                         tokens.update(|tokens| {
                             tokens.remove(&token_id);
                         });
+                        state.refresh_OM_balance();
+                        return true
                     }
                 }
+                false
             }
         }
-    });
+    };
 
     let zoom_token = move |token_id| {
         if let Some(token) = tokens.get().get(&token_id) {
-            use_navigate()(&preserve_log_level(format!("/tokens/{}", token_id)), Default::default());
+            use_navigate()(&preserve_query(format!("/tokens/{}", token_id)), Default::default());
             let frame = token.to_frame(FrameColor::Blue);
             state.mandelbrot.lock().unwrap().move_into_bounds(&frame.bounds)
         }
@@ -46,16 +90,16 @@ pub fn Tokens(
     let edited_token = create_rw_signal(None);
     let (bids_minimum_price, set_bids_minimum_price) = create_signal(0.0);
     let edit_token = move |token: Metadata| {
-        set_bids_minimum_price.set(token.minimum_price);
+        set_bids_minimum_price.set(token.minimum_price.to_ether());
         edited_token.set(Some(token))
     };
     let edit_token_submit = create_action({
-        let erc1155_contract = state.erc1155_contract.clone();
+        let state = state.clone();
         move |_| {
-            let erc1155_contract = erc1155_contract.clone();
+            let state = state.clone();
             async move {
-                if let (Some(address), Some(token)) = (state.address.get_untracked(), edited_token.get_untracked()) {
-                    erc1155_contract.set_minimum_bid(address, token.token_id, bids_minimum_price.get_untracked()).await;
+                if let (Some(address), Some(token)) = (state.sender().await, edited_token.get_untracked()) {
+                    state.erc1155_contract.set_minimum_bid(address, token.token_id, bids_minimum_price.get_untracked()).await;
                 }
                 edited_token.set(None);
             }
@@ -66,18 +110,35 @@ pub fn Tokens(
         <Show when=move || {tokens.get().len() > 0} fallback=|| {}>
             {
                 let zoom_token = zoom_token.clone();
+                let erc1155_contract = state.erc1155_contract.clone();
                 view! {
                     <Box id="content">
                         <For
                             each=move || tokens.get().into_values()
                             key=|token| token.token_id
-                            children=move |token| view! {
-                                <p>
-                                    <Button on_click={let zoom_token = zoom_token.clone(); move |_| zoom_token(token.token_id)}>"Zoom"</Button>
-                                    {format!("Token Id: {} Locked OM: {}", token.token_id, token.locked_OM.to_string())}
-                                    <Button on_click={let token = token.clone(); move |_| edit_token(token.clone())}>"Edit"</Button>
-                                    <Button on_click=move |_| burn_token.dispatch(token.token_id)>"Burn"</Button>
-                                </p>
+                            children=move |token| {
+                                let blocker = create_local_resource(move || state.address.get(), {
+                                    let erc1155_contract = erc1155_contract.clone();
+                                    let token = token.clone();
+                                    move |address| burn_blocker(erc1155_contract.clone(), address, token.clone())
+                                });
+                                view! {
+                                    <p>
+                                        <Button on_click={let zoom_token = zoom_token.clone(); move |_| zoom_token(token.token_id)}>"Zoom"</Button>
+                                        {format!("Token Id: {} Locked OM: {}", token.token_id, format_amount(token.locked_OM.to_ether()))}
+                                        {move || state.notes.get(token.token_id).map(|_| " 📝")}
+                                        <Button on_click={let token = token.clone(); move |_| edit_token(token.clone())}>"Edit"</Button>
+                                        <Button
+                                            on_click=move |_| burned_token.set(Some(token.token_id))
+                                            disabled=Signal::derive(move || blocker.get().map_or(true, |blocker| blocker.is_some()))
+                                        >
+                                            "Burn"
+                                        </Button>
+                                        {move || blocker.get().flatten().map(|blocker| view! {
+                                            <span style="font-size: 0.8em">{blocker.reason()}</span>
+                                        })}
+                                    </p>
+                                }
                             }
                         />
                     </Box>
@@ -85,6 +146,19 @@ pub fn Tokens(
             }
         </Show>
 
+        <ConfirmModal
+            show=Signal::derive(move || burned_token.get().is_some())
+            title="Burn NFT"
+            confirm_label="Burn"
+            on_confirm=burn_token
+            on_close=move |_| burned_token.set(None)
+        >
+            <p>
+                "Are you sure you want to burn NFT "{move || burned_token.get().map(|token_id| token_id.to_string())}
+                "? This cannot be undone."
+            </p>
+        </ConfirmModal>
+
         <Modal show_when=MaybeSignal::derive(move || edited_token.get().is_some())>
             <ModalHeader><ModalTitle>
                 "Token Id "{move || edited_token.get().map_or("".into(), |token| token.token_id.to_string())}
@@ -96,7 +170,7 @@ pub fn Tokens(
                             view! {
                                 <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6)>
                                     "Minimum bid price:"
-                                    <NumberInput min=token.minimum_price get=bids_minimum_price set=set_bids_minimum_price/>
+                                    <NumberInput min=token.minimum_price.to_ether() get=bids_minimum_price set=set_bids_minimum_price/>
                                 </Stack>
                             }
                         } else {
@@ -110,7 +184,74 @@ pub fn Tokens(
                     <Button on_click=move |_| edit_token_submit.dispatch(()) color=ButtonColor::Primary>"Save"</Button>
                     <Button on_click=move |_| edited_token.set(None) color=ButtonColor::Secondary>"Cancel"</Button>
                 </ButtonWrapper>
+                <ConfirmationEstimate pending=edit_token_submit.pending()/>
             </ModalFooter>
         </Modal>
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mandelbrot_explorer::BigFloat;
+    use wasm_bindgen_test::*;
+    use web3::contract::tokens::Tokenizable;
+
+    use super::*;
+    use crate::evm::{mock::{output, selector, MockProvider}, types::OMAmount};
+
+    fn token(token_id: u128, owner: Address) -> Metadata {
+        Metadata {
+            token_id,
+            owner,
+            parent_id: 1,
+            field: crate::evm::types::Field {
+                x_min: BigFloat::from(0.0),
+                y_min: BigFloat::from(0.0),
+                x_max: BigFloat::from(0.1),
+                y_max: BigFloat::from(0.1),
+            },
+            locked_OM: OMAmount::from_ether(1.0),
+            minimum_price: OMAmount::default(),
+            layer: 2,
+            owned: true,
+            selected: false,
+        }
+    }
+
+    /// Blocker of burning token 2 owned by `owner` when it has `children` and `bids`.
+    async fn blocker(address: Address, owner: Address, children: usize, bids: usize) -> Option<BurnBlocker> {
+        let provider = MockProvider::new(move |_, params| {
+            let count = if params[0]["data"].as_str().unwrap().starts_with(&selector("getChildrenMetadata")) {
+                children
+            } else {
+                bids
+            };
+            let tokens = (0..count).map(|index| token(10 + index as u128, owner).into_token()).collect();
+            Ok(output(&[ethabi::Token::Array(tokens)]))
+        });
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        burn_blocker(erc1155_contract, Some(address), token(2, owner)).await
+    }
+
+    #[wasm_bindgen_test]
+    async fn burn_is_blocked_for_every_condition_with_its_reason() {
+        let owner = Address::repeat_byte(1);
+
+        let not_owner = blocker(Address::repeat_byte(2), owner, 0, 0).await;
+        assert!(not_owner == Some(BurnBlocker::NotOwner));
+        assert_eq!(not_owner.unwrap().reason(), "Only the owner can burn this NFT");
+
+        let has_children = blocker(owner, owner, 2, 1).await;
+        assert!(has_children == Some(BurnBlocker::HasChildren(2)));
+        assert_eq!(has_children.unwrap().reason(), "Cannot burn while 2 NFTs are minted inside");
+
+        let has_open_bids = blocker(owner, owner, 0, 3).await;
+        assert!(has_open_bids == Some(BurnBlocker::HasOpenBids(3)));
+        assert_eq!(has_open_bids.unwrap().reason(), "Cannot burn while 3 bids are open, approve or wait for them to be deleted");
+
+        assert!(blocker(owner, owner, 0, 0).await.is_none());
+    }
+}
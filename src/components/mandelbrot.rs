@@ -1,42 +1,135 @@
-use std::sync::{Arc, Mutex};
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::Duration};
 
 use leptos::*;
 
+use crate::state::Pointer;
+
+
+const REDRAW_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+static REDRAW_PENDING: AtomicBool = AtomicBool::new(false);
+
+
+/// Redraw the explorer, a redraw requested before the explorer is started is queued until it is.
+pub fn request_redraw(interface: &mandelbrot_explorer::Interface) {
+    match &interface.redraw {
+        Some(redraw) => redraw(),
+        None => REDRAW_PENDING.store(true, Ordering::Relaxed),
+    }
+}
+
+/// Size of the canvas in device pixels, rounded so that the drawing buffer and the sample always match.
+fn canvas_size(css_size: f64, device_pixel_ratio: f64) -> u32 {
+    (css_size.max(1.0) * device_pixel_ratio).round() as u32
+}
+
+fn flush_redraw(interface: Arc<Mutex<mandelbrot_explorer::Interface>>) {
+    if !REDRAW_PENDING.load(Ordering::Relaxed) {
+        return
+    }
+    let redraw = interface.lock().unwrap().redraw.clone();
+    match redraw {
+        Some(redraw) => {
+            REDRAW_PENDING.store(false, Ordering::Relaxed);
+            redraw();
+        }
+        // the redraw callback may only be installed once the explorer finishes starting
+        None => set_timeout(move || flush_redraw(interface), REDRAW_RETRY_INTERVAL),
+    }
+}
+
 
 #[component]
 pub fn Mandelbrot(
     interface: Arc<Mutex<mandelbrot_explorer::Interface>>,
+    pointer: StoredValue<Option<Pointer>>,
 ) -> impl IntoView {
     let canvas = create_node_ref::<html::Canvas>();
     let window = web_sys::window().unwrap();
     let height = window.inner_height().unwrap().as_f64().unwrap() + 1.0;
     let (get_height, set_height) = create_signal(height);
+    // changes with browser zoom and when moving between displays, both of which also resize the window
+    let (pixel_ratio, set_pixel_ratio) = create_signal(window.device_pixel_ratio());
+    let size = create_memo(move |_| canvas_size(get_height.get(), pixel_ratio.get()));
 
     let resize_callback = Arc::new({
         let window = window.clone();
         move || {
             let height = window.inner_height().unwrap().as_f64().unwrap() + 1.0;
-            set_height.set(height);
+            batch(|| {
+                set_height.set(height);
+                set_pixel_ratio.set(window.device_pixel_ratio());
+            });
         }
     });
 
     create_effect({
-        let window = window.clone();
         let interface = interface.clone();
-        move |_| interface.lock().unwrap().sample.borrow_mut().resize(
-            (get_height.get() * window.device_pixel_ratio()) as u32,
-            (get_height.get() * window.device_pixel_ratio()) as u32
-        )
+        move |_| interface.lock().unwrap().sample.borrow_mut().resize(size.get(), size.get())
+    });
+    canvas.on_load(|canvas| {
+        mandelbrot_explorer::start(Some((*canvas).clone()), interface.clone(), resize_callback);
+        flush_redraw(interface);
     });
-    canvas.on_load(|canvas| mandelbrot_explorer::start(Some((*canvas).clone()), interface, resize_callback));
 
     view! {
         <canvas
             _ref=canvas
-            width={let window = window.clone(); move || get_height.get() * window.device_pixel_ratio()}
-            height={let window = window.clone(); move || get_height.get() * window.device_pixel_ratio()}
+            width=move || size.get()
+            height=move || size.get()
             style:width=move || format!("{}px", get_height.get().max(1.0).to_string())
             style:height=move || format!("{}px", get_height.get().max(1.0).to_string())
+            on:mousemove=move |event| {
+                let size = get_height.get_untracked().max(1.0);
+                pointer.set_value(Some(Pointer {
+                    x: event.offset_x() as f64 / size,
+                    y: event.offset_y() as f64 / size,
+                    size,
+                }));
+            }
+            on:mouseleave=move |_| pointer.set_value(None)
         />
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, sync::atomic::AtomicUsize};
+
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn redraw_requested_before_the_start_is_flushed_once_installed() {
+        let interface = Arc::new(Mutex::new(mandelbrot_explorer::Interface::new(
+            Rc::new(RefCell::new(mandelbrot_explorer::PerturbationEngine::new(16, 16))),
+            mandelbrot_explorer::Coloring { max_iterations: 1600, offset: 0.0, length: 360.0 },
+        )));
+        request_redraw(&interface.lock().unwrap());
+
+        let redraws = Arc::new(AtomicUsize::new(0));
+        interface.lock().unwrap().redraw = Some(Arc::new({
+            let redraws = redraws.clone();
+            move || { redraws.fetch_add(1, Ordering::SeqCst); }
+        }));
+        flush_redraw(interface.clone());
+        assert_eq!(redraws.load(Ordering::SeqCst), 1);
+
+        // nothing is left queued
+        flush_redraw(interface.clone());
+        assert_eq!(redraws.load(Ordering::SeqCst), 1);
+        request_redraw(&interface.lock().unwrap());
+        assert_eq!(redraws.load(Ordering::SeqCst), 2);
+    }
+
+    #[wasm_bindgen_test]
+    fn canvas_size_is_in_whole_device_pixels() {
+        assert_eq!(canvas_size(800.0, 1.0), 800);
+        assert_eq!(canvas_size(800.0, 2.0), 1600);
+        // fractional ratios of zoomed browsers round to the closest device pixel
+        assert_eq!(canvas_size(801.0, 1.25), 1001);
+        assert_eq!(canvas_size(0.0, 2.0), 2);
+    }
+}
@@ -1,11 +1,15 @@
 mod about;
 mod account;
+mod confirm;
+mod confirmation;
 mod explorer;
 mod guide;
 mod inventory;
 mod mandelbrot;
+mod query;
 mod sales;
 mod state;
+mod status;
 
 use std::{sync::{Arc, Mutex}, rc::Rc, cell::RefCell};
 
@@ -15,6 +19,7 @@ use leptos_ethereum_provider::{ConnectButton, EthereumContextProvider};
 use leptos_router::Router;
 use mandelbrot_explorer::ISample;
 
+use crate::{state::Pointer, util::is_spectator};
 use {
     about::About,
     account::{Account, AccountButton},
@@ -24,6 +29,7 @@ use {
     inventory::Inventory,
     mandelbrot::Mandelbrot,
     sales::Sales,
+    status::{AppStatus, FetchProgress},
 };
 
 
@@ -41,40 +47,48 @@ pub fn App() -> impl IntoView {
         },
     )));
     
+    let spectator = is_spectator();
+    let pointer = store_value(None::<Pointer>);
     let account_open = create_rw_signal(false);
     let OM_balance = create_rw_signal(0.0);
 
     view! {
         <Root default_theme=LeptonicTheme::default()>
             <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(0.6) style="align-items: stretch;">
-                <Mandelbrot interface=interface.clone()/>
+                <Mandelbrot interface=interface.clone() pointer/>
                 <EthereumContextProvider>
-                    <StateContextProvider mandelbrot=interface.clone()>
+                    <StateContextProvider mandelbrot=interface.clone() pointer OM_balance spectator>
                         <Box style="position: relative; border: width: 100%; overflow: auto;">
                             <AppBar height=Size::Em(3.0) style="z-index: 1; background: var(--brand-color); color: white;">
                                 <H3 style="margin-left: 1em; color: white;">"Mandelbrot NFT"</H3>
-                                <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(1.0) style="margin-right: 1em">
-                                    <ConnectButton connected_html=view! {
-                                        <AccountButton
-                                            balance=OM_balance.read_only()
-                                            on_click=move |_| account_open.update(|account_open| {
-                                                *account_open = !*account_open;
-                                            })
-                                        />
-                                    }/>
-                                </Stack>
+                                <Show when=move || !spectator fallback=|| {}>
+                                    <Stack orientation=StackOrientation::Horizontal spacing=Size::Em(1.0) style="margin-right: 1em">
+                                        <ConnectButton connected_html=view! {
+                                            <AccountButton
+                                                balance=OM_balance.read_only()
+                                                on_click=move |_| account_open.update(|account_open| {
+                                                    *account_open = !*account_open;
+                                                })
+                                            />
+                                        }/>
+                                    </Stack>
+                                </Show>
                             </AppBar>
+                            <AppStatus/>
+                            <FetchProgress/>
                             <Router>
                                 <Tabs mount=Mount::Once>
                                     <Tab name="dapp" label="Explore".into_view()>
                                         <Explorer/>
                                     </Tab>
-                                    <Tab name="inventory" label="Inventory".into_view()>
-                                        <Inventory/>
-                                    </Tab>
-                                    <Tab name="sales" label="Sales".into_view()>
-                                        <Sales/>
-                                    </Tab>
+                                    {(!spectator).then(|| view! {
+                                        <Tab name="inventory" label="Inventory".into_view()>
+                                            <Inventory/>
+                                        </Tab>
+                                        <Tab name="sales" label="Sales".into_view()>
+                                            <Sales/>
+                                        </Tab>
+                                    })}
                                     <Tab name="description" label="Description".into_view()>
                                         <About/>
                                     </Tab>
@@ -0,0 +1,93 @@
+use std::future::Future;
+
+use leptos::*;
+
+use crate::{evm::contracts::ERC1155Contract, state::State};
+
+
+/// Reactive state of a contract query, see [`use_contract_query`].
+#[derive(Clone, Copy)]
+pub struct ContractQuery<T: 'static> {
+    pub data: Signal<Option<T>>,
+    pub error: Signal<Option<String>>,
+    pub loading: Signal<bool>,
+    pub refetch: Callback<()>,
+}
+
+
+/// Run `query` against the contract whenever `source` changes. `data` keeps the last successful result
+/// of the current source and `error` the reason of the last failure.
+pub fn use_contract_query<S, T, Fu>(
+    source: impl Fn() -> S + 'static,
+    query: impl Fn(ERC1155Contract, S) -> Fu + 'static,
+) -> ContractQuery<T>
+where
+    S: Clone + PartialEq + 'static,
+    T: Clone + 'static,
+    Fu: Future<Output = eyre::Result<T>> + 'static,
+{
+    contract_query(use_context::<State>().unwrap().erc1155_contract, source, query)
+}
+
+fn contract_query<S, T, Fu>(
+    erc1155_contract: ERC1155Contract,
+    source: impl Fn() -> S + 'static,
+    query: impl Fn(ERC1155Contract, S) -> Fu + 'static,
+) -> ContractQuery<T>
+where
+    S: Clone + PartialEq + 'static,
+    T: Clone + 'static,
+    Fu: Future<Output = eyre::Result<T>> + 'static,
+{
+    let resource = create_local_resource(source, move |source| {
+        let result = query(erc1155_contract.clone(), source);
+        async move {
+            result.await.map_err(|error| error.to_string())
+        }
+    });
+
+    ContractQuery {
+        data: Signal::derive(move || resource.get().and_then(|result| result.ok())),
+        error: Signal::derive(move || resource.get().and_then(|result| result.err())),
+        loading: resource.loading().into(),
+        refetch: Callback::new(move |_| resource.refetch()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::{evm::mock::MockProvider, util::sleep};
+
+    #[wasm_bindgen_test]
+    async fn query_goes_from_loading_to_data_or_error() {
+        let runtime = create_runtime();
+        let provider = MockProvider::new(|_, _| Ok(serde_json::Value::Null));
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), true, Arc::new(|_| {}));
+        let (token_id, set_token_id) = create_signal(2_u128);
+        let query = contract_query(erc1155_contract, move || token_id.get(), |_, token_id| async move {
+            sleep(Duration::from_millis(20)).await;
+            if token_id == 2 { Ok(token_id * 10) } else { Err(eyre::eyre!("no token {token_id}")) }
+        });
+
+        sleep(Duration::from_millis(5)).await;
+        assert!(query.loading.get_untracked());
+        assert_eq!(query.data.get_untracked(), None);
+
+        sleep(Duration::from_millis(40)).await;
+        assert!(!query.loading.get_untracked());
+        assert_eq!(query.data.get_untracked(), Some(20));
+        assert_eq!(query.error.get_untracked(), None);
+
+        set_token_id.set(3);
+        sleep(Duration::from_millis(60)).await;
+        assert_eq!(query.data.get_untracked(), None);
+        assert_eq!(query.error.get_untracked().as_deref(), Some("no token 3"));
+        runtime.dispose();
+    }
+}
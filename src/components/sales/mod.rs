@@ -1,16 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use leptonic::prelude::*;
 use leptos::*;
 use leptos_router::use_navigate;
 use mandelbrot_explorer::FrameColor;
 
-use crate::{state::State, util::preserve_log_level, evm::types::Metadata};
+use crate::{
+    components::{confirm::ConfirmModal, query::use_contract_query},
+    state::{LedgerAction, State},
+    util::{format_amount, preserve_query},
+    evm::{contracts::{self, ERC1155Contract}, types::{Metadata, OMAmount}},
+};
+
+
+/// Locked OM of `token` once `bids` inside of it are approved. The upstream share of every bid is distributed
+/// among all the ancestors, this assumes that the parent keeps all of it, so the result is an upper bound.
+fn projected_locked_OM(token: &Metadata, bids: &[Metadata], upstream_share: f64) -> OMAmount {
+    token.locked_OM + bids.iter()
+        .filter(|bid| bid.parent_id == token.token_id)
+        .map(|bid| OMAmount::from_ether(bid.locked_OM.to_ether() * upstream_share))
+        .sum()
+}
 
 
 #[component]
 pub fn Sales() -> impl IntoView {
     let state = use_context::<State>().unwrap();
+    let set_error = use_context::<WriteSignal<Option<contracts::Error>>>().unwrap();
 
     let refresh = create_action({
         let state = state.clone();
@@ -26,7 +42,7 @@ pub fn Sales() -> impl IntoView {
         let mandelbrot = state.mandelbrot.clone();
         move |token_id| {
             if let Some(token) = state.inventory.tokens.get().get(&token_id) {
-                use_navigate()(&preserve_log_level(format!("/tokens/{}", token_id)), Default::default());
+                use_navigate()(&preserve_query(format!("/tokens/{}", token_id)), Default::default());
                 let frame = token.to_frame(FrameColor::Blue);
                 mandelbrot.lock().unwrap().move_into_bounds(&frame.bounds)
             }
@@ -71,20 +87,77 @@ pub fn Sales() -> impl IntoView {
             .collect::<Vec<_>>()
     };
 
-    let total_approve_amount = move || selected_bids().iter().map(|bid| bid.locked_OM).sum::<f64>();
+    let total_approve_amount = move || selected_bids().iter().map(|bid| bid.locked_OM).sum::<OMAmount>();
 
-    let approve_bids = create_action({
-        let erc1155_contract = state.erc1155_contract.clone();
-        move |_| {
-            let erc1155_contract = erc1155_contract.clone();
+    let show_approve = create_rw_signal(false);
+    let upstream_share = use_contract_query(|| (), |erc1155_contract, _| async move {
+        erc1155_contract.get_upstream_share().await
+    }).data;
+    let approval_preview = move || {
+        let selected_bids = selected_bids();
+        let upstream_share = upstream_share.get()?;
+        let mut tokens: Vec<_> = state.inventory.tokens.get().into_values()
+            .filter(|token| selected_bids.iter().any(|bid| bid.parent_id == token.token_id))
+            .collect();
+        tokens.sort_by_key(|token| token.token_id);
+        Some(tokens.into_iter()
+            .map(|token| (token.token_id, token.locked_OM, projected_locked_OM(&token, &selected_bids, upstream_share)))
+            .collect::<Vec<_>>())
+    };
+
+    let approve_bids = {
+        let state = state.clone();
+        move || {
+            let state = state.clone();
             async move {
-                if let Some(address) = state.address.get_untracked() {
-                    let selected_bids: Vec<u128> = selected_bids().iter().map(|bid| bid.token_id).collect();
-                    erc1155_contract.batch_approve_bids(address, &selected_bids).await;
+                if let Some(address) = state.sender().await {
+                    let selected_bids = selected_bids();
+                    let parent_ids = selected_bids.iter().map(|bid| bid.parent_id).collect::<HashSet<_>>();
+
+                    let stale_bid_ids = stale_bid_ids(&state.erc1155_contract, &selected_bids).await;
+                    if !stale_bid_ids.is_empty() {
+                        state.sales.bids.update(|bids| {
+                            for bids in bids.values_mut() {
+                                bids.retain(|bid_id, _| !stale_bid_ids.contains(bid_id));
+                            }
+                        });
+                        set_error.set(Some(contracts::Error::StaleBids(stale_bid_ids.clone())));
+                    }
+
+                    let bid_ids: Vec<u128> = selected_bids.iter()
+                        .map(|bid| bid.token_id)
+                        .filter(|bid_id| !stale_bid_ids.contains(bid_id))
+                        .collect();
+                    if bid_ids.is_empty() {
+                        return false
+                    }
+                    let before = state.erc1155_contract.get_OM_balance(address).await.ok();
+                    if let Some(transaction_hash) = state.erc1155_contract.batch_approve_bids(address, &bid_ids).await {
+                        let mut approved_amount = 0.0;
+                        for bid in selected_bids.iter().filter(|bid| bid_ids.contains(&bid.token_id)) {
+                            state.ledger.record(LedgerAction::Approval, Some(bid.token_id), bid.locked_OM.to_ether());
+                            approved_amount += bid.locked_OM.to_ether();
+                        }
+                        // the approval is only submitted here, its effect on the balance is shown once it's mined
+                        spawn_local({
+                            let state = state.clone();
+                            async move {
+                                if state.erc1155_contract.confirmed(transaction_hash).await {
+                                    state.show_OM_change(address, before, approved_amount).await;
+                                }
+                            }
+                        });
+                        state.refresh_OM_balance();
+                        for parent_id in parent_ids {
+                            state.reload_bids(parent_id).await;
+                        }
+                        return true
+                    }
                 }
+                false
             }
         }
-    });
+    };
 
     view! {
         <Collapsibles default_on_open=OnOpen::CloseOthers>
@@ -100,6 +173,10 @@ pub fn Sales() -> impl IntoView {
                                 <CollapsibleHeader slot>
                                     <Button on_click={let zoom_token = zoom_token.clone(); move |_| zoom_token(token.token_id)}>"Zoom"</Button>
                                     {token.token_id}
+                                    {move || match state.sales.bids.with(|bids| bids.get(&token.token_id).map_or(0, |bids| bids.len())) {
+                                        0 => None,
+                                        count => Some(view! { <span class="badge">{format!(" {count} open bids")}</span> }),
+                                    }}
                                 </CollapsibleHeader>
                                 <CollapsibleBody slot>
                                     {
@@ -108,7 +185,7 @@ pub fn Sales() -> impl IntoView {
                                             let bids = move || state.sales.bids.get().get(&token.token_id).unwrap_or(&HashMap::new()).clone();
                                             let sorted_bids = move || {
                                                 let mut bids: Vec<Metadata> = bids().values().map(|bid| bid.clone()).collect();
-                                                bids.sort_by(|bid_a, bid_b| bid_b.locked_OM.partial_cmp(&bid_a.locked_OM).unwrap());
+                                                bids.sort_by(|bid_a, bid_b| bid_b.locked_OM.cmp(&bid_a.locked_OM));
                                                 bids
                                             };
                                             view! {
@@ -122,7 +199,7 @@ pub fn Sales() -> impl IntoView {
                                                                 set_state=move |state: bool| toggle_bid(token.token_id, bid.token_id, state)
                                                                 variant=ToggleVariant::Stationary
                                                             />
-                                                            {format!("{} {:?}", bid.locked_OM.to_string(), bid.owner)}
+                                                            {format!("{} {:?}", format_amount(bid.locked_OM.to_ether()), bid.owner)}
                                                             <Button on_click={let zoom_bid = zoom_bid.clone(); move |_| zoom_bid(token.token_id, bid.token_id)}>"Zoom"</Button>
                                                         </p>
                                                     }
@@ -138,9 +215,96 @@ pub fn Sales() -> impl IntoView {
             </Stack>
         </Collapsibles>
         <p>
-            {move || total_approve_amount()}
-            <Button on_click=move |_| approve_bids.dispatch(())>"Approve"</Button>
+            {move || format_amount(total_approve_amount().to_ether())}
+            <Button on_click=move |_| show_approve.set(true)>"Approve"</Button>
         </p>
+        <ConfirmModal
+            show=show_approve
+            title="Approve bids"
+            confirm_label="Approve"
+            on_confirm=approve_bids
+            on_close=move |_| show_approve.set(false)
+        >
+            <p>{move || format!("Approve {} bids locking {} OM in total?", selected_bids().len(), format_amount(total_approve_amount().to_ether()))}</p>
+            {move || match approval_preview() {
+                Some(preview) => preview.into_iter().map(|(token_id, before, after)| view! {
+                    <p>{format!("NFT {token_id} locked OM: {} → up to {} after approval", format_amount(before.to_ether()), format_amount(after.to_ether()))}</p>
+                }).collect_view(),
+                None => view! { <p>"Unable to preview the locked OM after approval"</p> }.into_view(),
+            }}
+        </ConfirmModal>
         <Button on_click=move |_| refresh.dispatch(())>"Refresh"</Button>
     }
 }
+
+
+/// Ids of selected bids which are no longer pending, as the selection could have gone stale if bids were deleted
+/// or approved elsewhere.
+async fn stale_bid_ids(erc1155_contract: &ERC1155Contract, selected_bids: &[Metadata]) -> Vec<u128> {
+    let mut stale_bid_ids = Vec::new();
+    for parent_id in selected_bids.iter().map(|bid| bid.parent_id).collect::<HashSet<_>>() {
+        if let Ok(bids) = erc1155_contract.get_bids(parent_id).await {
+            stale_bid_ids.extend(
+                selected_bids.iter()
+                    .filter(|bid| bid.parent_id == parent_id && !bids.iter().any(|bid_| bid_.token_id == bid.token_id))
+                    .map(|bid| bid.token_id)
+            );
+        }
+    }
+    stale_bid_ids
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mandelbrot_explorer::BigFloat;
+    use wasm_bindgen_test::*;
+    use web3::{contract::tokens::Tokenizable, types::Address};
+
+    use super::*;
+    use crate::evm::mock::{output, MockProvider};
+
+    fn bid(token_id: u128, parent_id: u128) -> Metadata {
+        Metadata {
+            token_id,
+            owner: Address::zero(),
+            parent_id,
+            field: crate::evm::types::Field {
+                x_min: BigFloat::from(0.0),
+                y_min: BigFloat::from(0.0),
+                x_max: BigFloat::from(1.0),
+                y_max: BigFloat::from(1.0),
+            },
+            locked_OM: OMAmount::from_ether(1.0),
+            minimum_price: OMAmount::default(),
+            layer: 1,
+            owned: false,
+            selected: true,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn bids_no_longer_pending_are_pruned_from_the_selection() {
+        let provider = MockProvider::new(|_, _| Ok(output(&[ethabi::Token::Array(vec![bid(3, 1).into_token()])])));
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let stale_bid_ids = stale_bid_ids(&erc1155_contract, &[bid(3, 1), bid(4, 1)]).await;
+        assert_eq!(stale_bid_ids, vec![4]);
+        assert_eq!(provider.params("eth_call").len(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn approval_adds_the_upstream_share_of_the_bids_inside_of_the_token() {
+        let token = Metadata { locked_OM: OMAmount::from_ether(10.0), ..bid(2, 1) };
+        let bids = [
+            Metadata { locked_OM: OMAmount::from_ether(4.0), ..bid(3, 2) },
+            Metadata { locked_OM: OMAmount::from_ether(6.0), ..bid(4, 2) },
+            // inside of another token
+            Metadata { locked_OM: OMAmount::from_ether(100.0), ..bid(5, 7) },
+        ];
+        assert_eq!(projected_locked_OM(&token, &bids, 0.5), OMAmount::from_ether(15.0));
+        assert_eq!(projected_locked_OM(&token, &[], 0.5), OMAmount::from_ether(10.0));
+    }
+}
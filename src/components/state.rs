@@ -6,12 +6,16 @@ use leptos_ethereum_provider::EthereumInterface;
 use web3::transports::{eip_1193::Eip1193, Either, Http};
 
 use crate::{
-    chain::sepolia_testnet,
+    chain::{expected_chain, is_expected_chain},
     evm::contracts::{self, ERC1155Contract},
-    state::{State, ExplorerState, InventoryState, SalesState},
+    state::{State, AppState, ContractFeatures, ExplorerState, InventoryState, SalesState, Ledger, Notes, Pointer, Progress, Debounce, BALANCE_REFRESH_DELAY},
+    util::{format_amount, load_local},
 };
 
 
+pub const OWNED_ONLY_KEY: &str = "owned_only";
+
+
 #[derive(Clone, Debug)]
 pub struct Web3(pub web3::Web3<Either<Eip1193, Http>>);
 
@@ -19,13 +23,16 @@ pub struct Web3(pub web3::Web3<Either<Eip1193, Http>>);
 #[component]
 pub fn StateContextProvider(
     mandelbrot: Arc<Mutex<mandelbrot_explorer::Interface>>,
+    pointer: StoredValue<Option<Pointer>>,
+    OM_balance: RwSignal<f64>,
+    spectator: bool,
     children: Children
 ) -> impl IntoView {
     let ethereum = use_context::<Option<EthereumInterface>>().unwrap();
     let transport = if let Some(ethereum) = &ethereum {
         Either::Left(Eip1193::new(ethereum.provider.clone()))
     } else {
-        Either::Right(Http::new(&sepolia_testnet().rpc_urls[0]).unwrap())
+        Either::Right(Http::new(&expected_chain().rpc_urls[0]).unwrap())
     };
     let web3 = web3::Web3::new(transport);
     provide_context(Web3(web3.clone()));
@@ -40,6 +47,12 @@ pub fn StateContextProvider(
                     contracts::Error::TokenNotEmpty => "It is not allowed to burn an NFT if it has minted NFTs inside".into(),
                     contracts::Error::BidNotFound => "Unable to find a bid with this Id".into(),
                     contracts::Error::BidTooLow => "Your bid is too low".into(),
+                    contracts::Error::BidBelowMinimum(minimum_price) => format!("Bid must be at least {} OM", format_amount(*minimum_price)),
+                    contracts::Error::BidAboveMaximum(cost) => format!("Bid would now cost {} OM including the minting fee, more than you agreed to", format_amount(*cost)),
+                    contracts::Error::BidNotIncreased => "New bid amount has to be higher than the current one".into(),
+                    contracts::Error::BidIncreaseIncomplete(bid_id) => format!(
+                        "Bid {bid_id} was withdrawn and its OM returned to your balance, but the increased bid could not be placed",
+                    ),
                     contracts::Error::MinimumBidTooLow => "Minimum bid for the NFT that you wish to mint is too low".into(),
                     contracts::Error::TooManyChildTokens => "This NFT cannot contain any more NFTs".into(),
                     contracts::Error::NoRightsToApproveBid => "You don't have the necessary rights to approve these bids".into(),
@@ -47,6 +60,22 @@ pub fn StateContextProvider(
                     contracts::Error::FieldOutside => "NFT that you are trying to mint has to be within the bounds of parent NFT".into(),
                     contracts::Error::FieldsOverlap => "NFT that you are trying to mint overlaps with another NFT".into(),
                     contracts::Error::FieldTooLarge => "NFT that you are trying to mint is too large".into(),
+                    contracts::Error::DeadlineExpired => "Transaction could not be completed before the deadline".into(),
+                    contracts::Error::ReadOnly => "Transactions are disabled in spectator mode".into(),
+                    contracts::Error::DuplicateSubmission => "An identical transaction is already being submitted".into(),
+                    contracts::Error::Reverted(reason) if reason.is_empty() => "Transaction would fail, so it was not submitted".into(),
+                    contracts::Error::Reverted(reason) => format!("Transaction would fail with \"{reason}\", so it was not submitted"),
+                    contracts::Error::StaleBids(bid_ids) => format!(
+                        "Bids {} are no longer available and were left out of the approval",
+                        bid_ids.iter().map(|bid_id| bid_id.to_string()).collect::<Vec<_>>().join(", "),
+                    ),
+                    contracts::Error::Reorged(transaction_hash) => format!(
+                        "Transaction {:?} was reverted by a chain reorganization, please refresh the page",
+                        transaction_hash,
+                    ),
+                    contracts::Error::Failed(transaction_hash) => format!("Transaction {:?} failed", transaction_hash),
+                    contracts::Error::Rejected => "Transaction was rejected in the wallet".into(),
+                    contracts::Error::Rpc(message) => format!("Request failed: {message}"),
                     contracts::Error::Other(message) => message.clone(),
                 })
             } else {
@@ -56,18 +85,37 @@ pub fn StateContextProvider(
     });
     provide_context(set_error);
 
+    let address = Signal::derive({
+        let ethereum = ethereum.clone();
+        move || ethereum.clone().filter(|_| !spectator).and_then(|ethereum| ethereum.address().get())
+    });
+    let correct_network = create_local_resource(move || address.get(), {
+        let web3 = web3.clone();
+        move |_| {
+            let web3 = web3.clone();
+            async move {
+                web3.eth().chain_id().await.ok().map(|chain_id| is_expected_chain(chain_id.as_u64()))
+            }
+        }
+    });
+    let wallet = ethereum.is_some();
+    let app_state = create_memo(move |_| AppState::new(spectator, wallet, correct_network.get().flatten(), address.get()));
+
     let state = State {
         mandelbrot: mandelbrot.clone(),
-        address: Signal::derive(move || {
-            ethereum.clone().and_then(|ethereum| ethereum.address().get())
-        }),
-        erc1155_contract: ERC1155Contract::new(&web3, Arc::new({
+        pointer,
+        address,
+        OM_balance,
+        balance_refresh: Debounce::new(BALANCE_REFRESH_DELAY),
+        OM_change: create_rw_signal(None),
+        app_state,
+        spectator,
+        erc1155_contract: ERC1155Contract::new(&web3, spectator, Arc::new({
             move |error| set_error.set(Some(error))
         })),
         explorer: ExplorerState {
-            nav_history: create_rw_signal(Vec::new()),
-            children: create_rw_signal(HashMap::new()),
-            bids: create_rw_signal(HashMap::new()),
+            owned_only: create_rw_signal(load_local(OWNED_ONLY_KEY).as_deref() == Some("true")),
+            ..ExplorerState::default()
         },
         inventory: InventoryState {
             tokens: create_rw_signal(HashMap::new()),
@@ -76,9 +124,20 @@ pub fn StateContextProvider(
         sales: SalesState {
             bids: create_rw_signal(HashMap::new()),
         },
+        ledger: Ledger(create_rw_signal(Vec::new())),
+        notes: Notes::load(),
+        features: create_rw_signal(ContractFeatures::default()),
+        progress: Progress::new(),
     };
     provide_context(state.clone());
 
+    spawn_local({
+        let state = state.clone();
+        async move {
+            state.detect_features().await;
+        }
+    });
+
     create_effect(move |_| {
         if state.address.get().is_some() {
             let state = state.clone();
@@ -0,0 +1,56 @@
+use leptonic::prelude::*;
+use leptos::*;
+
+use crate::{
+    chain::expected_chain,
+    evm::wallet,
+    state::{AppState, State},
+};
+
+
+/// Explains what is missing before the user can interact with the contract.
+#[component]
+pub fn AppStatus() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+
+    // the chain id is only read along with the address, so the page is reloaded to pick up the new network
+    let switch_network = create_action(|_: &()| async move {
+        match wallet::switch_chain(&expected_chain().chain_id).await {
+            Ok(()) => {
+                let _ = web_sys::window().unwrap().location().reload();
+            }
+            Err(error) => log::warn!("Could not switch network: {}", error),
+        }
+    });
+
+    move || {
+        let message = match state.app_state.get() {
+            AppState::NoWallet => Some("Install an Ethereum wallet to place bids and manage your NFTs".to_string()),
+            AppState::WrongNetwork => Some(format!("Switch your wallet to {} to place bids and manage your NFTs", expected_chain().chain_name)),
+            AppState::ReadOnly => Some("Spectator mode, transactions are disabled".to_string()),
+            AppState::Connecting | AppState::Ready { .. } => None,
+        };
+        let wrong_network = state.app_state.get() == AppState::WrongNetwork;
+        message.map(|message| view! {
+            <p style="margin: 0.5em 1em">
+                {message}
+                {wrong_network.then(|| view! {
+                    <Button on_click=move |_| switch_network.dispatch(()) disabled=Signal::derive(move || switch_network.pending().get())>
+                        "Switch network"
+                    </Button>
+                })}
+            </p>
+        })
+    }
+}
+
+
+/// Progress of the fetches in flight, hidden when there are none.
+#[component]
+pub fn FetchProgress() -> impl IntoView {
+    let state = use_context::<State>().unwrap();
+
+    move || state.progress.fraction().map(|fraction| view! {
+        <progress style="width: 100%; height: 0.3em; display: block" max="1" value=fraction/>
+    })
+}
@@ -0,0 +1,79 @@
+use std::{sync::OnceLock, time::Duration};
+
+use eyre::{eyre, Result};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::util::sleep;
+
+
+const BUNDLED_ERC1155_ABI: &[u8] = include_bytes!("../../resources/MandelbrotNFT.json");
+/// The app is only mounted once the ABI is loaded, so a hanging fetch must not keep the page blank.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+static ERC1155_ABI: OnceLock<Vec<u8>> = OnceLock::new();
+
+
+/// Fetch the ERC1155 ABI from `ERC1155_ABI_URL` if it is configured, so that the ABI can be updated without a release.
+/// Has to finish before any contract is constructed, the bundled ABI is used whenever the fetch fails.
+pub async fn load() {
+    if let Some(url) = option_env!("ERC1155_ABI_URL") {
+        load_from(url).await;
+    }
+}
+
+async fn load_from(url: &str) {
+    let result = match futures::future::select(Box::pin(fetch(url)), Box::pin(sleep(FETCH_TIMEOUT))).await {
+        futures::future::Either::Left((result, _)) => result,
+        futures::future::Either::Right(_) => Err(eyre!("timed out after {:?}", FETCH_TIMEOUT)),
+    };
+    match result {
+        Ok(abi) => {
+            let _ = ERC1155_ABI.set(abi);
+        }
+        Err(error) => {
+            log::warn!("failed to load ABI from {}, using the bundled one: {}", url, error);
+        }
+    }
+}
+
+pub fn erc1155() -> &'static [u8] {
+    ERC1155_ABI.get().map(|abi| abi.as_slice()).unwrap_or(BUNDLED_ERC1155_ABI)
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>> {
+    let response: web_sys::Response = JsFuture::from(web_sys::window().unwrap().fetch_with_str(url)).await
+        .map_err(|error| eyre!("{:?}", error))?
+        .dyn_into()
+        .map_err(|error| eyre!("{:?}", error))?;
+    if !response.ok() {
+        return Err(eyre!("status {}", response.status()))
+    }
+    let abi = JsFuture::from(response.text().map_err(|error| eyre!("{:?}", error))?).await
+        .map_err(|error| eyre!("{:?}", error))?
+        .as_string()
+        .ok_or_else(|| eyre!("response is not text"))?;
+    // make sure that the contract can be constructed from it
+    ethabi::Contract::load(abi.as_bytes())?;
+    Ok(abi.into_bytes())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    async fn document_which_is_not_an_abi_is_rejected() {
+        assert!(fetch("data:application/json,{}").await.is_err());
+        assert!(fetch("data:application/json,[]").await.is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    async fn bundled_abi_is_used_when_the_fetch_fails() {
+        load_from("http://127.0.0.1:9/MandelbrotNFT.json").await;
+        assert_eq!(erc1155(), BUNDLED_ERC1155_ABI);
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use eyre::{eyre, Result};
+use web3::{
+    types::{BlockId, BlockNumber, U64},
+    transports::{eip_1193::Eip1193, Either, Http},
+    Web3,
+};
+
+
+/// Number of most recent blocks used to average the block time.
+const BLOCK_TIME_SAMPLES: u64 = 10;
+/// Lower bound of the average, guards against several blocks sharing a timestamp.
+const MINIMUM_BLOCK_TIME: Duration = Duration::from_secs(1);
+
+
+/// Latest block number together with the average interval between recent blocks.
+pub async fn average_block_time(web3: &Web3<Either<Eip1193, Http>>) -> Result<(U64, Duration)> {
+    let latest = web3.eth().block_number().await?;
+    let samples = BLOCK_TIME_SAMPLES.min(latest.as_u64());
+    if samples == 0 {
+        return Err(eyre!("not enough blocks to estimate block time"))
+    }
+    let timestamp = |number: U64| async move {
+        web3.eth().block(BlockId::Number(BlockNumber::Number(number))).await?
+            .map(|block| block.timestamp.as_u64())
+            .ok_or_else(|| eyre!("block {} not found", number))
+    };
+    let elapsed = timestamp(latest).await?.saturating_sub(timestamp(latest - samples).await?);
+    Ok((latest, Duration::from_secs(elapsed).div_f64(samples as f64).max(MINIMUM_BLOCK_TIME)))
+}
+
+pub fn estimate_confirmation_time(average_block_time: Duration, remaining_confirmations: u64) -> Duration {
+    average_block_time * remaining_confirmations as u32
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+    use web3::types::{Block, H256, U256};
+
+    use super::*;
+    use crate::evm::mock::MockProvider;
+
+    #[wasm_bindgen_test]
+    fn estimate_is_the_average_block_time_per_remaining_confirmation() {
+        assert_eq!(estimate_confirmation_time(Duration::from_secs(12), 3), Duration::from_secs(36));
+        assert_eq!(estimate_confirmation_time(Duration::from_millis(2500), 2), Duration::from_secs(5));
+    }
+
+    #[wasm_bindgen_test]
+    async fn block_time_is_averaged_over_recent_blocks() {
+        // blocks get slower, 10 blocks took 150 seconds in total
+        let provider = MockProvider::new(|method, params| match method {
+            "eth_blockNumber" => Ok(serde_json::json!("0x14")),
+            _ => {
+                let number = U64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                let timestamp = if number == U64::from(20) { 1150 } else { 1000 };
+                Ok(serde_json::to_value(Block::<H256> {
+                    number: Some(number),
+                    timestamp: U256::from(timestamp),
+                    ..Default::default()
+                }).unwrap())
+            }
+        });
+
+        let (latest, average) = average_block_time(provider.web3()).await.unwrap();
+        assert_eq!(latest, U64::from(20));
+        assert_eq!(average, Duration::from_secs(15));
+        assert_eq!(provider.params("eth_getBlockByNumber").iter().map(|params| params[0].clone()).collect::<Vec<_>>(), vec![
+            serde_json::json!("0x14"),
+            serde_json::json!("0xa"),
+        ]);
+    }
+}
@@ -1,18 +1,43 @@
-use std::sync::Arc;
+use std::{collections::{HashMap, HashSet}, sync::{Arc, Mutex}, time::Duration};
 
 use async_trait::async_trait;
-use eyre::Result;
+use ethabi::token::Token;
+use eyre::{eyre, Result};
+use leptos::spawn_local;
 use web3::{
-    contract::{tokens::Tokenize, Contract, Options},
-    types::{Address, H256, U256, TransactionReceipt},
+    api::Eth,
+    contract::{ens::Ens, tokens::{Detokenize, Tokenize}, Contract, Options},
+    types::{Address, BlockId, BlockNumber, Bytes, CallRequest, FilterBuilder, Log, H256, U256, U64, TransactionReceipt},
     transports::{eip_1193::Eip1193, Either, Http},
     Web3
 };
 
-use super::types::{Field, Metadata};
+use crate::util::sleep;
+use super::{abi, types::{Field, Metadata, NodeSnapshot, OMAmount, SubdivisionRule}};
 
 
 const OM: U256 = U256([0, 0, 0, 0]);
+pub const ERC1155_INTERFACE_ID: [u8; 4] = [0xd9, 0xb6, 0x7a, 0x26];
+pub const ERC1155_METADATA_URI_INTERFACE_ID: [u8; 4] = [0x0e, 0x89, 0x34, 0x1c];
+/// Blocks that have to be mined on top of a transaction before it is considered confirmed.
+const DEFAULT_CONFIRMATIONS: usize = 1;
+const ESTIMATE_GAS_ATTEMPTS: u32 = 3;
+const ESTIMATE_GAS_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_GAS_LIMIT: u64 = 500_000;
+const DEFAULT_QUERY_ATTEMPTS: u32 = 3;
+const QUERY_BACKOFF: Duration = Duration::from_millis(250);
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(180);
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const REORG_WATCH_POLLS: u32 = 12;
+const REORG_WATCH_INTERVAL: Duration = Duration::from_secs(10);
+const MAX_HISTORY_SNAPSHOTS: usize = 64;
+const MINT_POLL_INTERVAL: Duration = Duration::from_secs(15);
+const DEFAULT_DEPLOYMENT_BLOCK: u64 = 0;
+const LOG_BLOCK_RANGE: u64 = 10_000;
+/// Selector of Solidity's `Error(string)`, used by `require` and `revert` with a message.
+const REVERT_REASON_SELECTOR: &str = "0x08c379a0";
+/// EIP-1193 error code of a request the user rejected in the wallet.
+const USER_REJECTED_CODE: i64 = 4001;
 const CALLDATA: &[u8] = &[87, 114, 97, 112, 112, 101, 100, 32, 79, 77, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 20, 119, 79, 77, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 6, 18];
 
 
@@ -22,6 +47,10 @@ pub enum Error {
     TokenNotEmpty, // Cannot burn token if it has children
     BidNotFound,
     BidTooLow, // Bid must exceed or equal minimum bid price
+    BidBelowMinimum(f64), // Bid is below the minimum bid price, caught before submitting
+    BidAboveMaximum(f64), // Bid together with the minting fee would cost more than the user agreed to, caught before submitting
+    BidNotIncreased, // New amount of an edited bid has to exceed the current one
+    BidIncreaseIncomplete(u128), // Bid was deleted to be increased, but placing it again failed
     MinimumBidTooLow, // Child's minimum bid has to be at least as much as parent's
     TooManyChildTokens, // A maximum of MAX_CHILDREN child tokens can be minted
     NoRightsToApproveBid, // Only the owner of parent token can approve the bid
@@ -29,6 +58,15 @@ pub enum Error {
     FieldOutside, // Token has to be within the field of its parent
     FieldsOverlap, // Sibling fields cannot overlap
     FieldTooLarge, // Token's field cannot exceed MAXIMUM_FIELD_PORTION % of its parent's
+    DeadlineExpired, // Transaction could not be completed before the deadline set by the user
+    ReadOnly, // Transactions are disabled in spectator mode
+    DuplicateSubmission, // Identical transaction is still being submitted
+    Reverted(String), // Contract reverted with a reason string
+    StaleBids(Vec<u128>), // Selected bids were deleted or approved in the meantime
+    Reorged(H256), // Confirmed transaction is no longer part of the canonical chain
+    Failed(H256), // Transaction was mined but reverted
+    Rejected, // User rejected the request in the wallet
+    Rpc(String), // Node or wallet failed the request for a reason other than a revert
     Other(String),
 }
 
@@ -53,36 +91,425 @@ impl Error {
 }
 
 
+/// How fast transactions should be mined, trading off the gas price paid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GasSpeed {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl GasSpeed {
+    pub const ALL: [Self; 3] = [Self::Slow, Self::Normal, Self::Fast];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Slow => "Slow",
+            Self::Normal => "Normal",
+            Self::Fast => "Fast",
+        }
+    }
+
+    /// Percentage of the current gas price offered by transactions of this speed.
+    fn percentage(&self) -> u64 {
+        match self {
+            Self::Slow => 90,
+            Self::Normal => 100,
+            Self::Fast => 125,
+        }
+    }
+
+    pub fn gas_price(&self, current: U256) -> U256 {
+        current * U256::from(self.percentage()) / U256::from(100)
+    }
+}
+
+static GAS_SPEED: Mutex<GasSpeed> = Mutex::new(GasSpeed::Normal);
+
+/// Speed of every transaction submitted from now on.
+pub fn set_gas_speed(speed: GasSpeed) {
+    *GAS_SPEED.lock().unwrap() = speed;
+}
+
+pub fn gas_speed() -> GasSpeed {
+    *GAS_SPEED.lock().unwrap()
+}
+
+
+/// Progress of a transaction, reported while its submitting method is still waiting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TxStage {
+    Submitted(H256),
+    Confirmed,
+    Failed,
+}
+
+pub type OnStage = Arc<dyn Fn(TxStage)>;
+
+fn report_stage(on_stage: &Option<OnStage>, stage: TxStage) {
+    if let Some(on_stage) = on_stage {
+        on_stage(stage);
+    }
+}
+
+
+/// Outcome of a transaction that was submitted successfully.
+pub enum TxStatus {
+    Confirmed(TransactionReceipt),
+    /// Transaction did not confirm within `CONFIRMATION_TIMEOUT`, it can still be tracked by its hash.
+    Pending(H256),
+}
+
+
+/// Write to one of the contract's functions, collected to be submitted as a batch through `write_sequentially`.
+#[derive(Clone, Debug)]
+pub struct EncodedCall {
+    pub method: &'static str,
+    pub params: Vec<Token>,
+}
+
+impl EncodedCall {
+    pub fn new(method: &'static str, params: impl Tokenize) -> Self {
+        Self { method, params: params.into_tokens() }
+    }
+}
+
+
+/// Point in time after which the app stops waiting for a transaction and considers it failed.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline(f64);
+
+impl Deadline {
+    pub fn after(duration: Duration) -> Self {
+        Self(js_sys::Date::now() + duration.as_millis() as f64)
+    }
+
+    pub fn remaining(&self) -> Duration {
+        Duration::from_millis((self.0 - js_sys::Date::now()).max(0.0) as u64)
+    }
+
+    pub fn expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+
+/// Contract error carried by a reverted call, if the node returned one.
+/// Wallets nest the revert data under `originalError`, plain RPC nodes return it directly as `data`.
+fn decode_error(error: &web3::contract::Error) -> Option<Error> {
+    let web3::contract::Error::Api(web3::error::Error::Rpc(rpc_error)) = error else {
+        return None
+    };
+    if rpc_error.code.code() == USER_REJECTED_CODE {
+        return Some(Error::Rejected)
+    }
+    let (data, message) = match rpc_error.data.as_ref().and_then(|data| data.get("originalError")) {
+        Some(object) => (object.get("data").and_then(|data| data.as_str()), object.get("message").and_then(|message| message.as_str())),
+        None => (rpc_error.data.as_ref().and_then(|data| data.as_str()), Some(rpc_error.message.as_str())),
+    };
+    match (data, message) {
+        (Some(data), _) if data.starts_with(REVERT_REASON_SELECTOR) => {
+            let data: Bytes = serde_json::from_value(data.into()).ok()?;
+            let reason = ethabi::decode(&[ethabi::ParamType::String], data.0.get(4..)?).ok()?.pop()?.into_string()?;
+            Some(Error::Reverted(reason))
+        }
+        (Some(data), Some(message)) if data.len() >= 10 => Some(Error::from_code(&data[..10], message)),
+        (None, Some(message)) if message.contains("execution reverted") => {
+            Some(Error::Reverted(message.trim_start_matches("execution reverted").trim_start_matches(':').trim().into()))
+        }
+        _ => Some(Error::Rpc(rpc_error.message.clone())),
+    }
+}
+
+/// Block that the contract was deployed in, event logs are never searched before it.
+/// Configurable through the `DEPLOYMENT_BLOCK` env variable, public RPC nodes reject log queries spanning the whole chain.
+fn deployment_block() -> U64 {
+    option_env!("DEPLOYMENT_BLOCK")
+        .and_then(|block| block.parse().ok())
+        .unwrap_or(DEFAULT_DEPLOYMENT_BLOCK)
+        .into()
+}
+
+/// Read query retried with exponential backoff when the RPC node fails to answer, a single flaky response
+/// shouldn't fail the read. Errors returned by the contract itself are not retried.
+async fn query_with_retries<R: Detokenize, P: Tokenize + Clone>(contract: &Contract<Either<Eip1193, Http>>, method: &str, params: P, from: Option<Address>, options: Options, block: Option<BlockId>) -> web3::contract::Result<R> {
+    let mut backoff = QUERY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match contract.query(method, params.clone(), from, options.clone(), block).await {
+            Err(web3::contract::Error::Api(error @ (
+                web3::Error::Unreachable | web3::Error::Transport(_) | web3::Error::InvalidResponse(_) | web3::Error::Io(_)
+            ))) if attempt < query_attempts() => {
+                log::warn!("{} query failed (attempt {}/{}): {:?}", method, attempt, query_attempts(), error);
+                sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Input of the ERC1155 supply extension's `totalSupply(uint256)` for token `id`.
+fn total_supply_calldata(id: U256) -> Vec<u8> {
+    [&ethabi::short_signature("totalSupply", &[ethabi::ParamType::Uint(256)])[..], &ethabi::encode(&[Token::Uint(id)])].concat()
+}
+
+/// Recipient of a mint `log` if it minted `token_id`, either alone or as part of a batch.
+fn minted_to(single: &ethabi::Event, batch: &ethabi::Event, log: Log, token_id: u128) -> Result<Option<Address>> {
+    let signature = log.topics.first().copied();
+    let raw_log = ethabi::RawLog { topics: log.topics, data: log.data.0 };
+    let (log, ids) = if signature == Some(single.signature()) {
+        (single.parse_log(raw_log)?, "id")
+    } else if signature == Some(batch.signature()) {
+        (batch.parse_log(raw_log)?, "ids")
+    } else {
+        return Ok(None)
+    };
+    let param = |name: &str| log.params.iter().find(|param| param.name == name).map(|param| param.value.clone());
+    let ids = match param(ids) {
+        Some(Token::Array(ids)) => ids,
+        Some(id) => vec![id],
+        None => vec![],
+    };
+    if ids.into_iter().any(|id| id.into_uint() == Some(U256::from(token_id))) {
+        Ok(param("to").and_then(|to| to.into_address()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Gas limit used when estimation keeps failing, configurable through the `DEFAULT_GAS_LIMIT` env variable.
+pub fn default_gas_limit() -> U256 {
+    option_env!("DEFAULT_GAS_LIMIT")
+        .and_then(|gas| U256::from_dec_str(gas).ok())
+        .unwrap_or(U256::from(DEFAULT_GAS_LIMIT))
+}
+
+/// Times a read query is attempted before its failure is returned, configurable through the `QUERY_ATTEMPTS` env variable.
+fn query_attempts() -> u32 {
+    option_env!("QUERY_ATTEMPTS")
+        .and_then(|attempts| attempts.parse().ok())
+        .unwrap_or(DEFAULT_QUERY_ATTEMPTS)
+}
+
+/// Whether the receipt of the transaction went missing or moved away from `block_hash` within `REORG_WATCH_POLLS`
+/// polls `interval` apart.
+async fn reorged(eth: &Eth<Either<Eip1193, Http>>, transaction_hash: H256, block_hash: H256, interval: Duration) -> bool {
+    for _ in 0..REORG_WATCH_POLLS {
+        sleep(interval).await;
+        match eth.transaction_receipt(transaction_hash).await {
+            Ok(Some(receipt)) if receipt.block_hash == Some(block_hash) => {}
+            Ok(_) => return true,
+            // node could be temporarily unavailable, check again on the next poll
+            Err(_) => {}
+        }
+    }
+    false
+}
+
+
+/// Blocks that have to be mined on top of a transaction for it to count as confirmed,
+/// configurable through the `CONFIRMATIONS` env variable.
+pub fn default_confirmations() -> usize {
+    option_env!("CONFIRMATIONS")
+        .and_then(|confirmations| confirmations.parse().ok())
+        .unwrap_or(DEFAULT_CONFIRMATIONS)
+}
+
+
+/// Transport for read queries, a public RPC configured through the `READ_RPC_URL` env variable takes them
+/// off the wallet's node. Transactions always go through `web3`, which is also used for reads without it.
+fn read_web3(web3: &Web3<Either<Eip1193, Http>>) -> Web3<Either<Eip1193, Http>> {
+    match option_env!("READ_RPC_URL").map(Http::new) {
+        Some(Ok(transport)) => Web3::new(Either::Right(transport)),
+        Some(Err(error)) => {
+            log::warn!("Invalid READ_RPC_URL, reading through the wallet: {}", error);
+            web3.clone()
+        }
+        None => web3.clone(),
+    }
+}
+
+
+/// Submission claimed through `CallWrapper::claim_call`, released when dropped.
+struct PendingCall {
+    pending_calls: Arc<Mutex<HashSet<Vec<u8>>>>,
+    key: Vec<u8>,
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        self.pending_calls.lock().unwrap().remove(&self.key);
+    }
+}
+
+
 #[async_trait(?Send)]
 trait CallWrapper {
+    fn web3(&self) -> &Web3<Either<Eip1193, Http>>;
+
     fn contract(&self) -> &Contract<Either<Eip1193, Http>>;
 
-    fn _handle_error(&self, error: Error);
+    fn error_handler(&self) -> Arc<dyn Fn(Error)>;
+
+    fn _handle_error(&self, error: Error) {
+        (self.error_handler())(error);
+    }
+
+    fn read_only(&self) -> bool;
+
+    fn confirmations(&self) -> usize;
+
+    fn pending_calls(&self) -> Arc<Mutex<HashSet<Vec<u8>>>>;
+
+    /// Claim the submission of `method` with these exact params, `None` while an identical one is still pending.
+    fn claim_call<T: Tokenize>(&self, method: &str, params: T, sender: Address) -> Option<PendingCall> {
+        let mut key = method.as_bytes().to_vec();
+        key.extend(sender.as_bytes());
+        key.extend(ethabi::encode(&params.into_tokens()));
+        let pending_calls = self.pending_calls();
+        if pending_calls.lock().unwrap().insert(key.clone()) {
+            Some(PendingCall { pending_calls, key })
+        } else {
+            self._handle_error(Error::DuplicateSubmission);
+            None
+        }
+    }
 
+    /// Errors that aren't known contract errors are still reported, so that no failed transaction goes unnoticed.
     fn process_error(&self, error: web3::contract::Error) {
-        if let web3::contract::Error::Api(web3::error::Error::Rpc(rpc_error)) = &error {
-            if let Some(object) = &rpc_error.data {
-                if let Some(object) = object.get("originalError") {
-                    if let (Some(jsonrpc_core::types::Value::String(code)), Some(jsonrpc_core::types::Value::String(message))) = (object.get("data"), object.get("message")) {
-                        self._handle_error(Error::from_code(code, message));
+        self._handle_error(decode_error(&error).unwrap_or_else(|| Error::Other(error.to_string())));
+    }
+
+    fn write_forbidden(&self) -> bool {
+        if self.read_only() {
+            self._handle_error(Error::ReadOnly);
+        }
+        self.read_only()
+    }
+
+    fn deadline_expired(&self, deadline: Option<Deadline>) -> bool {
+        if deadline.is_some_and(|deadline| deadline.expired()) {
+            self._handle_error(Error::DeadlineExpired);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Unlike `call_with_confirmations`, gives up waiting after `timeout` and hands back the hash of the still pending transaction.
+    async fn call_with_timeout<T: Clone + Tokenize + std::marker::Send>(&self, method: &str, params: T, sender: Address, timeout: Duration, on_stage: Option<OnStage>) -> Option<TxStatus> {
+        let Some(transaction_hash) = self.call(method, params, sender, None).await else {
+            report_stage(&on_stage, TxStage::Failed);
+            return None
+        };
+        report_stage(&on_stage, TxStage::Submitted(transaction_hash));
+        match self.wait_for_receipt(transaction_hash, timeout).await {
+            Some(receipt) if receipt.status == Some(0.into()) => {
+                self._handle_error(Error::Failed(transaction_hash));
+                report_stage(&on_stage, TxStage::Failed);
+                None
+            }
+            Some(receipt) => {
+                report_stage(&on_stage, TxStage::Confirmed);
+                Some(TxStatus::Confirmed(receipt))
+            }
+            None => Some(TxStatus::Pending(transaction_hash)),
+        }
+    }
+
+    /// Receipt of the transaction once it has enough confirmations, `None` if that takes longer than `timeout`.
+    async fn wait_for_receipt(&self, transaction_hash: H256, timeout: Duration) -> Option<TransactionReceipt> {
+        let deadline = Deadline::after(timeout);
+        loop {
+            if let Ok(Some(receipt)) = self.web3().eth().transaction_receipt(transaction_hash).await {
+                if let (Some(block_number), Ok(latest)) = (receipt.block_number, self.web3().eth().block_number().await) {
+                    if block_number.as_u64() + self.confirmations() as u64 <= latest.as_u64() {
+                        self.watch_reorg(&receipt);
+                        return Some(receipt)
                     }
                 }
             }
+            if deadline.expired() {
+                return None
+            }
+            sleep(RECEIPT_POLL_INTERVAL).await;
         }
     }
 
-    async fn call<T: Clone + Tokenize + std::marker::Send>(&self, method: &str, params: T, sender: Address) -> Option<H256> {
-        match self.contract().estimate_gas(method, params.clone(), sender, Options::default()).await {
-            Ok(gas) => {
-                log::info!("{} GAS: {:?}", method, gas);
+    /// Keep polling the receipt for a while after confirmation, the block it was confirmed in could still be reorganized away.
+    fn watch_reorg(&self, receipt: &TransactionReceipt) {
+        let Some(block_hash) = receipt.block_hash else {
+            return
+        };
+        let eth = self.web3().eth();
+        let transaction_hash = receipt.transaction_hash;
+        let handle_error = self.error_handler();
+        spawn_local(async move {
+            if reorged(&eth, transaction_hash, block_hash, REORG_WATCH_INTERVAL).await {
+                handle_error(Error::Reorged(transaction_hash));
             }
-            Err(error) => {
-                self.process_error(error);
-                return None
+        });
+    }
+
+    /// Estimation failures are retried with backoff and eventually replaced by the default gas limit. Only a revert,
+    /// a contract error or the user rejecting the request aborts the call, so that a transaction doomed to fail is never
+    /// submitted, any other node error such as rate limiting is retried like an unreachable node.
+    async fn estimate_gas<T: Clone + Tokenize + std::marker::Send>(&self, method: &str, params: T, sender: Address) -> Option<Options> {
+        let mut backoff = ESTIMATE_GAS_BACKOFF;
+        for attempt in 1..=ESTIMATE_GAS_ATTEMPTS {
+            match self.contract().estimate_gas(method, params.clone(), sender, Options::default()).await {
+                Ok(gas) => {
+                    log::info!("{} GAS: {:?}", method, gas);
+                    return Some(self.with_gas_speed(Options::default(), gas_speed()).await)
+                }
+                Err(error) => {
+                    match decode_error(&error) {
+                        Some(Error::Rpc(_)) | None => {}
+                        Some(error) => {
+                            self._handle_error(error);
+                            return None
+                        }
+                    }
+                    log::warn!("{} gas estimation failed (attempt {}/{}): {:?}", method, attempt, ESTIMATE_GAS_ATTEMPTS, error);
+                }
+            }
+            if attempt < ESTIMATE_GAS_ATTEMPTS {
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        let gas = default_gas_limit();
+        log::warn!("{} falling back to default gas limit: {:?}", method, gas);
+        Some(self.with_gas_speed(Options::with(|options| options.gas = Some(gas)), gas_speed()).await)
+    }
+
+    /// Normal speed leaves the gas price up to the wallet, other speeds offer a share of the current one.
+    async fn with_gas_speed(&self, mut options: Options, speed: GasSpeed) -> Options {
+        if speed != GasSpeed::Normal {
+            match self.web3().eth().gas_price().await {
+                Ok(gas_price) => options.gas_price = Some(speed.gas_price(gas_price)),
+                Err(error) => log::warn!("Unable to get the gas price, leaving it to the wallet: {:?}", error),
             }
         }
+        options
+    }
+
+    async fn call<T: Clone + Tokenize + std::marker::Send>(&self, method: &str, params: T, sender: Address, deadline: Option<Deadline>) -> Option<H256> {
+        if self.write_forbidden() || self.deadline_expired(deadline) {
+            return None
+        }
+        let _pending_call = self.claim_call(method, params.clone(), sender)?;
+
+        let options = self.estimate_gas(method, params.clone(), sender).await?;
+
+        if self.deadline_expired(deadline) {
+            return None
+        }
 
-        match self.contract().call(method, params, sender, Options::default()).await {
+        match self.contract().call(method, params, sender, options).await {
             Ok(tx_hash) => {
                 Some(tx_hash)
             }
@@ -93,23 +520,42 @@ trait CallWrapper {
         }
     }
 
-    async fn call_with_confirmations<T: Clone + Tokenize + std::marker::Send>(&self, method: &str, params: T, sender: Address) -> Option<TransactionReceipt> {
-        match self.contract().estimate_gas(method, params.clone(), sender, Options::default()).await {
-            Ok(gas) => {
-                log::info!("{} GAS: {:?}", method, gas);
-            }
-            Err(error) => {
-                self.process_error(error);
-                return None
-            }
+    async fn call_with_confirmations<T: Clone + Tokenize + std::marker::Send>(&self, method: &str, params: T, sender: Address, deadline: Option<Deadline>) -> Option<TransactionReceipt> {
+        if self.write_forbidden() || self.deadline_expired(deadline) {
+            return None
+        }
+        let _pending_call = self.claim_call(method, params.clone(), sender)?;
+
+        let options = self.estimate_gas(method, params.clone(), sender).await?;
+
+        if self.deadline_expired(deadline) {
+            return None
         }
 
-        match self.contract().call_with_confirmations(method, params, sender, Options::default(), 1).await {
+        let confirmation = self.contract().call_with_confirmations(method, params, sender, options, self.confirmations());
+        let result = if let Some(deadline) = deadline {
+            match futures::future::select(Box::pin(confirmation), Box::pin(sleep(deadline.remaining()))).await {
+                futures::future::Either::Left((result, _)) => result,
+                futures::future::Either::Right(_) => {
+                    self._handle_error(Error::DeadlineExpired);
+                    return None
+                }
+            }
+        } else {
+            confirmation.await
+        };
+
+        match result {
+            Ok(receipt) if receipt.status == Some(0.into()) => {
+                self._handle_error(Error::Failed(receipt.transaction_hash));
+                None
+            }
             Ok(receipt) => {
+                self.watch_reorg(&receipt);
                 Some(receipt)
             }
             Err(error) => {
-                // self.process_error(error);
+                self.process_error(error);
                 return None
             }
         }
@@ -117,82 +563,261 @@ trait CallWrapper {
 }
 
 
+/// Arguments of `mintNFT`, shared by the transaction and its gas estimate.
+fn mint_params(parent_id: u128, recipient: Address, field: Field) -> (U256, Address, Field) {
+    (U256::from(parent_id), recipient, field)
+}
+
+
 #[derive(Clone)]
 pub struct ERC1155Contract {
+    web3: Web3<Either<Eip1193, Http>>,
     contract: Contract<Either<Eip1193, Http>>,
+    read_only: bool,
+    confirmations: usize,
+    supported_interfaces: Arc<Mutex<HashMap<[u8; 4], bool>>>,
+    creators: Arc<Mutex<HashMap<u128, Address>>>,
+    pending_calls: Arc<Mutex<HashSet<Vec<u8>>>>,
     handle_error: Arc<dyn Fn(Error)>,
+    /// Same contract on the transport for reads, see `read_web3`.
+    reader: Contract<Either<Eip1193, Http>>,
+    read_web3: Web3<Either<Eip1193, Http>>,
+    multicall: Option<Multicall3Contract>,
 }
 
 #[async_trait]
 impl CallWrapper for ERC1155Contract {
+    fn web3(&self) -> &Web3<Either<Eip1193, Http>> {
+        &self.web3
+    }
+
     fn contract(&self) -> &Contract<Either<Eip1193, Http>> {
         &self.contract
     }
 
-    fn _handle_error(&self, error: Error) {
-        (self.handle_error)(error);
+    fn error_handler(&self) -> Arc<dyn Fn(Error)> {
+        self.handle_error.clone()
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn confirmations(&self) -> usize {
+        self.confirmations
+    }
+
+    fn pending_calls(&self) -> Arc<Mutex<HashSet<Vec<u8>>>> {
+        self.pending_calls.clone()
     }
 }
 
 impl ERC1155Contract {
-    pub fn new(web3: &Web3<Either<Eip1193, Http>>, handle_error: Arc<dyn Fn(Error)>) -> Self {
+    pub fn new(web3: &Web3<Either<Eip1193, Http>>, read_only: bool, handle_error: Arc<dyn Fn(Error)>) -> Self {
+        let address: Address = env!("ERC1155_CONTRACT_ADDRESS").trim_start_matches("0x").parse().unwrap();
+        let read_web3 = read_web3(web3);
         Self {
-            contract: Contract::from_json(
-                web3.eth(),
-                env!("ERC1155_CONTRACT_ADDRESS").trim_start_matches("0x").parse().unwrap(),
-                include_bytes!("../../resources/MandelbrotNFT.json"),
-            ).unwrap(),
+            web3: web3.clone(),
+            contract: Contract::from_json(web3.eth(), address, abi::erc1155()).unwrap(),
+            read_only,
+            confirmations: default_confirmations(),
+            supported_interfaces: Arc::new(Mutex::new(HashMap::new())),
+            creators: Arc::new(Mutex::new(HashMap::new())),
+            pending_calls: Arc::new(Mutex::new(HashSet::new())),
             handle_error,
+            reader: Contract::from_json(read_web3.eth(), address, abi::erc1155()).unwrap(),
+            multicall: Multicall3Contract::new(&read_web3),
+            read_web3,
         }
     }
 
+    /// Send reads through `read_web3` instead of the transport that transactions are submitted with.
+    pub fn with_read_web3(mut self, read_web3: &Web3<Either<Eip1193, Http>>) -> Self {
+        self.reader = Contract::from_json(read_web3.eth(), self.contract.address(), abi::erc1155()).unwrap();
+        self.multicall = Multicall3Contract::new(read_web3);
+        self.read_web3 = read_web3.clone();
+        self
+    }
+
+    /// Require more confirmations than `default_confirmations` on chains that reorganize often.
+    pub fn with_confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
     pub fn address(&self) -> Address {
         self.contract.address()
     }
 
-    pub async fn get_OM_balance(&self, address: Address) -> Result<f64> {
-        let result: web3::contract::Result<U256> = self.contract.query(
+    /// See `query_with_retries`.
+    async fn query<R: Detokenize, P: Tokenize + Clone>(&self, method: &str, params: P, from: Option<Address>, options: Options, block: impl Into<Option<BlockId>>) -> web3::contract::Result<R> {
+        query_with_retries(&self.reader, method, params, from, options, block.into()).await
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Transactions of `address` that were submitted but not mined yet, new ones will be queued behind them.
+    pub async fn get_pending_transactions(&self, address: Address) -> Result<u64> {
+        let eth = self.web3.eth();
+        let latest = eth.transaction_count(address, Some(BlockNumber::Latest)).await?;
+        let pending = eth.transaction_count(address, Some(BlockNumber::Pending)).await?;
+        Ok(pending.saturating_sub(latest).as_u64())
+    }
+
+    /// ERC165 interface detection, answers are cached since they cannot change for a deployed contract.
+    pub async fn supports_interface(&self, interface_id: [u8; 4]) -> Result<bool> {
+        if let Some(supported) = self.supported_interfaces.lock().unwrap().get(&interface_id) {
+            return Ok(*supported)
+        }
+        let result: web3::contract::Result<bool> = self.query(
+            "supportsInterface",
+            (interface_id,),
+            None,
+            Options::default(),
+            None
+        ).await;
+        let supported = result?;
+        self.supported_interfaces.lock().unwrap().insert(interface_id, supported);
+        Ok(supported)
+    }
+
+    pub async fn get_uri(&self, token_id: u128) -> Result<String> {
+        let result: web3::contract::Result<String> = self.query(
+            "uri",
+            (U256::from(token_id),),
+            None,
+            Options::default(),
+            None
+        ).await;
+        Ok(result?)
+    }
+
+    pub async fn get_OM_balance(&self, address: Address) -> Result<OMAmount> {
+        let result: web3::contract::Result<U256> = self.query(
             "balanceOf",
             (address, OM,),
             None,
             Options::default(),
             None
         ).await;
-        Ok(result?.as_u128() as f64 / 10_f64.powi(18))
+        Ok(OMAmount::from_raw(result?))
     }
 
-    pub async fn transfer_OM(&self, from: Address, to: Address, amount: f64) -> Option<TransactionReceipt> {
+    /// Bidding doesn't need an approval since OM never leaves the contract, this is only needed for third party operators.
+    pub async fn set_approval_for_all(&self, sender: Address, operator: Address, approved: bool) -> Option<TransactionReceipt> {
         self.call_with_confirmations(
+            "setApprovalForAll",
+            (operator, approved),
+            sender,
+            None,
+        ).await
+    }
+
+    pub async fn is_approved_for_all(&self, owner: Address, operator: Address) -> Result<bool> {
+        let result: web3::contract::Result<bool> = self.query(
+            "isApprovedForAll",
+            (owner, operator),
+            None,
+            Options::default(),
+            None
+        ).await;
+        Ok(result?)
+    }
+
+    /// Account that the wallet currently exposes, `None` when there is no wallet or it exposes no accounts.
+    pub async fn get_account(&self) -> Option<Address> {
+        if self.read_only {
+            return None
+        }
+        self.web3.eth().accounts().await.ok()?.into_iter().next()
+    }
+
+    /// Total amount of OM ever minted, read through `totalSupply(OM)`. The bundled ABI has no such getter, so the call is
+    /// encoded by hand, deployments without it fall back to the contract's fixed `TOTAL_SUPPLY`.
+    pub async fn get_OM_total_supply(&self) -> Result<OMAmount> {
+        let request = CallRequest {
+            to: Some(self.contract.address()),
+            data: Some(total_supply_calldata(OM).into()),
+            ..Default::default()
+        };
+        if let Ok(output) = self.read_web3.eth().call(request, None).await {
+            // a contract without the function and without a fallback answers with no output at all
+            if let Ok(Some(Token::Uint(supply))) = ethabi::decode(&[ethabi::ParamType::Uint(256)], &output.0).map(|mut tokens| tokens.pop()) {
+                return Ok(OMAmount::from_raw(supply))
+            }
+        }
+        let result: web3::contract::Result<U256> = self.query("TOTAL_SUPPLY", (), None, Options::default(), None).await;
+        Ok(OMAmount::from_raw(result?))
+    }
+
+    pub async fn transfer_OM(&self, from: Address, to: Address, amount: OMAmount) -> Option<TxStatus> {
+        self.call_with_timeout(
             "safeTransferFrom",
             (
                 from,
                 to,
                 OM,
-                U256::from((amount * 10_f64.powi(18)) as u128),
+                amount.raw(),
                 CALLDATA.to_vec(),
             ),
             from,
+            CONFIRMATION_TIMEOUT,
+            None,
         ).await
     }
 
-    pub async fn mint(&self, sender: Address, parent_id: u128, field: Field) -> Option<H256> {
-        self.call(
+    /// A mint that is mined but reverted is reported as `Error::Failed` rather than returned.
+    pub async fn mint(&self, sender: Address, parent_id: u128, field: Field, on_stage: Option<OnStage>) -> Option<TxStatus> {
+        self.call_with_timeout(
             "mintNFT",
-            (U256::from(parent_id), sender, field),
+            mint_params(parent_id, sender, field),
             sender,
+            CONFIRMATION_TIMEOUT,
+            on_stage,
         ).await
     }
 
+    /// Gas that `mint` would use, estimated with the same arguments without submitting anything.
+    pub async fn estimate_mint_gas(&self, parent_id: u128, recipient: Address, field: Field) -> Result<U256> {
+        Ok(self.contract.estimate_gas("mintNFT", mint_params(parent_id, recipient, field), recipient, Options::default()).await?)
+    }
+
+    /// Ownership is checked up front, so that burning someone else's NFT reports `NoRightsToBurn` without
+    /// waiting for the transaction to revert.
     pub async fn burn(&self, sender: Address, token_id: u128) -> Option<TransactionReceipt> {
+        match self.get_metadata(token_id).await {
+            Ok(token) if token.owner != sender => {
+                (self.handle_error)(Error::NoRightsToBurn);
+                return None
+            }
+            Ok(_) => {}
+            Err(error) => log::warn!("Could not check the owner of {} before burning: {}", token_id, error),
+        }
         self.call_with_confirmations(
             "burn",
             (U256::from(token_id),),
             sender,
+            None,
         ).await
     }
 
+    /// Fraction of every minting bid that is distributed among the parent NFTs instead of being locked in the minted one.
+    pub async fn get_upstream_share(&self) -> Result<f64> {
+        let result: web3::contract::Result<U256> = self.query("UPSTREAM_SHARE", (), None, Options::default(), None).await;
+        Ok(result?.as_u128() as f64 / 100.0)
+    }
+
+    /// Fee in OM charged by the contract for minting. None of the contract's functions are payable,
+    /// so the fee is never attached as ETH value to a transaction.
+    pub async fn get_mint_fee(&self) -> Result<OMAmount> {
+        let result: web3::contract::Result<U256> = self.query("MINT_FEE", (), None, Options::default(), None).await;
+        Ok(OMAmount::from_raw(result?))
+    }
+
     pub async fn get_metadata(&self, token_id: u128) -> Result<Metadata> {
-        let result: web3::contract::Result<Metadata> = self.contract.query(
+        let result: web3::contract::Result<Metadata> = self.query(
             "getMetadata",
             (U256::from(token_id),),
             None,
@@ -202,45 +827,71 @@ impl ERC1155Contract {
         Ok(result?)
     }
 
-    pub async fn get_children_metadata(&self, parent_id: u128) -> Result<Vec<Metadata>> {
-        let result: web3::contract::Result<Vec<Metadata>> = self.contract.query(
-            "getChildrenMetadata",
-            (U256::from(parent_id),),
-            None,
-            Options::default(),
-            None
-        ).await;
-        Ok(result?)
+    /// OM locked in `token_id`, the contract has no getter for it alone so it's read from the metadata.
+    pub async fn get_locked_OM(&self, token_id: u128) -> Result<OMAmount> {
+        Ok(self.get_metadata(token_id).await?.locked_OM)
     }
 
-    pub async fn get_ancestry_metadata(&self, token_id: u128) -> Result<Vec<Metadata>> {
-        let result: web3::contract::Result<Vec<Metadata>> = self.contract.query(
-            "getAncestryMetadata",
+    /// `getSubdivisionRule` reports the grid of `token_id` as its columns and rows, zero for free selection.
+    /// Deployments without it have no constraints beyond non overlapping fields, so every NFT allows free selection.
+    pub async fn get_subdivision_rule(&self, token_id: u128) -> Result<SubdivisionRule> {
+        if self.contract.abi().function("getSubdivisionRule").is_err() {
+            return Ok(SubdivisionRule::Free)
+        }
+        let result: web3::contract::Result<(U256, U256)> = self.query(
+            "getSubdivisionRule",
             (U256::from(token_id),),
             None,
             Options::default(),
             None
         ).await;
-        Ok(result?)
+        let (columns, rows) = result?;
+        Ok(match (u32::try_from(columns), u32::try_from(rows)) {
+            (Ok(columns), Ok(rows)) if columns > 0 && rows > 0 => SubdivisionRule::Grid { columns, rows },
+            _ => SubdivisionRule::Free,
+        })
     }
 
-    pub async fn bid(&self, sender: Address, parent_id: u128, field: Field, amount: f64, minimum_price: f64) -> Option<H256> {
-        self.call(
-            "bid",
-            (
-                U256::from(parent_id),
-                sender,
-                field.clone(),
-                U256::from((amount * 10_f64.powi(18)) as u128),
-                U256::from((minimum_price * 10_f64.powi(18)) as u128),
-            ),
-            sender,
-        ).await
+    /// Address that the token was originally minted to, found through the `TransferSingle` or `TransferBatch` event
+    /// emitted by the mint. Logs are searched from the deployment block up to the latest one, `LOG_BLOCK_RANGE` blocks
+    /// per request as nodes reject wider ranges. Found creators are cached since a mint can't change.
+    pub async fn get_creator(&self, token_id: u128) -> Result<Option<Address>> {
+        if let Some(creator) = self.creators.lock().unwrap().get(&token_id) {
+            return Ok(Some(*creator))
+        }
+        let abi = self.contract.abi();
+        let (single, batch) = (abi.event("TransferSingle")?, abi.event("TransferBatch")?);
+        let latest_block = self.read_web3.eth().block_number().await?.as_u64();
+        let mut from_block = deployment_block().as_u64();
+        while from_block <= latest_block {
+            let to_block = (from_block + LOG_BLOCK_RANGE - 1).min(latest_block);
+            let logs = self.read_web3.eth().logs(
+                FilterBuilder::default()
+                    .address(vec![self.contract.address()])
+                    .topics(Some(vec![single.signature(), batch.signature()]), None, Some(vec![H256::zero()]), None)
+                    .from_block(BlockNumber::Number(from_block.into()))
+                    .to_block(BlockNumber::Number(to_block.into()))
+                    .build()
+            ).await?;
+            for log in logs {
+                if let Some(creator) = minted_to(single, batch, log, token_id)? {
+                    self.creators.lock().unwrap().insert(token_id, creator);
+                    return Ok(Some(creator))
+                }
+            }
+            from_block = to_block + 1;
+        }
+        Ok(None)
     }
 
-    pub async fn get_bids(&self, parent_id: u128) -> Result<Vec<Metadata>> {
-        let result: web3::contract::Result<Vec<Metadata>> = self.contract.query(
-            "getBids",
+    /// ENS name that the address resolves to, if it has one.
+    pub async fn lookup_name(&self, address: Address) -> Option<String> {
+        self.read_web3.api::<Ens<_>>().canonical_name(address).await.ok().filter(|name| !name.is_empty())
+    }
+
+    pub async fn get_children_metadata(&self, parent_id: u128) -> Result<Vec<Metadata>> {
+        let result: web3::contract::Result<Vec<Metadata>> = self.query(
+            "getChildrenMetadata",
             (U256::from(parent_id),),
             None,
             Options::default(),
@@ -249,38 +900,326 @@ impl ERC1155Contract {
         Ok(result?)
     }
 
-    pub async fn get_owned_items(&self, owner: Address) -> Result<(Vec<Metadata>, Vec<Metadata>)> {
-        let result: web3::contract::Result<(Vec<Metadata>, Vec<Metadata>)> = self.contract.query(
-            "getOwnedItems",
-            (owner,),
-            None,
-            Options::default(),
-            None
-        ).await;
-        Ok(result?)
+    /// At most `limit` children of `parent_id` starting at `offset`, the last page is shorter and pages past the end are empty.
+    /// Deployments without `getChildrenMetadataPaged` return every child at once, which is then sliced here.
+    pub async fn get_children_metadata_paged(&self, parent_id: u128, offset: u64, limit: u64) -> Result<Vec<Metadata>> {
+        if self.contract.abi().function("getChildrenMetadataPaged").is_ok() {
+            let result: web3::contract::Result<Vec<Metadata>> = self.query(
+                "getChildrenMetadataPaged",
+                (U256::from(parent_id), U256::from(offset), U256::from(limit)),
+                None,
+                Options::default(),
+                None
+            ).await;
+            return Ok(result?)
+        }
+        let children = self.get_children_metadata(parent_id).await?;
+        Ok(children.into_iter().skip(offset as usize).take(limit as usize).collect())
     }
 
-    pub async fn approve_bid(&self, sender: Address, bid_id: u128) -> Option<H256> {
-        self.call(
-            "approve",
-            (U256::from(bid_id),),
-            sender,
+    /// State of `token_id` after every block between `from_block` and `to_block` in which the contract moved tokens,
+    /// which covers every mint, bid and burn. Blocks that left the children and bids unchanged are skipped.
+    /// Reading past state needs an archive node, only the last `MAX_HISTORY_SNAPSHOTS` active blocks are read.
+    pub async fn get_node_history(&self, token_id: u128, from_block: U64, to_block: U64) -> Result<Vec<NodeSnapshot>> {
+        let abi = self.contract.abi();
+        let signatures = vec![abi.event("TransferSingle")?.signature(), abi.event("TransferBatch")?.signature()];
+        let logs = self.read_web3.eth().logs(
+            FilterBuilder::default()
+                .address(vec![self.contract.address()])
+                .topics(Some(signatures), None, None, None)
+                .from_block(BlockNumber::Number(from_block))
+                .to_block(BlockNumber::Number(to_block))
+                .build()
+        ).await?;
+        let mut blocks: Vec<U64> = logs.into_iter().filter_map(|log| log.block_number).collect();
+        blocks.dedup();
+        if blocks.len() > MAX_HISTORY_SNAPSHOTS {
+            log::warn!("Reading only the last {} of {} blocks with activity", MAX_HISTORY_SNAPSHOTS, blocks.len());
+            blocks.drain(..blocks.len() - MAX_HISTORY_SNAPSHOTS);
+        }
+
+        let mut snapshots: Vec<NodeSnapshot> = vec![];
+        for block in blocks {
+            let at = BlockId::Number(BlockNumber::Number(block));
+            let children: web3::contract::Result<Vec<Metadata>> = self.query(
+                "getChildrenMetadata", (U256::from(token_id),), None, Options::default(), at,
+            ).await;
+            let bids: web3::contract::Result<Vec<Metadata>> = self.query(
+                "getBids", (U256::from(token_id),), None, Options::default(), at,
+            ).await;
+            let mut snapshot = NodeSnapshot {
+                block,
+                children: children?,
+                bids: bids?.into_iter().filter(|bid| bid.parent_id == token_id).collect(),
+            };
+            snapshot.children.sort_by_key(|token| token.token_id);
+            snapshot.bids.sort_by_key(|bid| bid.token_id);
+            if snapshots.last().map_or(true, |last| !last.same_state(&snapshot)) {
+                snapshots.push(snapshot);
+            }
+        }
+        Ok(snapshots)
+    }
+
+    /// Polls for tokens minted after the call and reports the children of `parent_id` every time some appear, until dropped.
+    /// Transfer logs don't carry the parent, so any mint in the contract triggers a query of the children.
+    pub async fn watch_children(&self, parent_id: u128, on_children: impl Fn(Vec<Metadata>)) {
+        let Ok(mut from_block) = self.read_web3.eth().block_number().await else {
+            return
+        };
+        loop {
+            sleep(MINT_POLL_INTERVAL).await;
+            let Ok(to_block) = self.read_web3.eth().block_number().await else {
+                continue
+            };
+            if to_block <= from_block {
+                continue
+            }
+            match self.minted_between(from_block + 1, to_block).await {
+                Ok(false) => {}
+                Ok(true) => match self.get_children_metadata(parent_id).await {
+                    Ok(children) => on_children(children),
+                    Err(error) => {
+                        log::warn!("Could not read the children of {} after a mint: {}", parent_id, error);
+                        continue
+                    }
+                },
+                Err(error) => {
+                    log::warn!("Could not read mints in blocks {}-{}: {}", from_block + 1, to_block, error);
+                    continue
+                }
+            }
+            from_block = to_block;
+        }
+    }
+
+    /// Whether any token was minted, transferred from the zero address, between the blocks.
+    async fn minted_between(&self, from_block: U64, to_block: U64) -> Result<bool> {
+        let abi = self.contract.abi();
+        let signatures = vec![abi.event("TransferSingle")?.signature(), abi.event("TransferBatch")?.signature()];
+        let logs = self.read_web3.eth().logs(
+            FilterBuilder::default()
+                .address(vec![self.contract.address()])
+                .topics(Some(signatures), None, Some(vec![H256::zero()]), None)
+                .from_block(BlockNumber::Number(from_block))
+                .to_block(BlockNumber::Number(to_block))
+                .build()
+        ).await?;
+        Ok(!logs.is_empty())
+    }
+
+    /// Chain of NFTs from `token_id` itself up to the root, leaf first.
+    pub async fn get_ancestry_metadata(&self, token_id: u128) -> Result<Vec<Metadata>> {
+        let result: web3::contract::Result<Vec<Metadata>> = self.query(
+            "getAncestryMetadata",
+            (U256::from(token_id),),
+            None,
+            Options::default(),
+            None
+        ).await;
+        Ok(result?)
+    }
+
+    /// Returns as soon as the bid is submitted, its confirmation is only reported through `on_stage`.
+    /// The contract takes no cap, so `max_amount`, the most the user agreed to pay including the minting fee, is only checked
+    /// client-side against the current fee right before submitting, a fee raised after that still goes through on-chain.
+    /// A bid still unconfirmed at `deadline` is reported as failed.
+    pub async fn bid(&self, sender: Address, parent_id: u128, field: Field, amount: OMAmount, minimum_price: OMAmount, max_amount: Option<OMAmount>, deadline: Option<Deadline>, on_stage: Option<OnStage>) -> Option<H256> {
+        // minimum price of the parent could have been raised since the user filled in the bid
+        if let Ok(parent) = self.get_metadata(parent_id).await {
+            if amount < parent.minimum_price {
+                (self.handle_error)(Error::BidBelowMinimum(parent.minimum_price.to_ether()));
+                return None
+            }
+        }
+        if let (Some(max_amount), Ok(fee)) = (max_amount, self.get_mint_fee().await) {
+            let cost = amount + fee;
+            if cost > max_amount {
+                (self.handle_error)(Error::BidAboveMaximum(cost.to_ether()));
+                return None
+            }
+        }
+
+        let transaction_hash = self.call(
+            "bid",
+            Self::bid_params(sender, parent_id, field, amount, minimum_price),
+            sender,
+            deadline,
+        ).await;
+        match transaction_hash {
+            Some(transaction_hash) if on_stage.is_some() || deadline.is_some() => {
+                report_stage(&on_stage, TxStage::Submitted(transaction_hash));
+                let contract = self.clone();
+                let timeout = deadline.map_or(CONFIRMATION_TIMEOUT, |deadline| deadline.remaining());
+                spawn_local(async move {
+                    match contract.wait_for_receipt(transaction_hash, timeout).await {
+                        Some(receipt) if receipt.status == Some(0.into()) => report_stage(&on_stage, TxStage::Failed),
+                        Some(_) => report_stage(&on_stage, TxStage::Confirmed),
+                        // a submitted transaction can only be replaced from the wallet, so the user is told that it missed the deadline
+                        None if deadline.is_some() => {
+                            (contract.handle_error)(Error::DeadlineExpired);
+                            report_stage(&on_stage, TxStage::Failed);
+                        }
+                        None => {}
+                    }
+                });
+            }
+            None => report_stage(&on_stage, TxStage::Failed),
+            _ => {}
+        }
+        transaction_hash
+    }
+
+    /// The contract cannot change a bid in place, so the bid is deleted and placed again with the same field and minimum price.
+    /// This is not atomic, if the new bid fails the old one stays deleted and its OM is back in the bidder's balance,
+    /// which is reported as `BidIncreaseIncomplete` after the error of the new bid.
+    pub async fn increase_bid(&self, sender: Address, bid: &Metadata, amount: OMAmount) -> Option<H256> {
+        if amount <= bid.locked_OM {
+            (self.handle_error)(Error::BidNotIncreased);
+            return None
+        }
+        self.delete_bid(sender, bid.token_id).await?;
+        let transaction_hash = self.bid(sender, bid.parent_id, bid.field.clone(), amount, bid.minimum_price, None, None, None).await;
+        if transaction_hash.is_none() {
+            (self.handle_error)(Error::BidIncreaseIncomplete(bid.token_id));
+        }
+        transaction_hash
+    }
+
+    /// ABI-encoded function selector and arguments that `bid` would submit.
+    pub fn bid_calldata(&self, sender: Address, parent_id: u128, field: Field, amount: OMAmount, minimum_price: OMAmount) -> Result<Vec<u8>> {
+        let params = Self::bid_params(sender, parent_id, field, amount, minimum_price);
+        Ok(self.contract.abi().function("bid")?.encode_input(&params.into_tokens())?)
+    }
+
+    fn bid_params(sender: Address, parent_id: u128, field: Field, amount: OMAmount, minimum_price: OMAmount) -> (U256, Address, Field, U256, U256) {
+        (
+            U256::from(parent_id),
+            sender,
+            field,
+            amount.raw(),
+            minimum_price.raw(),
+        )
+    }
+
+    /// Bids to mint directly inside of `parent_id`. Bids on deeper descendants are left out, even if the contract
+    /// returns them, since the explorer draws and approves bids one level at a time.
+    pub async fn get_bids(&self, parent_id: u128) -> Result<Vec<Metadata>> {
+        let result: web3::contract::Result<Vec<Metadata>> = self.query(
+            "getBids",
+            (U256::from(parent_id),),
+            None,
+            Options::default(),
+            None
+        ).await;
+        Ok(direct_bids(parent_id, result?))
+    }
+
+    /// Ancestry, children and bids of `token_id`, as returned by `get_ancestry_metadata`, `get_children_metadata` and `get_bids`.
+    /// They are read in a single aggregated call when a Multicall3 contract is configured, and in parallel otherwise.
+    pub async fn get_node(&self, token_id: u128) -> Result<(Vec<Metadata>, Vec<Metadata>, Vec<Metadata>)> {
+        let Some(multicall) = &self.multicall else {
+            let (ancestry, children, bids) = futures::join!(
+                self.get_ancestry_metadata(token_id),
+                self.get_children_metadata(token_id),
+                self.get_bids(token_id),
+            );
+            return Ok((ancestry?, children?, bids?))
+        };
+        let abi = self.contract.abi();
+        let methods = ["getAncestryMetadata", "getChildrenMetadata", "getBids"];
+        let calls = methods.iter()
+            .map(|method| Ok((self.contract.address(), abi.function(method)?.encode_input(&[Token::Uint(U256::from(token_id))])?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut results = multicall.aggregate(calls).await?.into_iter().zip(methods).map(|(output, method)| {
+            let output = output.ok_or_else(|| eyre!("{} of {} failed", method, token_id))?;
+            Ok(<Vec<Metadata> as Detokenize>::from_tokens(abi.function(method)?.decode_output(&output)?)?)
+        }).collect::<Result<Vec<_>>>()?.into_iter();
+        match (results.next(), results.next(), results.next()) {
+            (Some(ancestry), Some(children), Some(bids)) => Ok((ancestry, children, direct_bids(token_id, bids))),
+            _ => Err(eyre!("Multicall returned fewer results than calls")),
+        }
+    }
+
+    /// The contract has no getter for a single bid, so it is looked up among the bids of its parent.
+    /// `None` once the bid was deleted or approved.
+    pub async fn get_bid(&self, parent_id: u128, bid_id: u128) -> Result<Option<Metadata>> {
+        Ok(self.get_bids(parent_id).await?.into_iter().find(|bid| bid.token_id == bid_id))
+    }
+
+    /// The contract has no dedicated counter, so this is the number of bids returned by `get_bids`.
+    pub async fn get_bids_count(&self, parent_id: u128) -> Result<u64> {
+        Ok(self.get_bids(parent_id).await?.len() as u64)
+    }
+
+    pub async fn get_owned_items(&self, owner: Address) -> Result<(Vec<Metadata>, Vec<Metadata>)> {
+        let result: web3::contract::Result<(Vec<Metadata>, Vec<Metadata>)> = self.query(
+            "getOwnedItems",
+            (owner,),
+            None,
+            Options::default(),
+            None
+        ).await;
+        Ok(result?)
+    }
+
+    /// NFTs owned by `owner` anywhere in the tree, without the bids that `get_owned_items` also returns.
+    pub async fn get_owned_tokens(&self, owner: Address) -> Result<Vec<Metadata>> {
+        Ok(self.get_owned_items(owner).await?.0)
+    }
+
+    pub async fn approve_bid(&self, sender: Address, bid_id: u128) -> Option<H256> {
+        self.call(
+            "approve",
+            (U256::from(bid_id),),
+            sender,
+            None,
         ).await
     }
 
+    /// Submit the calls one transaction after another, stopping at the first failure. The contract has no `multicall`,
+    /// so the batch is not atomic and calls submitted before a failure stay on chain. Every call is encoded before
+    /// anything is submitted, so that a malformed call doesn't leave the batch half done. Returns the hashes of the
+    /// submitted transactions.
+    pub async fn write_sequentially(&self, sender: Address, calls: Vec<EncodedCall>) -> Option<Vec<H256>> {
+        let abi = self.contract.abi();
+        for call in calls.iter() {
+            if let Err(error) = abi.function(call.method).and_then(|function| function.encode_input(&call.params)) {
+                (self.handle_error)(Error::Other(format!("Invalid call to {}: {}", call.method, error)));
+                return None
+            }
+        }
+
+        let mut transaction_hashes = Vec::with_capacity(calls.len());
+        for call in calls.iter() {
+            transaction_hashes.push(self.call(call.method, call.params.as_slice(), sender, None).await?);
+        }
+        Some(transaction_hashes)
+    }
+
+    /// Bids are approved in the given order, repeated ids are only passed once as approving a bid twice reverts.
     pub async fn batch_approve_bids(&self, sender: Address, bid_ids: &[u128]) -> Option<H256> {
+        let mut seen = HashSet::new();
         self.call(
             "batchApprove",
-            (bid_ids.iter().map(|bid_id| U256::from(*bid_id)).collect::<Vec<U256>>(),),
+            (bid_ids.iter().filter(|bid_id| seen.insert(**bid_id)).map(|bid_id| U256::from(*bid_id)).collect::<Vec<U256>>(),),
             sender,
+            None,
         ).await
     }
 
+    /// Whether the submitted transaction got mined without reverting, giving up after `CONFIRMATION_TIMEOUT`.
+    pub async fn confirmed(&self, transaction_hash: H256) -> bool {
+        matches!(self.wait_for_receipt(transaction_hash, CONFIRMATION_TIMEOUT).await, Some(receipt) if receipt.status != Some(0.into()))
+    }
+
+    /// Bidder taking back a pending bid along with the OM locked in it, the contract only lets the bid creator delete it.
     pub async fn delete_bid(&self, sender: Address, bid_id: u128) -> Option<TransactionReceipt> {
         self.call_with_confirmations(
             "deleteBid",
             (U256::from(bid_id),),
             sender,
+            None,
         ).await
     }
 
@@ -289,93 +1228,1623 @@ impl ERC1155Contract {
             "setMinimumBid",
             (
                 U256::from(token_id),
-                U256::from((minimum_bid * 10_f64.powi(18)) as u128),
+                OMAmount::from_ether(minimum_bid).raw(),
             ),
             sender,
+            None,
         ).await
 
     }
 }
 
 
+/// The contract also returns bids placed on descendants of `parent_id`, which are left out.
+fn direct_bids(parent_id: u128, bids: Vec<Metadata>) -> Vec<Metadata> {
+    let (bids, nested): (Vec<_>, Vec<_>) = bids.into_iter().partition(|bid| bid.parent_id == parent_id);
+    if !nested.is_empty() {
+        log::warn!("Ignoring {} bids returned for {} that belong to its descendants", nested.len(), parent_id);
+    }
+    bids
+}
+
+
+/// Multicall3 aggregating read calls into a single `eth_call`, so that a node is loaded in one round trip.
+#[derive(Clone)]
+pub struct Multicall3Contract {
+    web3: Web3<Either<Eip1193, Http>>,
+    contract: Contract<Either<Eip1193, Http>>,
+}
+
+impl Multicall3Contract {
+    /// Configured through the `MULTICALL_ADDRESS` env variable, without it every call is made on its own.
+    pub fn new(web3: &Web3<Either<Eip1193, Http>>) -> Option<Self> {
+        let address = option_env!("MULTICALL_ADDRESS")?.trim_start_matches("0x").parse().ok()?;
+        Some(Self::at(web3, address))
+    }
+
+    pub fn at(web3: &Web3<Either<Eip1193, Http>>, address: Address) -> Self {
+        Self {
+            web3: web3.clone(),
+            contract: Contract::from_json(web3.eth(), address, include_bytes!("../../resources/Multicall3.json")).unwrap(),
+        }
+    }
+
+    /// `aggregate3` input calling every `(target, calldata)`, a call that fails doesn't fail the others.
+    pub fn aggregate_calldata(&self, calls: Vec<(Address, Vec<u8>)>) -> Result<Vec<u8>> {
+        let calls = calls.into_iter()
+            .map(|(target, calldata)| Token::Tuple(vec![Token::Address(target), Token::Bool(true), Token::Bytes(calldata)]))
+            .collect();
+        Ok(self.contract.abi().function("aggregate3")?.encode_input(&[Token::Array(calls)])?)
+    }
+
+    /// Output of every call in order, `None` for the ones that reverted.
+    pub async fn aggregate(&self, calls: Vec<(Address, Vec<u8>)>) -> Result<Vec<Option<Vec<u8>>>> {
+        let request = CallRequest {
+            to: Some(self.contract.address()),
+            data: Some(self.aggregate_calldata(calls)?.into()),
+            ..Default::default()
+        };
+        let output = self.web3.eth().call(request, None).await?;
+        let Some(Token::Array(results)) = self.contract.abi().function("aggregate3")?.decode_output(&output.0)?.pop() else {
+            return Err(eyre!("Invalid aggregate3 output"))
+        };
+        Ok(results.into_iter().map(|result| match result {
+            Token::Tuple(fields) => match fields.as_slice() {
+                [Token::Bool(true), Token::Bytes(output)] => Some(output.clone()),
+                _ => None,
+            },
+            _ => None,
+        }).collect())
+    }
+}
+
+
 #[derive(Clone)]
 pub struct Wrapped1155FactoryContract {
+    web3: Web3<Either<Eip1193, Http>>,
     contract: Contract<Either<Eip1193, Http>>,
+    read_only: bool,
+    confirmations: usize,
+    pending_calls: Arc<Mutex<HashSet<Vec<u8>>>>,
     handle_error: Arc<dyn Fn(Error)>,
     erc1155_address: Address,
 }
 
 #[async_trait]
 impl CallWrapper for Wrapped1155FactoryContract {
+    fn web3(&self) -> &Web3<Either<Eip1193, Http>> {
+        &self.web3
+    }
+
     fn contract(&self) -> &Contract<Either<Eip1193, Http>> {
         &self.contract
     }
 
-    fn _handle_error(&self, error: Error) {
-        (self.handle_error)(error);
+    fn error_handler(&self) -> Arc<dyn Fn(Error)> {
+        self.handle_error.clone()
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn confirmations(&self) -> usize {
+        self.confirmations
+    }
+
+    fn pending_calls(&self) -> Arc<Mutex<HashSet<Vec<u8>>>> {
+        self.pending_calls.clone()
     }
 }
 
 impl Wrapped1155FactoryContract {
-    pub fn new(web3: &Web3<Either<Eip1193, Http>>, erc1155_address: Address, handle_error: Arc<dyn Fn(Error)>) -> Self {
+    pub fn new(web3: &Web3<Either<Eip1193, Http>>, erc1155_address: Address, read_only: bool, handle_error: Arc<dyn Fn(Error)>) -> Self {
         Self {
+            web3: web3.clone(),
             contract: Contract::from_json(
                 web3.eth(),
                 env!("WRAPPER_FACTORY_CONTRACT_ADDRESS").trim_start_matches("0x").parse().unwrap(),
                 include_bytes!("../../resources/Wrapped1155Factory.json"),
             ).unwrap(),
+            read_only,
+            confirmations: default_confirmations(),
+            pending_calls: Arc::new(Mutex::new(HashSet::new())),
             handle_error,
             erc1155_address,
         }
     }
 
+    pub fn with_confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
     pub fn address(&self) -> Address {
         self.contract.address()
     }
 
-    pub async fn unwrap(&self, recipient: Address, amount: f64) -> Option<TransactionReceipt> {
-        self.call_with_confirmations(
+    pub async fn unwrap(&self, recipient: Address, amount: OMAmount) -> Option<TxStatus> {
+        self.call_with_timeout(
             "unwrap",
             (
                 self.erc1155_address,
                 OM,
-                U256::from((amount * 10_f64.powi(18)) as u128),
+                amount.raw(),
                 recipient,
                 CALLDATA.to_vec(),
-            ), recipient
+            ),
+            recipient,
+            CONFIRMATION_TIMEOUT,
+            None,
         ).await
     }
+
+    /// Wrapping is done by sending OM to the factory together with `CALLDATA`, which describes the ERC20 to mint,
+    /// so the transaction goes through the ERC1155 contract rather than the factory.
+    pub async fn wrap(&self, erc1155_contract: &ERC1155Contract, sender: Address, amount: OMAmount) -> Option<TxStatus> {
+        erc1155_contract.transfer_OM(sender, self.address(), amount).await
+    }
 }
 
 
 #[derive(Clone)]
 pub struct ERC20Contract {
+    web3: Web3<Either<Eip1193, Http>>,
     contract: Contract<Either<Eip1193, Http>>,
+    read_only: bool,
+    confirmations: usize,
+    pending_calls: Arc<Mutex<HashSet<Vec<u8>>>>,
+    handle_error: Arc<dyn Fn(Error)>,
+}
+
+#[async_trait]
+impl CallWrapper for ERC20Contract {
+    fn web3(&self) -> &Web3<Either<Eip1193, Http>> {
+        &self.web3
+    }
+
+    fn contract(&self) -> &Contract<Either<Eip1193, Http>> {
+        &self.contract
+    }
+
+    fn error_handler(&self) -> Arc<dyn Fn(Error)> {
+        self.handle_error.clone()
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn confirmations(&self) -> usize {
+        self.confirmations
+    }
+
+    fn pending_calls(&self) -> Arc<Mutex<HashSet<Vec<u8>>>> {
+        self.pending_calls.clone()
+    }
 }
 
 impl ERC20Contract {
-    pub fn new(web3: &Web3<Either<Eip1193, Http>>) -> Self {
+    /// Wrapped OM token configured through the `ERC20_CONTRACT_ADDRESS` env variable.
+    pub fn new(web3: &Web3<Either<Eip1193, Http>>, read_only: bool, handle_error: Arc<dyn Fn(Error)>) -> Self {
+        Self::at(web3, env!("ERC20_CONTRACT_ADDRESS").trim_start_matches("0x").parse().unwrap(), read_only, handle_error)
+    }
+
+    /// Any other wrapped token, they all share the `Wrapped1155` ABI.
+    pub fn at(web3: &Web3<Either<Eip1193, Http>>, address: Address, read_only: bool, handle_error: Arc<dyn Fn(Error)>) -> Self {
         Self {
+            web3: web3.clone(),
             contract: Contract::from_json(
                 web3.eth(),
-                env!("ERC20_CONTRACT_ADDRESS").trim_start_matches("0x").parse().unwrap(),
+                address,
                 include_bytes!("../../resources/Wrapped1155.json"),
             ).unwrap(),
+            read_only,
+            confirmations: default_confirmations(),
+            pending_calls: Arc::new(Mutex::new(HashSet::new())),
+            handle_error,
         }
     }
 
+    pub fn with_confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
     pub fn address(&self) -> Address {
         self.contract.address()
     }
 
-    pub async fn get_balance(&self, address: Address) -> Result<f64> {
-        let result: web3::contract::Result<U256> = self.contract.query(
-            "balanceOf",
-            (address,),
+    /// See `query_with_retries`.
+    async fn query<R: Detokenize, P: Tokenize + Clone>(&self, method: &str, params: P) -> web3::contract::Result<R> {
+        query_with_retries(&self.contract, method, params, None, Options::default(), None).await
+    }
+
+    pub async fn get_balance(&self, address: Address) -> Result<OMAmount> {
+        let result: web3::contract::Result<U256> = self.query("balanceOf", (address,)).await;
+        Ok(OMAmount::from_raw(result?))
+    }
+
+    pub async fn get_symbol(&self) -> Result<String> {
+        Ok(self.query("symbol", ()).await?)
+    }
+
+    pub async fn get_decimals(&self) -> Result<u8> {
+        let result: web3::contract::Result<U256> = self.query("decimals", ()).await;
+        Ok(result?.as_u32() as u8)
+    }
+
+    /// wOM has the same 18 decimals as OM, so amounts are given the same way.
+    pub async fn transfer(&self, sender: Address, recipient: Address, amount: OMAmount) -> Option<TxStatus> {
+        self.call_with_timeout(
+            "transfer",
+            (recipient, amount.raw()),
+            sender,
+            CONFIRMATION_TIMEOUT,
             None,
-            Options::default(),
-            None
-        ).await;
-        Ok(result?.as_u128() as f64 / 10_f64.powi(18))
+        ).await
+    }
+
+    pub async fn approve(&self, sender: Address, spender: Address, amount: OMAmount) -> Option<TxStatus> {
+        self.call_with_timeout(
+            "approve",
+            (spender, amount.raw()),
+            sender,
+            CONFIRMATION_TIMEOUT,
+            None,
+        ).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::evm::mock::{output, selector, MockProvider};
+
+    #[wasm_bindgen_test]
+    fn deadline_in_the_past_is_expired() {
+        let deadline = Deadline(js_sys::Date::now() - 1000.0);
+        assert!(deadline.expired());
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+        assert!(Deadline::after(Duration::ZERO).expired());
+    }
+
+    #[wasm_bindgen_test]
+    fn deadline_in_the_future_is_not_expired() {
+        let deadline = Deadline::after(Duration::from_secs(60));
+        assert!(!deadline.expired());
+        assert!(deadline.remaining() <= Duration::from_secs(60));
+        assert!(deadline.remaining() > Duration::from_secs(50));
+    }
+
+    #[wasm_bindgen_test]
+    async fn expired_deadline_short_circuits_submission() {
+        let provider = MockProvider::new(|_, _| Ok(serde_json::Value::Null));
+        let expired = Arc::new(AtomicUsize::new(0));
+        let counted = expired.clone();
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(move |error| {
+            if matches!(error, Error::DeadlineExpired) {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+        let deadline = Some(Deadline::after(Duration::ZERO));
+
+        assert!(contract.call("deleteBid", (U256::one(),), Address::zero(), deadline).await.is_none());
+        assert!(contract.call_with_confirmations("deleteBid", (U256::one(),), Address::zero(), deadline).await.is_none());
+        assert_eq!(expired.load(Ordering::SeqCst), 2);
+        assert!(provider.methods().is_empty());
+    }
+
+    fn transfer_single_log(to: Address, token_id: u128) -> serde_json::Value {
+        let abi = ethabi::Contract::load(&include_bytes!("../../resources/MandelbrotNFT.json")[..]).unwrap();
+        let topics = [abi.event("TransferSingle").unwrap().signature(), H256::zero(), H256::zero(), H256::from(to)];
+        let data = ethabi::encode(&[ethabi::Token::Uint(token_id.into()), ethabi::Token::Uint(U256::one())]);
+        serde_json::json!({
+            "address": Address::zero(),
+            "topics": topics,
+            "data": web3::types::Bytes(data),
+        })
+    }
+
+    fn transfer_batch_log(to: Address, token_ids: &[u128]) -> serde_json::Value {
+        let abi = ethabi::Contract::load(&include_bytes!("../../resources/MandelbrotNFT.json")[..]).unwrap();
+        let topics = [abi.event("TransferBatch").unwrap().signature(), H256::zero(), H256::zero(), H256::from(to)];
+        let data = ethabi::encode(&[
+            ethabi::Token::Array(token_ids.iter().map(|token_id| ethabi::Token::Uint((*token_id).into())).collect()),
+            ethabi::Token::Array(token_ids.iter().map(|_| ethabi::Token::Uint(U256::one())).collect()),
+        ]);
+        serde_json::json!({
+            "address": Address::zero(),
+            "topics": topics,
+            "data": web3::types::Bytes(data),
+        })
+    }
+
+    #[wasm_bindgen_test]
+    async fn creator_is_decoded_from_the_mint_event() {
+        let creator = Address::repeat_byte(7);
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_blockNumber" => Ok(serde_json::json!("0x6")),
+            "eth_getLogs" => Ok(serde_json::json!([transfer_single_log(Address::repeat_byte(3), 3), transfer_single_log(creator, 7)])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_creator(7).await.unwrap(), Some(creator));
+        assert_eq!(contract.get_creator(5).await.unwrap(), None);
+        let filter = &provider.params("eth_getLogs")[0][0];
+        assert_eq!(filter["topics"][0].as_array().unwrap().len(), 2);
+        assert_eq!(filter["topics"][2], serde_json::json!([H256::zero()]));
+    }
+
+    #[wasm_bindgen_test]
+    async fn creator_of_a_batch_mint_is_the_recipient_of_the_batch() {
+        let creator = Address::repeat_byte(7);
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_blockNumber" => Ok(serde_json::json!("0x6")),
+            "eth_getLogs" => Ok(serde_json::json!([transfer_batch_log(Address::repeat_byte(3), &[2, 3]), transfer_batch_log(creator, &[6, 7, 8])])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_creator(7).await.unwrap(), Some(creator));
+        assert_eq!(contract.get_creator(3).await.unwrap(), Some(Address::repeat_byte(3)));
+        assert_eq!(contract.get_creator(5).await.unwrap(), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn creator_is_searched_in_bounded_block_ranges_up_to_the_latest_block() {
+        let creator = Address::repeat_byte(7);
+        let provider = MockProvider::new(move |method, params| match method {
+            "eth_blockNumber" => Ok(serde_json::json!(format!("{:#x}", 2 * LOG_BLOCK_RANGE + 5))),
+            "eth_getLogs" if params[0]["fromBlock"] == format!("{:#x}", LOG_BLOCK_RANGE) => Ok(serde_json::json!([transfer_single_log(creator, 7)])),
+            "eth_getLogs" => Ok(serde_json::json!([])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_creator(7).await.unwrap(), Some(creator));
+        let ranges = |provider: &MockProvider| provider.params("eth_getLogs").iter()
+            .map(|params| (params[0]["fromBlock"].clone(), params[0]["toBlock"].clone()))
+            .collect::<Vec<_>>();
+        // the search stops at the range holding the mint
+        assert_eq!(ranges(&provider), vec![
+            (serde_json::json!("0x0"), serde_json::json!(format!("{:#x}", LOG_BLOCK_RANGE - 1))),
+            (serde_json::json!(format!("{:#x}", LOG_BLOCK_RANGE)), serde_json::json!(format!("{:#x}", 2 * LOG_BLOCK_RANGE - 1))),
+        ]);
+
+        assert_eq!(contract.get_creator(5).await.unwrap(), None);
+        assert_eq!(ranges(&provider).last().unwrap().1, serde_json::json!(format!("{:#x}", 2 * LOG_BLOCK_RANGE + 5)));
+        assert_eq!(ranges(&provider).len(), 5);
+    }
+
+    #[wasm_bindgen_test]
+    async fn read_only_contract_sends_no_transactions() {
+        let provider = MockProvider::new(|_, _| Ok(serde_json::Value::Null));
+        let refused = Arc::new(AtomicUsize::new(0));
+        let counted = refused.clone();
+        let contract = ERC1155Contract::new(provider.web3(), true, Arc::new(move |error| {
+            if matches!(error, Error::ReadOnly) {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+
+        assert!(contract.call("deleteBid", (U256::one(),), Address::zero(), None).await.is_none());
+        assert!(contract.call_with_confirmations("burn", (U256::one(),), Address::zero(), None).await.is_none());
+        assert_eq!(refused.load(Ordering::SeqCst), 2);
+        assert!(provider.methods().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn failing_estimation_falls_back_to_the_default_gas_limit() {
+        let transaction_hash = H256::repeat_byte(1);
+        let provider = MockProvider::new(move |method, _| match method {
+            // not a JSON-RPC error, like a node that can't be reached
+            "eth_estimateGas" => Err(serde_json::json!("network error")),
+            "eth_sendTransaction" => Ok(serde_json::json!(transaction_hash)),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.call("deleteBid", (U256::one(),), Address::zero(), None).await, Some(transaction_hash));
+        assert_eq!(provider.params("eth_estimateGas").len(), ESTIMATE_GAS_ATTEMPTS as usize);
+        let sent = provider.params("eth_sendTransaction");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0][0]["gas"], serde_json::json!(format!("{:#x}", default_gas_limit())));
+    }
+
+    #[wasm_bindgen_test]
+    async fn rate_limited_estimation_is_retried_and_falls_back_to_the_default_gas_limit() {
+        let transaction_hash = H256::repeat_byte(1);
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_estimateGas" => Err(serde_json::json!({ "code": -32005, "message": "rate limit exceeded" })),
+            "eth_sendTransaction" => Ok(serde_json::json!(transaction_hash)),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let errors = Arc::new(AtomicUsize::new(0));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new({
+            let errors = errors.clone();
+            move |_| {
+                errors.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+
+        assert_eq!(contract.call("deleteBid", (U256::one(),), Address::zero(), None).await, Some(transaction_hash));
+        assert_eq!(provider.params("eth_estimateGas").len(), ESTIMATE_GAS_ATTEMPTS as usize);
+        assert_eq!(provider.params("eth_sendTransaction")[0][0]["gas"], serde_json::json!(format!("{:#x}", default_gas_limit())));
+        assert_eq!(errors.load(Ordering::SeqCst), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn bid_calldata_is_the_selector_followed_by_the_encoded_arguments() {
+        let provider = MockProvider::new(|_, _| Ok(serde_json::Value::Null));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let sender = Address::repeat_byte(7);
+        let field = Field {
+            x_min: mandelbrot_explorer::BigFloat::from(-0.5),
+            y_min: mandelbrot_explorer::BigFloat::from(-0.25),
+            x_max: mandelbrot_explorer::BigFloat::from(0.0),
+            y_max: mandelbrot_explorer::BigFloat::from(0.25),
+        };
+
+        let calldata = contract.bid_calldata(sender, 3, field.clone(), OMAmount::from_ether(1.5), OMAmount::from_ether(0.25)).unwrap();
+
+        let abi = ethabi::Contract::load(&include_bytes!("../../resources/MandelbrotNFT.json")[..]).unwrap();
+        assert_eq!(calldata[..4], abi.function("bid").unwrap().short_signature());
+        assert_eq!(calldata[4..], ethabi::encode(&[
+            ethabi::Token::Uint(U256::from(3)),
+            ethabi::Token::Address(sender),
+            web3::contract::tokens::Tokenizable::into_token(field),
+            ethabi::Token::Uint(U256::from(1_500_000_000_000_000_000_u128)),
+            ethabi::Token::Uint(U256::from(250_000_000_000_000_000_u128)),
+        ]));
+        // parent id, recipient, the four field bounds, amount and minimum price, each in its own word
+        assert_eq!(calldata.len(), 4 + 8 * 32);
+    }
+
+    #[wasm_bindgen_test]
+    async fn receipt_disappearing_after_confirmation_is_a_reorg() {
+        let block_hash = H256::repeat_byte(1);
+        let polls = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new({
+            let polls = polls.clone();
+            move |_, params| {
+                // receipt is still in the confirmation block on the first poll, then it is gone
+                if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Ok(serde_json::to_value(TransactionReceipt {
+                        transaction_hash: serde_json::from_value(params[0].clone()).unwrap(),
+                        block_hash: Some(block_hash),
+                        ..Default::default()
+                    }).unwrap())
+                } else {
+                    Ok(serde_json::Value::Null)
+                }
+            }
+        });
+
+        assert!(reorged(&provider.web3().eth(), H256::repeat_byte(2), block_hash, Duration::ZERO).await);
+        assert_eq!(provider.methods(), vec!["eth_getTransactionReceipt"; 2]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn receipt_staying_in_its_block_is_not_a_reorg() {
+        let block_hash = H256::repeat_byte(1);
+        let provider = MockProvider::new(move |_, params| Ok(serde_json::to_value(TransactionReceipt {
+            transaction_hash: serde_json::from_value(params[0].clone()).unwrap(),
+            block_hash: Some(block_hash),
+            ..Default::default()
+        }).unwrap()));
+
+        assert!(!reorged(&provider.web3().eth(), H256::repeat_byte(2), block_hash, Duration::ZERO).await);
+        assert_eq!(provider.methods().len(), REORG_WATCH_POLLS as usize);
+    }
+
+    #[wasm_bindgen_test]
+    async fn supported_interface_is_decoded_and_cached() {
+        let provider = MockProvider::new(|_, _| Ok(output(&[ethabi::Token::Bool(true)])));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(contract.supports_interface(ERC1155_INTERFACE_ID).await.unwrap());
+        assert!(contract.supports_interface(ERC1155_INTERFACE_ID).await.unwrap());
+        assert_eq!(provider.calldata("eth_call"), vec![format!("{}d9b67a26{}", selector("supportsInterface"), "0".repeat(56))]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn unsupported_interface_is_decoded() {
+        let provider = MockProvider::new(|_, _| Ok(output(&[ethabi::Token::Bool(false)])));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert!(!contract.supports_interface(ERC1155_METADATA_URI_INTERFACE_ID).await.unwrap());
+    }
+
+    /// Provider accepting any transaction, whose receipt is `receipt` and the latest block is `latest`.
+    fn submitting_provider(transaction_hash: H256, receipt: Option<TransactionReceipt>, latest: u64) -> MockProvider {
+        MockProvider::new(move |method, _| match method {
+            "eth_estimateGas" => Ok(serde_json::json!("0x5208")),
+            "eth_sendTransaction" => Ok(serde_json::json!(transaction_hash)),
+            "eth_getTransactionReceipt" => Ok(serde_json::to_value(&receipt).unwrap()),
+            "eth_blockNumber" => Ok(serde_json::json!(format!("{latest:#x}"))),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        })
+    }
+
+    #[wasm_bindgen_test]
+    async fn never_confirming_call_is_pending_after_the_timeout() {
+        let transaction_hash = H256::repeat_byte(1);
+        let provider = submitting_provider(transaction_hash, None, 5);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let status = contract.call_with_timeout("deleteBid", (U256::one(),), Address::zero(), Duration::ZERO, None).await;
+        assert!(matches!(status, Some(TxStatus::Pending(hash)) if hash == transaction_hash));
+        assert_eq!(provider.params("eth_sendTransaction").len(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    async fn call_confirmed_before_the_timeout_returns_its_receipt() {
+        let transaction_hash = H256::repeat_byte(1);
+        let receipt = TransactionReceipt {
+            transaction_hash,
+            block_number: Some(5.into()),
+            ..Default::default()
+        };
+        let provider = submitting_provider(transaction_hash, Some(receipt), 5 + default_confirmations() as u64);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let status = contract.call_with_timeout("deleteBid", (U256::one(),), Address::zero(), Duration::from_secs(60), None).await;
+        assert!(matches!(status, Some(TxStatus::Confirmed(receipt)) if receipt.transaction_hash == transaction_hash));
+    }
+
+    #[wasm_bindgen_test]
+    async fn metadata_is_decoded_from_the_contract_output() {
+        // getMetadata(7) as returned by the contract, a MetadataView tuple with its Field tuple inlined
+        let words = [
+            format!("{:064x}", 7),
+            format!("{:0>64}", "11".repeat(20)),
+            format!("{:064x}", 1),
+            format!("{:0<64}", "2"),
+            format!("{:0<64}", "1"),
+            format!("{:0<64}", "3"),
+            format!("{:0<64}", "2"),
+            format!("{:064x}", 2_500_000_000_000_000_000_u128),
+            format!("{:064x}", 1_000_000_000_000_000_000_u128),
+            format!("{:064x}", 2),
+        ];
+        let provider = MockProvider::new(move |_, _| Ok(serde_json::json!(format!("0x{}", words.concat()))));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let metadata = contract.get_metadata(7).await.unwrap();
+        assert_eq!(metadata.token_id, 7);
+        assert_eq!(metadata.owner, Address::repeat_byte(0x11));
+        assert_eq!(metadata.parent_id, 1);
+        assert_eq!(metadata.locked_OM, OMAmount::from_ether(2.5));
+        assert_eq!(metadata.minimum_price, OMAmount::from_ether(1.0));
+        assert_eq!(metadata.layer, 2);
+        // coordinates are offset by (2.1, 1.5) and scaled by 16^63 on chain
+        let field = Field {
+            x_min: mandelbrot_explorer::BigFloat::from(-0.1),
+            y_min: mandelbrot_explorer::BigFloat::from(-0.5),
+            x_max: mandelbrot_explorer::BigFloat::from(0.9),
+            y_max: mandelbrot_explorer::BigFloat::from(0.5),
+        };
+        assert!(metadata.field.approx_eq(&field, 1e-9));
+        assert_eq!(provider.calldata("eth_call"), vec![format!("{}{:064x}", selector("getMetadata"), 7)]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn reverting_estimation_blocks_submission_with_the_reason() {
+        let reason = format!(
+            "{REVERT_REASON_SELECTOR}{}",
+            ethabi::encode(&[ethabi::Token::String("bid too low".into())]).iter().map(|byte| format!("{byte:02x}")).collect::<String>(),
+        );
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_estimateGas" => Err(serde_json::json!({ "code": 3, "message": "execution reverted: bid too low", "data": reason })),
+            _ => Ok(serde_json::Value::Null),
+        });
+        let reasons = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new({
+            let reasons = reasons.clone();
+            move |error| if let Error::Reverted(reason) = error {
+                reasons.lock().unwrap().push(reason);
+            }
+        }));
+
+        assert!(contract.call("deleteBid", (U256::one(),), Address::zero(), None).await.is_none());
+        assert_eq!(*reasons.lock().unwrap(), vec!["bid too low".to_string()]);
+        assert_eq!(provider.params("eth_estimateGas").len(), 1);
+        assert!(provider.params("eth_sendTransaction").is_empty());
+    }
+
+    fn transaction_count_provider(latest: u64, pending: u64) -> MockProvider {
+        MockProvider::new(move |method, params| match (method, params[1].as_str()) {
+            ("eth_getTransactionCount", Some("latest")) => Ok(serde_json::json!(format!("{latest:#x}"))),
+            ("eth_getTransactionCount", Some("pending")) => Ok(serde_json::json!(format!("{pending:#x}"))),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        })
+    }
+
+    #[wasm_bindgen_test]
+    async fn pending_transactions_are_the_pending_count_above_the_latest() {
+        let provider = transaction_count_provider(5, 7);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert_eq!(contract.get_pending_transactions(Address::repeat_byte(1)).await.unwrap(), 2);
+
+        let provider = transaction_count_provider(5, 5);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert_eq!(contract.get_pending_transactions(Address::repeat_byte(1)).await.unwrap(), 0);
+        assert_eq!(provider.params("eth_getTransactionCount")[0][0], serde_json::json!(Address::repeat_byte(1)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn pending_count_lagging_behind_is_no_pending_transaction() {
+        let provider = transaction_count_provider(7, 5);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert_eq!(contract.get_pending_transactions(Address::repeat_byte(1)).await.unwrap(), 0);
+    }
+
+    fn bid(token_id: u128, parent_id: u128) -> Metadata {
+        Metadata {
+            token_id,
+            owner: Address::zero(),
+            parent_id,
+            field: Field {
+                x_min: mandelbrot_explorer::BigFloat::from(0.0),
+                y_min: mandelbrot_explorer::BigFloat::from(0.0),
+                x_max: mandelbrot_explorer::BigFloat::from(0.5),
+                y_max: mandelbrot_explorer::BigFloat::from(0.5),
+            },
+            locked_OM: OMAmount::from_ether(1.0),
+            minimum_price: OMAmount::default(),
+            layer: 2,
+            owned: false,
+            selected: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn bids_on_descendants_are_left_out() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Array(vec![
+                web3::contract::tokens::Tokenizable::into_token(bid(3, 2)),
+                web3::contract::tokens::Tokenizable::into_token(bid(4, 3)),
+                web3::contract::tokens::Tokenizable::into_token(bid(5, 2)),
+            ])])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let bids = contract.get_bids(2).await.unwrap();
+        assert_eq!(bids.iter().map(|bid| bid.token_id).collect::<Vec<_>>(), vec![3, 5]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn duplicate_submission_while_pending_is_rejected() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap_or(Ok(serde_json::Value::Null)));
+        let duplicates = Arc::new(AtomicUsize::new(0));
+        let counted = duplicates.clone();
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(move |error| {
+            if matches!(error, Error::DuplicateSubmission) {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+
+        let (first, duplicate, other) = futures::join!(
+            contract.call("deleteBid", (U256::one(),), Address::zero(), None),
+            contract.call("deleteBid", (U256::one(),), Address::zero(), None),
+            contract.call("deleteBid", (U256::from(2),), Address::zero(), None),
+        );
+        assert!(first.is_some());
+        assert!(duplicate.is_none());
+        assert!(other.is_some());
+        assert_eq!(duplicates.load(Ordering::SeqCst), 1);
+        assert_eq!(provider.params("eth_sendTransaction").len(), 2);
+
+        // the claim is released once the first submission is over
+        assert!(contract.call("deleteBid", (U256::one(),), Address::zero(), None).await.is_some());
+    }
+
+    #[wasm_bindgen_test]
+    async fn bids_count_is_decoded_from_the_bids() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Array(vec![
+                web3::contract::tokens::Tokenizable::into_token(bid(3, 2)),
+                web3::contract::tokens::Tokenizable::into_token(bid(4, 2)),
+            ])])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_bids_count(2).await.unwrap(), 2);
+        assert_eq!(provider.calldata("eth_call")[0], format!("{}{:064x}", selector("getBids"), 2));
+    }
+
+    #[wasm_bindgen_test]
+    fn gas_speed_offers_a_share_of_the_current_price() {
+        let current = U256::from(20_000_000_000u64);
+        assert_eq!(GasSpeed::Slow.gas_price(current), U256::from(18_000_000_000u64));
+        assert_eq!(GasSpeed::Normal.gas_price(current), current);
+        assert_eq!(GasSpeed::Fast.gas_price(current), U256::from(25_000_000_000u64));
+    }
+
+    #[wasm_bindgen_test]
+    async fn gas_speed_sets_the_gas_price_of_the_options() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_gasPrice" => Ok(serde_json::json!("0x4a817c800")),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let options = contract.with_gas_speed(Options::default(), GasSpeed::Fast).await;
+        assert_eq!(options.gas_price, Some(U256::from(25_000_000_000u64)));
+        let options = contract.with_gas_speed(Options::default(), GasSpeed::Normal).await;
+        assert_eq!(options.gas_price, None);
+        // normal speed doesn't need the current price
+        assert_eq!(provider.params("eth_gasPrice").len(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    async fn bid_is_only_increased_to_a_higher_amount() {
+        let provider = MockProvider::new(|_, _| Ok(serde_json::Value::Null));
+        let refused = Arc::new(AtomicUsize::new(0));
+        let counted = refused.clone();
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(move |error| {
+            if matches!(error, Error::BidNotIncreased) {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+
+        assert!(contract.increase_bid(Address::zero(), &bid(3, 2), OMAmount::from_ether(1.0)).await.is_none());
+        assert!(contract.increase_bid(Address::zero(), &bid(3, 2), OMAmount::from_ether(0.5)).await.is_none());
+        assert_eq!(refused.load(Ordering::SeqCst), 2);
+        assert!(provider.methods().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn increased_bid_is_deleted_and_placed_again() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method)
+            .unwrap_or(Err(serde_json::json!({ "code": -32601, "message": "unsupported" }))));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let sender = Address::repeat_byte(7);
+        let old = Metadata { minimum_price: OMAmount::from_ether(0.25), ..bid(3, 2) };
+
+        assert!(contract.increase_bid(sender, &old, OMAmount::from_ether(2.0)).await.is_some());
+        let bid_calldata = contract.bid_calldata(sender, 2, old.field.clone(), OMAmount::from_ether(2.0), OMAmount::from_ether(0.25)).unwrap();
+        assert_eq!(provider.calldata("eth_sendTransaction"), vec![
+            format!("{}{:064x}", selector("deleteBid"), 3),
+            format!("0x{}", bid_calldata.iter().map(|byte| format!("{byte:02x}")).collect::<String>()),
+        ]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn failing_to_place_the_increased_bid_is_reported_as_incomplete() {
+        let provider = MockProvider::new(|method, params| match method {
+            "eth_sendTransaction" if params[0]["data"].as_str().is_some_and(|data| data.starts_with(&selector("bid"))) =>
+                Err(serde_json::json!({ "code": 4001, "message": "User rejected the request." })),
+            _ => crate::evm::mock::confirmed_transaction(method)
+                .unwrap_or(Err(serde_json::json!({ "code": -32601, "message": "unsupported" }))),
+        });
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new({
+            let errors = errors.clone();
+            move |error| errors.lock().unwrap().push(error)
+        }));
+
+        assert!(contract.increase_bid(Address::repeat_byte(7), &bid(3, 2), OMAmount::from_ether(2.0)).await.is_none());
+        assert_eq!(provider.calldata("eth_sendTransaction")[0], format!("{}{:064x}", selector("deleteBid"), 3));
+        // the reason the bid failed comes first, the incomplete increase last so that it's the one left on screen
+        assert!(matches!(errors.lock().unwrap().as_slice(), [.., Error::Rejected, Error::BidIncreaseIncomplete(3)]));
+    }
+
+    #[wasm_bindgen_test]
+    async fn abi_without_a_subdivision_rule_allows_free_selection_without_asking_the_node() {
+        let provider = MockProvider::new(|_, _| Err(serde_json::json!({ "code": -32601, "message": "unsupported" })));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(matches!(contract.get_subdivision_rule(2).await, Ok(SubdivisionRule::Free)));
+        assert!(provider.methods().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn total_supply_is_read_for_the_OM_id() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Uint(U256::from(1_000_500_000_000_000_000_000u128))])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_OM_total_supply().await.unwrap().to_ether(), 1000.5);
+        assert_eq!(provider.calldata("eth_call"), vec![format!("0xbd85b039{:064x}", 0)]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn total_supply_falls_back_to_the_fixed_supply_without_a_getter() {
+        let provider = MockProvider::new(|method, params| match method {
+            "eth_call" if params[0]["data"].as_str().is_some_and(|data| data.starts_with(&selector("TOTAL_SUPPLY"))) =>
+                Ok(output(&[ethabi::Token::Uint(U256::from(1_000_500_000_000_000_000_000u128))])),
+            "eth_call" => Err(serde_json::json!({ "code": 3, "message": "execution reverted", "data": "0x" })),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_OM_total_supply().await.unwrap().to_ether(), 1000.5);
+        assert_eq!(provider.calldata("eth_call").last(), Some(&selector("TOTAL_SUPPLY")));
+    }
+
+    fn owned_token_provider(owner: Address) -> MockProvider {
+        MockProvider::new(move |method, _| match method {
+            "eth_call" => Ok(output(&[web3::contract::tokens::Tokenizable::into_token(Metadata { owner, ..bid(7, 1) })])),
+            _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+        })
+    }
+
+    #[wasm_bindgen_test]
+    async fn burn_sends_the_token_id() {
+        let owner = Address::repeat_byte(1);
+        let provider = owned_token_provider(owner);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(contract.burn(owner, 7).await.is_some());
+        assert_eq!(provider.calldata("eth_sendTransaction"), vec![format!("{}{:064x}", selector("burn"), 7)]);
+        assert_eq!(provider.params("eth_sendTransaction")[0][0]["from"], serde_json::json!(owner));
+    }
+
+    #[wasm_bindgen_test]
+    async fn burning_someone_elses_token_is_refused() {
+        let provider = owned_token_provider(Address::repeat_byte(2));
+        let refused = Arc::new(AtomicUsize::new(0));
+        let counted = refused.clone();
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(move |error| {
+            if matches!(error, Error::NoRightsToBurn) {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+
+        assert!(contract.burn(Address::repeat_byte(1), 7).await.is_none());
+        assert_eq!(refused.load(Ordering::SeqCst), 1);
+        assert!(provider.params("eth_sendTransaction").is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn batch_approval_keeps_the_order_and_width_of_each_bid_once() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(contract.batch_approve_bids(Address::repeat_byte(1), &[5, u128::MAX, 5, 2]).await.is_some());
+        assert_eq!(
+            provider.calldata("eth_sendTransaction"),
+            vec![format!("{}{:064x}{:064x}{:064x}{:064x}{:064x}", selector("batchApprove"), 0x20, 3, 5, u128::MAX, 2)],
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn deleting_a_bid_sends_its_id() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(contract.delete_bid(Address::repeat_byte(1), 9).await.is_some());
+        assert_eq!(provider.calldata("eth_sendTransaction"), vec![format!("{}{:064x}", selector("deleteBid"), 9)]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn ancestry_is_decoded_leaf_first() {
+        let ancestry = vec![bid(3, 2), bid(2, 1), bid(1, 0)];
+        let answer = output(&[ethabi::Token::Array(ancestry.into_iter().map(web3::contract::tokens::Tokenizable::into_token).collect())]);
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_call" => Ok(answer.clone()),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let Ok(ancestry) = contract.get_ancestry_metadata(3).await else { panic!("ancestry not decoded") };
+        assert_eq!(ancestry.iter().map(|token| token.token_id).collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(provider.calldata("eth_call"), vec![format!("{}{:064x}", selector("getAncestryMetadata"), 3)]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn history_reconstructs_the_children_and_bids_at_each_active_block() {
+        let log_at = |block: u64| {
+            let mut log = transfer_single_log(Address::zero(), 3);
+            log["blockNumber"] = serde_json::json!(format!("{block:#x}"));
+            log
+        };
+        let logs = serde_json::json!([log_at(5), log_at(5), log_at(7), log_at(9)]);
+        let provider = MockProvider::new(move |method, params| match method {
+            "eth_getLogs" => Ok(logs.clone()),
+            "eth_call" => {
+                let block = params[1].as_str().unwrap().to_string();
+                let data = params[0]["data"].as_str().unwrap();
+                let tokens = match (data.starts_with(&selector("getChildrenMetadata")), block.as_str()) {
+                    (true, "0x5") => vec![],
+                    (true, _) => vec![bid(3, 2)],
+                    // a bid on another token is left out
+                    (false, "0x5") => vec![bid(3, 2), bid(4, 9)],
+                    (false, _) => vec![],
+                };
+                Ok(output(&[ethabi::Token::Array(tokens.into_iter().map(web3::contract::tokens::Tokenizable::into_token).collect())]))
+            }
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let Ok(history) = contract.get_node_history(2, 0.into(), 10.into()).await else { panic!("history not read") };
+        // block 9 left the state of block 7 unchanged
+        assert_eq!(history.iter().map(|snapshot| snapshot.block.as_u64()).collect::<Vec<_>>(), vec![5, 7]);
+        assert!(history[0].children.is_empty());
+        assert_eq!(history[0].bids.iter().map(|bid| bid.token_id).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(history[1].children.iter().map(|token| token.token_id).collect::<Vec<_>>(), vec![3]);
+        assert!(history[1].bids.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn transaction_mined_with_a_failed_status_is_an_error() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_getTransactionReceipt" => Ok(serde_json::to_value(TransactionReceipt {
+                transaction_hash: H256::repeat_byte(0x11),
+                block_number: Some(5.into()),
+                status: Some(0.into()),
+                ..Default::default()
+            }).unwrap()),
+            _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+        });
+        let failed = Arc::new(AtomicUsize::new(0));
+        let counted = failed.clone();
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(move |error| {
+            if matches!(error, Error::Failed(transaction_hash) if transaction_hash == H256::repeat_byte(0x11)) {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+
+        assert!(contract.delete_bid(Address::repeat_byte(1), 9).await.is_none());
+        assert_eq!(failed.load(Ordering::SeqCst), 1);
+    }
+
+    #[wasm_bindgen_test]
+    async fn approval_for_all_is_sent_as_a_boolean() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let operator = Address::repeat_byte(2);
+
+        assert!(contract.set_approval_for_all(Address::repeat_byte(1), operator, true).await.is_some());
+        assert!(contract.set_approval_for_all(Address::repeat_byte(1), operator, false).await.is_some());
+        assert_eq!(provider.calldata("eth_sendTransaction"), vec![
+            format!("{}{:024x}{operator:x}{:064x}", selector("setApprovalForAll"), 0, 1),
+            format!("{}{:024x}{operator:x}{:064x}", selector("setApprovalForAll"), 0, 0),
+        ]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn approval_for_all_is_decoded() {
+        let provider = MockProvider::new(|method, params| match method {
+            "eth_call" => {
+                let approved = params[0]["data"].as_str().unwrap().ends_with(&format!("{:x}", Address::repeat_byte(2)));
+                Ok(output(&[ethabi::Token::Bool(approved)]))
+            }
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(matches!(contract.is_approved_for_all(Address::repeat_byte(1), Address::repeat_byte(2)).await, Ok(true)));
+        assert!(matches!(contract.is_approved_for_all(Address::repeat_byte(1), Address::repeat_byte(3)).await, Ok(false)));
+    }
+
+    fn accounts_provider(accounts: serde_json::Value) -> MockProvider {
+        MockProvider::new(move |method, _| match method {
+            "eth_accounts" => Ok(accounts.clone()),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        })
+    }
+
+    #[wasm_bindgen_test]
+    async fn empty_accounts_leave_no_sender() {
+        let provider = accounts_provider(serde_json::json!([]));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert_eq!(contract.get_account().await, None);
+
+        let provider = accounts_provider(serde_json::Value::Null);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert_eq!(contract.get_account().await, None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn first_exposed_account_is_the_sender() {
+        let provider = accounts_provider(serde_json::json!([Address::repeat_byte(1), Address::repeat_byte(2)]));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert_eq!(contract.get_account().await, Some(Address::repeat_byte(1)));
+
+        // a read-only contract never writes, whatever the provider exposes
+        let contract = ERC1155Contract::new(provider.web3(), true, Arc::new(|_| {}));
+        assert_eq!(contract.get_account().await, None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn failed_submission_is_reported_with_the_node_message() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_sendTransaction" => Err(serde_json::json!({ "code": -32000, "message": "insufficient funds for gas" })),
+            _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+        });
+        let reported = Arc::new(Mutex::new(vec![]));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new({
+            let reported = reported.clone();
+            move |error| if let Error::Rpc(message) = error {
+                reported.lock().unwrap().push(message);
+            }
+        }));
+
+        assert!(contract.delete_bid(Address::repeat_byte(1), 9).await.is_none());
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0].contains("insufficient funds"));
+    }
+
+    #[wasm_bindgen_test]
+    async fn wrapping_sends_the_scaled_amount_to_the_factory_with_the_calldata() {
+        let transaction_hash = H256::repeat_byte(1);
+        let receipt = TransactionReceipt { transaction_hash, block_number: Some(5.into()), ..Default::default() };
+        let provider = submitting_provider(transaction_hash, Some(receipt), 5 + default_confirmations() as u64);
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let wrapper_contract = Wrapped1155FactoryContract::new(provider.web3(), erc1155_contract.address(), false, Arc::new(|_| {}));
+        let sender = Address::repeat_byte(1);
+
+        let status = wrapper_contract.wrap(&erc1155_contract, sender, OMAmount::from_ether(1.5)).await;
+        assert!(matches!(status, Some(TxStatus::Confirmed(_))));
+        let calldata = provider.calldata("eth_sendTransaction").remove(0);
+        assert!(calldata.starts_with(&selector("safeTransferFrom")));
+        let calldata: Bytes = serde_json::from_value(calldata.into()).unwrap();
+        let params = crate::evm::mock::erc1155_abi().function("safeTransferFrom").unwrap().decode_input(&calldata.0[4..]).unwrap();
+        assert_eq!(params, vec![
+            ethabi::Token::Address(sender),
+            ethabi::Token::Address(wrapper_contract.address()),
+            ethabi::Token::Uint(OM),
+            ethabi::Token::Uint(U256::from(1_500_000_000_000_000_000u128)),
+            ethabi::Token::Bytes(CALLDATA.to_vec()),
+        ]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn erc20_amounts_are_scaled_to_wei() {
+        let transaction_hash = H256::repeat_byte(1);
+        let receipt = TransactionReceipt { transaction_hash, block_number: Some(5.into()), ..Default::default() };
+        let provider = submitting_provider(transaction_hash, Some(receipt), 5 + default_confirmations() as u64);
+        let contract = ERC20Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let (sender, recipient) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        assert!(matches!(contract.transfer(sender, recipient, OMAmount::from_ether(2.5)).await, Some(TxStatus::Confirmed(_))));
+        assert!(matches!(contract.approve(sender, recipient, OMAmount::from_ether(0.25)).await, Some(TxStatus::Confirmed(_))));
+        let abi = ethabi::Contract::load(&include_bytes!("../../resources/Wrapped1155.json")[..]).unwrap();
+        let calls = provider.calldata("eth_sendTransaction").into_iter().map(|calldata| {
+            let calldata: Bytes = serde_json::from_value(calldata.into()).unwrap();
+            calldata.0
+        }).collect::<Vec<_>>();
+        assert_eq!(calls, vec![
+            abi.function("transfer").unwrap().encode_input(&[
+                ethabi::Token::Address(recipient),
+                ethabi::Token::Uint(U256::from(2_500_000_000_000_000_000u128)),
+            ]).unwrap(),
+            abi.function("approve").unwrap().encode_input(&[
+                ethabi::Token::Address(recipient),
+                ethabi::Token::Uint(U256::from(250_000_000_000_000_000u128)),
+            ]).unwrap(),
+        ]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn calls_are_submitted_one_after_another() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let calls = vec![EncodedCall::new("deleteBid", (U256::from(3),)), EncodedCall::new("burn", (U256::from(4),))];
+        let transaction_hashes = contract.write_sequentially(Address::repeat_byte(1), calls).await.unwrap();
+        assert_eq!(transaction_hashes.len(), 2);
+        assert_eq!(provider.calldata("eth_sendTransaction"), vec![
+            format!("{}{:064x}", selector("deleteBid"), 3),
+            format!("{}{:064x}", selector("burn"), 4),
+        ]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn calls_after_a_rejected_one_are_not_submitted() {
+        let submitted = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new({
+            let submitted = submitted.clone();
+            move |method, _| {
+                if method == "eth_sendTransaction" && submitted.fetch_add(1, Ordering::SeqCst) == 1 {
+                    return Err(serde_json::json!({ "code": 4001, "message": "User rejected the request." }))
+                }
+                crate::evm::mock::confirmed_transaction(method).unwrap()
+            }
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let calls = vec![
+            EncodedCall::new("deleteBid", (U256::from(3),)),
+            EncodedCall::new("burn", (U256::from(4),)),
+            EncodedCall::new("burn", (U256::from(5),)),
+        ];
+        assert!(contract.write_sequentially(Address::repeat_byte(1), calls).await.is_none());
+        assert_eq!(provider.calldata("eth_sendTransaction"), vec![
+            format!("{}{:064x}", selector("deleteBid"), 3),
+            format!("{}{:064x}", selector("burn"), 4),
+        ]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn reverted_transaction_is_not_confirmed() {
+        let reverted = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new({
+            let reverted = reverted.clone();
+            move |method, _| match method {
+                "eth_getTransactionReceipt" if reverted.load(Ordering::SeqCst) > 0 => Ok(serde_json::to_value(TransactionReceipt {
+                    block_number: Some(5.into()),
+                    status: Some(0.into()),
+                    ..Default::default()
+                }).unwrap()),
+                _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+            }
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(contract.confirmed(H256::repeat_byte(0x11)).await);
+        reverted.store(1, Ordering::SeqCst);
+        assert!(!contract.confirmed(H256::repeat_byte(0x11)).await);
+    }
+
+    #[wasm_bindgen_test]
+    async fn malformed_call_submits_nothing() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let calls = vec![EncodedCall::new("deleteBid", (U256::from(3),)), EncodedCall::new("burn", (Address::zero(), U256::from(4)))];
+        assert!(contract.write_sequentially(Address::repeat_byte(1), calls).await.is_none());
+        assert!(provider.methods().iter().all(|method| method != "eth_sendTransaction"));
+    }
+
+    #[wasm_bindgen_test]
+    async fn configured_confirmations_are_waited_for() {
+        let transaction_hash = H256::repeat_byte(1);
+        let receipt = TransactionReceipt { transaction_hash, block_number: Some(5.into()), ..Default::default() };
+
+        let provider = submitting_provider(transaction_hash, Some(receipt.clone()), 7);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {})).with_confirmations(3);
+        let status = contract.call_with_timeout("deleteBid", (U256::one(),), Address::zero(), Duration::ZERO, None).await;
+        assert!(matches!(status, Some(TxStatus::Pending(_))));
+
+        let provider = submitting_provider(transaction_hash, Some(receipt), 8);
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {})).with_confirmations(3);
+        let status = contract.call_with_timeout("deleteBid", (U256::one(),), Address::zero(), Duration::ZERO, None).await;
+        assert!(matches!(status, Some(TxStatus::Confirmed(_))));
+    }
+
+    fn recorded_stages() -> (Arc<Mutex<Vec<TxStage>>>, Option<OnStage>) {
+        let stages = Arc::new(Mutex::new(vec![]));
+        let on_stage: OnStage = Arc::new({
+            let stages = stages.clone();
+            move |stage| stages.lock().unwrap().push(stage)
+        });
+        (stages, Some(on_stage))
+    }
+
+    #[wasm_bindgen_test]
+    async fn stages_are_reported_in_order() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let (stages, on_stage) = recorded_stages();
+
+        let status = contract.call_with_timeout("deleteBid", (U256::one(),), Address::zero(), CONFIRMATION_TIMEOUT, on_stage).await;
+        assert!(matches!(status, Some(TxStatus::Confirmed(_))));
+        assert_eq!(*stages.lock().unwrap(), vec![TxStage::Submitted(H256::repeat_byte(0x11)), TxStage::Confirmed]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn bid_confirmation_is_reported_after_it_returns() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[web3::contract::tokens::Tokenizable::into_token(bid(2, 1))])),
+            _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let field = Field { x_min: 0.0.into(), y_min: 0.0.into(), x_max: 0.5.into(), y_max: 0.5.into() };
+        let (stages, on_stage) = recorded_stages();
+
+        let transaction_hash = contract.bid(Address::repeat_byte(1), 2, field, OMAmount::from_ether(1.0), OMAmount::from_ether(0.0), None, None, on_stage).await;
+        assert_eq!(transaction_hash, Some(H256::repeat_byte(0x11)));
+        assert_eq!(*stages.lock().unwrap(), vec![TxStage::Submitted(H256::repeat_byte(0x11))]);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(*stages.lock().unwrap(), vec![TxStage::Submitted(H256::repeat_byte(0x11)), TxStage::Confirmed]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn rejected_transaction_is_reported_as_failed() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_sendTransaction" => Err(serde_json::json!({ "code": 4001, "message": "User rejected the request." })),
+            _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let (stages, on_stage) = recorded_stages();
+
+        assert!(contract.call_with_timeout("deleteBid", (U256::one(),), Address::zero(), CONFIRMATION_TIMEOUT, on_stage).await.is_none());
+        assert_eq!(*stages.lock().unwrap(), vec![TxStage::Failed]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn single_bid_is_decoded_from_the_bids_of_its_parent() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Array(vec![
+                web3::contract::tokens::Tokenizable::into_token(bid(3, 2)),
+                web3::contract::tokens::Tokenizable::into_token(Metadata { locked_OM: OMAmount::from_ether(2.5), ..bid(5, 2) }),
+            ])])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let Ok(Some(found)) = contract.get_bid(2, 5).await else { panic!("bid not found") };
+        assert_eq!((found.token_id, found.parent_id, found.locked_OM), (5, 2, OMAmount::from_ether(2.5)));
+        assert!(matches!(contract.get_bid(2, 4).await, Ok(None)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn bid_has_to_reach_the_minimum_price_of_the_parent() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[web3::contract::tokens::Tokenizable::into_token(Metadata { minimum_price: OMAmount::from_ether(1.5), ..bid(2, 1) })])),
+            _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+        });
+        let below_minimum = Arc::new(Mutex::new(vec![]));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new({
+            let below_minimum = below_minimum.clone();
+            move |error| if let Error::BidBelowMinimum(minimum_price) = error {
+                below_minimum.lock().unwrap().push(minimum_price);
+            }
+        }));
+        let field = Field { x_min: 0.0.into(), y_min: 0.0.into(), x_max: 0.5.into(), y_max: 0.5.into() };
+        let sender = Address::repeat_byte(1);
+
+        assert!(contract.bid(sender, 2, field.clone(), OMAmount::from_ether(1.4), OMAmount::from_ether(0.0), None, None, None).await.is_none());
+        assert_eq!(*below_minimum.lock().unwrap(), vec![1.5]);
+        assert!(provider.methods().iter().all(|method| method != "eth_sendTransaction"));
+
+        assert!(contract.bid(sender, 2, field, OMAmount::from_ether(1.5), OMAmount::from_ether(0.0), None, None, None).await.is_some());
+        assert_eq!(below_minimum.lock().unwrap().len(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    async fn owned_tokens_leave_the_owned_bids_out() {
+        let owner = Address::repeat_byte(4);
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_call" => Ok(output(&[
+                ethabi::Token::Array(vec![
+                    web3::contract::tokens::Tokenizable::into_token(Metadata { owner, ..bid(3, 1) }),
+                    web3::contract::tokens::Tokenizable::into_token(Metadata { owner, ..bid(8, 3) }),
+                ]),
+                ethabi::Token::Array(vec![web3::contract::tokens::Tokenizable::into_token(Metadata { owner, ..bid(9, 2) })]),
+            ])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let Ok(tokens) = contract.get_owned_tokens(owner).await else { panic!("owned tokens not decoded") };
+        assert_eq!(tokens.iter().map(|token| (token.token_id, token.owner)).collect::<Vec<_>>(), vec![(3, owner), (8, owner)]);
+        assert_eq!(provider.calldata("eth_call"), vec![format!("{}{:024x}{owner:x}", selector("getOwnedItems"), 0)]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn mint_fee_is_converted_from_wei() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Uint(U256::from(250_000_000_000_000_000u128))])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_mint_fee().await.unwrap().to_ether(), 0.25);
+        assert_eq!(provider.calldata("eth_call"), vec![selector("MINT_FEE")]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn query_succeeding_on_the_third_attempt_returns_ok() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new({
+            let attempts = attempts.clone();
+            move |method, _| match method {
+                "eth_call" if attempts.fetch_add(1, Ordering::SeqCst) < 2 => Err(serde_json::json!("connection reset")),
+                "eth_call" => Ok(output(&[ethabi::Token::Uint(U256::from(15))])),
+                _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+            }
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_upstream_share().await.ok(), Some(0.15));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[wasm_bindgen_test]
+    async fn wrapped_token_details_are_retried_like_other_queries() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new({
+            let attempts = attempts.clone();
+            move |method, params| match method {
+                "eth_call" if attempts.fetch_add(1, Ordering::SeqCst) % 2 == 0 => Err(serde_json::json!("connection reset")),
+                "eth_call" if params[0]["data"] == "0x95d89b41" => Ok(output(&[Token::String("wOM".into())])),
+                "eth_call" => Ok(output(&[Token::Uint(U256::from(18))])),
+                _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+            }
+        });
+        let contract = ERC20Contract::at(provider.web3(), Address::repeat_byte(4), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_symbol().await.ok().as_deref(), Some("wOM"));
+        assert_eq!(contract.get_decimals().await.ok(), Some(18));
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+
+    #[wasm_bindgen_test]
+    async fn contract_errors_are_not_retried() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let provider = MockProvider::new({
+            let attempts = attempts.clone();
+            move |_, _| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(serde_json::json!({ "code": 3, "message": "execution reverted", "data": "0x12345678" }))
+            }
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(contract.get_metadata(9).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[wasm_bindgen_test]
+    async fn locked_amount_is_read_from_the_metadata() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[web3::contract::tokens::Tokenizable::into_token(Metadata { locked_OM: OMAmount::from_ether(12.5), ..bid(4, 1) })])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_locked_OM(4).await.ok(), Some(OMAmount::from_ether(12.5)));
+        assert_eq!(provider.calldata("eth_call"), vec![format!("{}{:064x}", selector("getMetadata"), 4)]);
+    }
+
+    fn rpc_error(code: i64, message: &str, data: Option<serde_json::Value>) -> web3::contract::Error {
+        web3::contract::Error::Api(web3::Error::Rpc(jsonrpc_core::Error { code: code.into(), message: message.into(), data }))
+    }
+
+    fn revert_data(reason: &str) -> String {
+        let encoded = ethabi::encode(&[Token::String(reason.into())]);
+        format!("{REVERT_REASON_SELECTOR}{}", encoded.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+    }
+
+    #[wasm_bindgen_test]
+    fn rejection_in_the_wallet_is_recognized() {
+        assert!(matches!(decode_error(&rpc_error(USER_REJECTED_CODE, "User denied transaction signature", None)), Some(Error::Rejected)));
+    }
+
+    #[wasm_bindgen_test]
+    fn revert_reason_is_decoded() {
+        let error = rpc_error(3, "execution reverted: Bid too low", Some(revert_data("Bid too low").into()));
+        assert!(matches!(decode_error(&error), Some(Error::Reverted(reason)) if reason == "Bid too low"));
+    }
+
+    #[wasm_bindgen_test]
+    fn revert_reason_nested_by_the_wallet_is_decoded() {
+        let data = serde_json::json!({ "originalError": { "data": revert_data("Bid too low"), "message": "execution reverted" } });
+        let error = rpc_error(-32603, "Internal JSON-RPC error.", Some(data));
+        assert!(matches!(decode_error(&error), Some(Error::Reverted(reason)) if reason == "Bid too low"));
+    }
+
+    #[wasm_bindgen_test]
+    fn revert_reason_is_read_from_the_message_without_data() {
+        let error = rpc_error(-32000, "execution reverted: Bid too low", None);
+        assert!(matches!(decode_error(&error), Some(Error::Reverted(reason)) if reason == "Bid too low"));
+    }
+
+    #[wasm_bindgen_test]
+    fn custom_contract_errors_are_decoded() {
+        let data = serde_json::json!({ "originalError": { "data": "0xa0d26eb6", "message": "execution reverted" } });
+        assert!(matches!(decode_error(&rpc_error(-32603, "Internal JSON-RPC error.", Some(data))), Some(Error::BidTooLow)));
+        let data = serde_json::json!({ "originalError": { "data": "0x12345678", "message": "unknown" } });
+        assert!(matches!(decode_error(&rpc_error(-32603, "Internal JSON-RPC error.", Some(data))), Some(Error::Other(message)) if message == "unknown"));
+    }
+
+    #[wasm_bindgen_test]
+    fn other_rpc_errors_keep_their_message() {
+        let error = rpc_error(-32005, "rate limit exceeded", None);
+        assert!(matches!(decode_error(&error), Some(Error::Rpc(message)) if message == "rate limit exceeded"));
+    }
+
+    #[wasm_bindgen_test]
+    fn errors_not_coming_from_the_node_are_not_decoded() {
+        assert!(decode_error(&web3::contract::Error::InvalidOutputType("bool".into())).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    async fn mint_estimate_takes_the_arguments_of_the_mint() {
+        let field = Field { x_min: 0.0.into(), y_min: 0.0.into(), x_max: 0.5.into(), y_max: 0.5.into() };
+        let recipient = Address::repeat_byte(1);
+        assert_eq!(mint_params(2, recipient, field.clone()).into_tokens(), vec![
+            ethabi::Token::Uint(U256::from(2)),
+            ethabi::Token::Address(recipient),
+            web3::contract::tokens::Tokenizable::into_token(field.clone()),
+        ]);
+
+        // the bundled ABI has no mintNFT, so there is nothing to estimate rather than a made up amount
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        assert!(contract.estimate_mint_gas(2, recipient, field).await.is_err());
+        assert!(provider.methods().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn last_page_of_children_is_shorter() {
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Array(
+                (3..8).map(|token_id| web3::contract::tokens::Tokenizable::into_token(bid(token_id, 2))).collect()
+            )])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let page = |offset, limit| {
+            let contract = contract.clone();
+            async move {
+                contract.get_children_metadata_paged(2, offset, limit).await.unwrap().iter().map(|token| token.token_id).collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(page(0, 2).await, vec![3, 4]);
+        assert_eq!(page(4, 2).await, vec![7]);
+        assert_eq!(page(6, 2).await, Vec::<u128>::new());
+    }
+
+    #[wasm_bindgen_test]
+    async fn mints_are_transfers_from_the_zero_address() {
+        let logs = Arc::new(Mutex::new(serde_json::json!([])));
+        let provider = MockProvider::new({
+            let logs = logs.clone();
+            move |method, _| match method {
+                "eth_getLogs" => Ok(logs.lock().unwrap().clone()),
+                _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+            }
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        assert!(!contract.minted_between(5.into(), 7.into()).await.unwrap());
+        *logs.lock().unwrap() = serde_json::json!([transfer_single_log(Address::repeat_byte(3), 9)]);
+        assert!(contract.minted_between(8.into(), 9.into()).await.unwrap());
+
+        let filter = &provider.params("eth_getLogs")[1][0];
+        assert_eq!(filter["fromBlock"], "0x8");
+        assert_eq!(filter["toBlock"], "0x9");
+        assert_eq!(filter["topics"][2], serde_json::json!([H256::zero()]));
+    }
+
+    #[wasm_bindgen_test]
+    async fn withdrawing_a_bid_sends_its_id_from_the_bidder() {
+        let provider = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let bidder = Address::repeat_byte(7);
+
+        assert!(contract.delete_bid(bidder, 12).await.is_some());
+        assert_eq!(provider.calldata("eth_sendTransaction"), vec![format!("{}{:064x}", selector("deleteBid"), 12)]);
+        assert_eq!(provider.params("eth_sendTransaction")[0][0]["from"], serde_json::json!(format!("{bidder:?}")));
+    }
+
+    #[wasm_bindgen_test]
+    async fn bid_costing_more_than_the_cap_with_the_fee_is_not_submitted() {
+        let provider = MockProvider::new(|method, params| match method {
+            "eth_call" if params[0]["data"] == selector("MINT_FEE") => Ok(output(&[ethabi::Token::Uint(U256::from(250_000_000_000_000_000u128))])),
+            "eth_call" => Ok(output(&[web3::contract::tokens::Tokenizable::into_token(bid(2, 1))])),
+            _ => crate::evm::mock::confirmed_transaction(method).unwrap(),
+        });
+        let above_maximum = Arc::new(Mutex::new(vec![]));
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new({
+            let above_maximum = above_maximum.clone();
+            move |error| if let Error::BidAboveMaximum(cost) = error {
+                above_maximum.lock().unwrap().push(cost);
+            }
+        }));
+        let field = Field { x_min: 0.0.into(), y_min: 0.0.into(), x_max: 0.5.into(), y_max: 0.5.into() };
+        let sender = Address::repeat_byte(1);
+
+        let capped = |max_amount| contract.bid(sender, 2, field.clone(), OMAmount::from_ether(1.0), OMAmount::from_ether(0.0), Some(OMAmount::from_ether(max_amount)), None, None);
+        assert!(capped(1.2).await.is_none());
+        assert_eq!(*above_maximum.lock().unwrap(), vec![1.25]);
+        assert!(provider.methods().iter().all(|method| method != "eth_sendTransaction"));
+
+        assert!(capped(1.25).await.is_some());
+        assert_eq!(above_maximum.lock().unwrap().len(), 1);
+        // the cap never reaches the contract, the bid is submitted as usual
+        let calldata = provider.calldata("eth_sendTransaction");
+        assert_eq!(calldata.len(), 1);
+        assert!(calldata[0].starts_with(&selector("bid")));
+    }
+
+    #[wasm_bindgen_test]
+    async fn wrapped_token_is_called_at_its_own_address() {
+        let transaction_hash = H256::repeat_byte(1);
+        let receipt = TransactionReceipt { transaction_hash, block_number: Some(5.into()), ..Default::default() };
+        let provider = submitting_provider(transaction_hash, Some(receipt), 5 + default_confirmations() as u64);
+        let token = Address::repeat_byte(9);
+        let contract = ERC20Contract::at(provider.web3(), token, false, Arc::new(|_| {}));
+
+        assert_eq!(contract.address(), token);
+        assert!(matches!(contract.transfer(Address::repeat_byte(1), Address::repeat_byte(2), OMAmount::from_ether(1.0)).await, Some(TxStatus::Confirmed(_))));
+        assert_eq!(provider.params("eth_sendTransaction")[0][0]["to"], serde_json::json!(format!("{token:?}")));
+    }
+
+    #[wasm_bindgen_test]
+    async fn wrapped_balance_is_read_to_the_wei() {
+        let balance = U256::exp10(30) + U256::one();
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_call" => Ok(output(&[Token::Uint(balance)])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC20Contract::at(provider.web3(), Address::repeat_byte(4), false, Arc::new(|_| {}));
+
+        assert_eq!(contract.get_balance(Address::repeat_byte(1)).await.unwrap(), OMAmount::from_raw(balance));
+    }
+
+    #[wasm_bindgen_test]
+    async fn bids_are_decoded_with_the_minimum_price_they_propose() {
+        let answer = output(&[ethabi::Token::Array(vec![
+            web3::contract::tokens::Tokenizable::into_token(Metadata { locked_OM: OMAmount::from_ether(2.0), minimum_price: OMAmount::from_ether(0.5), ..bid(5, 2) }),
+        ])]);
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_call" => Ok(answer.clone()),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let Ok(bids) = contract.get_bids(2).await else { panic!("bids not decoded") };
+        assert_eq!(bids.iter().map(|bid| (bid.token_id, bid.locked_OM, bid.minimum_price)).collect::<Vec<_>>(), vec![(5, OMAmount::from_ether(2.0), OMAmount::from_ether(0.5))]);
+        assert_eq!(provider.calldata("eth_call"), vec![format!("{}{:064x}", selector("getBids"), 2)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn aggregated_calldata_encodes_each_call() {
+        let provider = MockProvider::new(|_, _| Ok(serde_json::Value::Null));
+        let multicall = Multicall3Contract::at(provider.web3(), Address::repeat_byte(3));
+        let target = Address::repeat_byte(5);
+        let erc1155 = crate::evm::mock::erc1155_abi();
+        let calls = ["getAncestryMetadata", "getBids"].into_iter()
+            .map(|method| (target, erc1155.function(method).unwrap().encode_input(&[Token::Uint(U256::from(7))]).unwrap()))
+            .collect::<Vec<_>>();
+
+        let calldata = multicall.aggregate_calldata(calls.clone()).unwrap();
+        let aggregate3 = multicall.contract.abi().function("aggregate3").unwrap();
+        assert_eq!(calldata[..4], aggregate3.short_signature());
+        assert_eq!(aggregate3.decode_input(&calldata[4..]).unwrap(), vec![Token::Array(calls.into_iter().map(|(target, calldata)| {
+            Token::Tuple(vec![Token::Address(target), Token::Bool(true), Token::Bytes(calldata)])
+        }).collect())]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn failed_calls_of_an_aggregate_have_no_output() {
+        let answer = output(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![1, 2])]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        ])]);
+        let provider = MockProvider::new(move |method, _| match method {
+            "eth_call" => Ok(answer.clone()),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let multicall = Multicall3Contract::at(provider.web3(), Address::repeat_byte(3));
+
+        let results = multicall.aggregate(vec![(Address::repeat_byte(5), vec![]), (Address::repeat_byte(5), vec![])]).await.unwrap();
+        assert_eq!(results, vec![Some(vec![1, 2]), None]);
+        assert_eq!(provider.params("eth_call")[0][0]["to"], serde_json::json!(format!("{:?}", Address::repeat_byte(3))));
+    }
+
+    #[wasm_bindgen_test]
+    async fn reads_go_through_the_read_transport_and_transactions_through_the_wallet() {
+        let wallet = MockProvider::new(|method, _| crate::evm::mock::confirmed_transaction(method).unwrap());
+        let reader = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[web3::contract::tokens::Tokenizable::into_token(bid(4, 1))])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let contract = ERC1155Contract::new(wallet.web3(), false, Arc::new(|_| {})).with_read_web3(reader.web3());
+
+        assert_eq!(contract.get_metadata(4).await.unwrap().token_id, 4);
+        assert!(contract.delete_bid(Address::repeat_byte(1), 4).await.is_some());
+        assert_eq!(reader.methods(), vec!["eth_call"]);
+        assert!(wallet.methods().contains(&"eth_sendTransaction".to_string()));
+        assert!(!wallet.methods().contains(&"eth_call".to_string()));
     }
 }
@@ -0,0 +1,127 @@
+//! EIP-1193 provider answering requests in place of a wallet, so that contracts can be tested without a node.
+
+use std::{cell::RefCell, rc::Rc};
+
+use serde_json::{json, Value};
+use wasm_bindgen::{prelude::*, JsCast};
+use web3::{
+    transports::{eip_1193::{Eip1193, Provider}, Either, Http},
+    types::{TransactionReceipt, H256},
+    Web3,
+};
+
+
+pub struct MockProvider {
+    requests: Rc<RefCell<Vec<(String, Value)>>>,
+    web3: Web3<Either<Eip1193, Http>>,
+}
+
+impl MockProvider {
+    /// Every request is answered by `respond` with its method and params, an `Err` is the JSON-RPC error object
+    /// that the request is rejected with.
+    pub fn new(respond: impl Fn(&str, &Value) -> Result<Value, Value> + 'static) -> Self {
+        let requests: Rc<RefCell<Vec<(String, Value)>>> = Rc::default();
+        let recorded = requests.clone();
+        let request = Closure::<dyn Fn(JsValue) -> js_sys::Promise>::new(move |args: JsValue| {
+            let method = js_sys::Reflect::get(&args, &"method".into()).ok()
+                .and_then(|method| method.as_string())
+                .unwrap_or_default();
+            let params = js_sys::Reflect::get(&args, &"params".into()).ok()
+                .and_then(|params| js_sys::JSON::stringify(&params).ok())
+                .and_then(|params| params.as_string())
+                .and_then(|params| serde_json::from_str(&params).ok())
+                .unwrap_or(Value::Null);
+            let response = respond(&method, &params);
+            recorded.borrow_mut().push((method, params));
+            match response {
+                Ok(result) => js_sys::Promise::resolve(&to_js(&result)),
+                Err(error) => js_sys::Promise::reject(&to_js(&error)),
+            }
+        });
+        // events are never emitted, listeners are only accepted
+        let listen = Closure::<dyn Fn(JsValue, JsValue)>::new(|_, _| {});
+
+        let provider = js_sys::Object::new();
+        js_sys::Reflect::set(&provider, &"request".into(), request.as_ref()).unwrap();
+        js_sys::Reflect::set(&provider, &"on".into(), listen.as_ref()).unwrap();
+        js_sys::Reflect::set(&provider, &"removeListener".into(), listen.as_ref()).unwrap();
+        request.forget();
+        listen.forget();
+
+        Self {
+            requests,
+            web3: Web3::new(Either::Left(Eip1193::new(provider.unchecked_into::<Provider>()))),
+        }
+    }
+
+    pub fn web3(&self) -> &Web3<Either<Eip1193, Http>> {
+        &self.web3
+    }
+
+    /// Methods requested so far, in order.
+    pub fn methods(&self) -> Vec<String> {
+        self.requests.borrow().iter().map(|(method, _)| method.clone()).collect()
+    }
+
+    /// Params of every request of `method` so far, in order.
+    pub fn params(&self, method: &str) -> Vec<Value> {
+        self.requests.borrow().iter().filter(|(requested, _)| requested == method).map(|(_, params)| params.clone()).collect()
+    }
+
+    /// Calldata of every `eth_call`, `eth_estimateGas` or `eth_sendTransaction` request of `method` so far, in order.
+    pub fn calldata(&self, method: &str) -> Vec<String> {
+        self.params(method).iter()
+            .filter_map(|params| params[0]["data"].as_str().map(String::from))
+            .collect()
+    }
+}
+
+
+/// ABI of the ERC1155 contract, to encode answers and recognize the functions called.
+pub fn erc1155_abi() -> ethabi::Contract {
+    ethabi::Contract::load(&include_bytes!("../../resources/MandelbrotNFT.json")[..]).unwrap()
+}
+
+/// Hex selector of `function` of the ERC1155 contract, as found at the start of its calldata.
+pub fn selector(function: &str) -> String {
+    let selector = erc1155_abi().function(function).unwrap().short_signature();
+    format!("0x{}", selector.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+}
+
+/// `eth_call` answer returning `tokens`.
+pub fn output(tokens: &[ethabi::Token]) -> Value {
+    serde_json::to_value(web3::types::Bytes(ethabi::encode(tokens))).unwrap()
+}
+
+/// Answer to the requests made while a transaction is submitted and confirmed, `None` for any other request.
+/// Every transaction is mined in block 5 while block 6 is the latest one, which is enough to confirm it.
+pub fn confirmed_transaction(method: &str) -> Option<Result<Value, Value>> {
+    let transaction_hash = H256::repeat_byte(0x11);
+    match method {
+        "eth_estimateGas" => Some(Ok(json!("0x5208"))),
+        "eth_sendTransaction" => Some(Ok(json!(transaction_hash))),
+        "eth_newBlockFilter" => Some(Ok(json!("0x1"))),
+        "eth_getFilterChanges" => Some(Ok(json!([H256::repeat_byte(5), H256::repeat_byte(6)]))),
+        "eth_getTransactionReceipt" => Some(Ok(serde_json::to_value(TransactionReceipt {
+            transaction_hash,
+            block_number: Some(5.into()),
+            ..Default::default()
+        }).unwrap())),
+        "eth_blockNumber" => Some(Ok(json!("0x6"))),
+        _ => None,
+    }
+}
+
+/// Rejection of a call reverted by the contract with the custom error `code`, as relayed by the wallet.
+pub fn revert(code: &str) -> Value {
+    json!({
+        "code": -32603,
+        "message": "execution reverted",
+        "data": { "originalError": { "code": 3, "data": code, "message": "execution reverted" } },
+    })
+}
+
+
+fn to_js(value: &Value) -> JsValue {
+    js_sys::JSON::parse(&value.to_string()).unwrap()
+}
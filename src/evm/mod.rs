@@ -1,2 +1,7 @@
+pub mod abi;
+pub mod blocks;
 pub mod contracts;
+#[cfg(test)]
+pub mod mock;
 pub mod types;
+pub mod wallet;
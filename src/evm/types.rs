@@ -1,14 +1,97 @@
-use std::ops::Deref;
+use std::{iter::Sum, ops::{Add, Deref}};
 
 use ethabi::token::Token;
+use serde_json::{json, Value};
 use web3::{
     contract::tokens::Tokenizable,
-    types::{Address, U256},
+    types::{Address, U256, U64},
 };
 
 use mandelbrot_explorer::{BigFloat, Radix};
 
 
+/// Token covering the whole set, every other token descends from it.
+pub const ROOT_TOKEN_ID: u128 = 1;
+
+/// Whether the deployment lets users bid directly on the root token, configurable through the `ALLOW_ROOT_BIDS` env variable.
+pub fn root_bids_allowed() -> bool {
+    option_env!("ALLOW_ROOT_BIDS").map_or(true, |allowed| allowed != "false")
+}
+
+/// Step of the bid amount stepper in OM, configurable through the `BID_INCREMENT` env variable.
+pub fn bid_increment() -> f64 {
+    option_env!("BID_INCREMENT").and_then(|increment| increment.parse().ok()).filter(|increment| *increment > 0.0).unwrap_or(0.1)
+}
+
+/// Token shown when the URL doesn't point to one, configurable through the `DEFAULT_TOKEN_ID` env variable.
+pub fn default_token_id() -> u128 {
+    initial_token_id(None, option_env!("DEFAULT_TOKEN_ID"))
+}
+
+/// Token from the URL, otherwise the `configured` one, otherwise the root.
+pub fn initial_token_id(url_token_id: Option<u128>, configured: Option<&str>) -> u128 {
+    url_token_id
+        .or_else(|| configured.and_then(|token_id| token_id.parse().ok()))
+        .unwrap_or(ROOT_TOKEN_ID)
+}
+
+
+/// Amount of OM as the raw on-chain integer with 18 decimals, so that amounts entered by the user are submitted exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OMAmount(U256);
+
+impl OMAmount {
+    const DECIMALS: usize = 18;
+
+    pub fn from_raw(raw: U256) -> Self {
+        Self(raw)
+    }
+
+    /// Converted through the shortest decimal representation of `amount`, so that `0.1` becomes exactly `10^17`.
+    /// Negative and non finite amounts become zero.
+    pub fn from_ether(amount: f64) -> Self {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Self::default()
+        }
+        let amount = amount.to_string();
+        let (whole, fraction) = amount.split_once('.').unwrap_or((&amount, ""));
+        let fraction = format!("{:0<width$}", &fraction[..fraction.len().min(Self::DECIMALS)], width = Self::DECIMALS);
+        Self(
+            U256::from_dec_str(whole).unwrap_or_default() * U256::exp10(Self::DECIMALS) +
+                U256::from_dec_str(&fraction).unwrap_or_default()
+        )
+    }
+
+    pub fn to_ether(&self) -> f64 {
+        let (whole, fraction) = self.0.div_mod(U256::exp10(Self::DECIMALS));
+        format!("{whole}.{:0width$}", fraction.as_u128(), width = Self::DECIMALS).parse().unwrap_or_default()
+    }
+
+    pub fn raw(&self) -> U256 {
+        self.0
+    }
+}
+
+impl Add for OMAmount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sum for OMAmount {
+    fn sum<I: Iterator<Item = Self>>(amounts: I) -> Self {
+        amounts.fold(Self::default(), Add::add)
+    }
+}
+
+
+/// Hex digits after the point of a coordinate encoded as a 256-bit fixed point number, one digit is left for the whole part.
+/// Fields narrower than `16^-63`, roughly 250 halvings of the full view, cannot be told apart on chain.
+const FRACTION_HEX_DIGITS: usize = 63;
+
+/// Coordinate as stored by the contract, shifted to be positive and encoded in fixed point without going through f64.
 struct TokenizableBigFloat(BigFloat);
 
 impl Deref for TokenizableBigFloat {
@@ -22,16 +105,20 @@ impl Deref for TokenizableBigFloat {
 impl TokenizableBigFloat {
     fn from_token(token: Token) -> Result<Self, web3::contract::Error> {
         let s = format!("{:x}", U256::from_token(token)?);
-        Ok(Self(BigFloat::parse(&s, Radix::Hex) / BigFloat::from(BigFloat::from(16f64.powf(63.0)))))
+        Ok(Self(BigFloat::parse(&s, Radix::Hex) / BigFloat::from(BigFloat::from(16f64.powf(FRACTION_HEX_DIGITS as f64)))))
     }
 
     fn into_token(self) -> Token {
-        if let Ok((_, digits, exponent)) = (&*self * BigFloat::from(BigFloat::from(16f64.powf(63.0)))).convert_to_radix(Radix::Hex) {
-            let s: String = digits.into_iter().map(|d| format!("{d:x}")).collect();
-            U256::from_str_radix(&s[..64.min(exponent as usize)], 16).unwrap()
-        } else {
+        match (&*self * BigFloat::from(BigFloat::from(16f64.powf(FRACTION_HEX_DIGITS as f64)))).convert_to_radix(Radix::Hex) {
+            Ok((_, digits, exponent)) if exponent <= FRACTION_HEX_DIGITS as i32 + 1 => {
+                // digits below the fixed point are dropped, the ones the mantissa doesn't reach are zeros
+                let length = exponent.max(0) as usize;
+                let mut s: String = digits.into_iter().take(length).map(|d| format!("{d:x}")).collect();
+                s.extend(std::iter::repeat('0').take(length - s.len()));
+                if s.is_empty() { U256::zero() } else { U256::from_str_radix(&s, 16).unwrap() }
+            }
             // This coordinate is invalid, so we return it, in case of an error, to be handled upstream
-            U256::from(3) * U256::from(10).pow(U256::from(76))
+            _ => U256::from(3) * U256::from(10).pow(U256::from(76)),
         }.into_token()
     }
 }
@@ -45,6 +132,124 @@ pub struct Field {
     pub y_max: BigFloat,
 }
 
+impl Field {
+    pub fn area(&self) -> BigFloat {
+        (&self.x_max - &self.x_min) * (&self.y_max - &self.y_min)
+    }
+
+    pub fn contains(&self, x: &BigFloat, y: &BigFloat) -> bool {
+        &self.x_min <= x && x <= &self.x_max && &self.y_min <= y && y <= &self.y_max
+    }
+
+    /// Position and size of this field as fractions of `outer`, as `[left, top, width, height]` with the top at `y_max`.
+    pub fn portion_of(&self, outer: &Field) -> [f64; 4] {
+        let width = &outer.x_max - &outer.x_min;
+        let height = &outer.y_max - &outer.y_min;
+        let fraction = |value: BigFloat, total: &BigFloat| to_f64(&(value / total.clone())).clamp(0.0, 1.0);
+        [
+            fraction(&self.x_min - &outer.x_min, &width),
+            fraction(&outer.y_max - &self.y_max, &height),
+            fraction(&self.x_max - &self.x_min, &width),
+            fraction(&self.y_max - &self.y_min, &height),
+        ]
+    }
+
+    /// Whether both fields cover the same region, up to `tolerance` as a fraction of this field's width.
+    pub fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        let tolerance = (&self.x_max - &self.x_min) * BigFloat::from(tolerance);
+        let close = |a: &BigFloat, b: &BigFloat| a - b <= tolerance && b - a <= tolerance;
+        close(&self.x_min, &other.x_min) &&
+            close(&self.y_min, &other.y_min) &&
+            close(&self.x_max, &other.x_max) &&
+            close(&self.y_max, &other.y_max)
+    }
+}
+
+/// How an NFT may be subdivided by the NFTs minted inside of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SubdivisionRule {
+    /// Any rectangle within the parent.
+    #[default]
+    Free,
+    /// Rectangles made of whole cells of a `columns` by `rows` grid over the parent.
+    Grid { columns: u32, rows: u32 },
+}
+
+impl SubdivisionRule {
+    /// Field conforming to the rule, every edge of a grid field moves to the nearest grid line,
+    /// keeping it at least one cell large.
+    pub fn snap(&self, field: Field, parent: &Field) -> Field {
+        match *self {
+            Self::Free => field,
+            Self::Grid { columns, rows } => {
+                let (x_min, x_max) = snap_span(&field.x_min, &field.x_max, &parent.x_min, &parent.x_max, columns);
+                let (y_min, y_max) = snap_span(&field.y_min, &field.y_max, &parent.y_min, &parent.y_max, rows);
+                Field { x_min, y_min, x_max, y_max }
+            }
+        }
+    }
+}
+
+fn snap_span(min: &BigFloat, max: &BigFloat, start: &BigFloat, end: &BigFloat, cells: u32) -> (BigFloat, BigFloat) {
+    let cells = cells.max(1);
+    let line = |index: u32| start + (end - start) * BigFloat::from(index as f64 / cells as f64);
+    let nearest = |value: &BigFloat| (0..=cells)
+        .map(|index| (if value >= &line(index) { value - line(index) } else { line(index) - value }, index))
+        .min_by(|(distance_a, _), (distance_b, _)| distance_a.partial_cmp(distance_b).unwrap_or(std::cmp::Ordering::Equal))
+        .map_or(0, |(_, index)| index);
+    let first = nearest(min).min(cells - 1);
+    let last = nearest(max).max(first + 1);
+    (line(first), line(last))
+}
+
+/// Minimum price that bids inside of a newly minted NFT have to offer. The contract takes a single price,
+/// so every policy is resolved for the minted field before the bid is encoded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MinimumPricePolicy {
+    Flat(f64),
+    /// Price of the whole parent NFT, charged pro rata to the area of the minted field.
+    PerArea(f64),
+    /// `base` multiplied by `factor` for every layer of depth of the minted NFT.
+    PerDepth { base: f64, factor: f64 },
+}
+
+impl Default for MinimumPricePolicy {
+    fn default() -> Self {
+        Self::Flat(0.0)
+    }
+}
+
+impl MinimumPricePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Self::Flat(price) | Self::PerArea(price) if !(price >= 0.0) => Err("Minimum bid price cannot be negative".into()),
+            Self::PerDepth { base, .. } if !(base >= 0.0) => Err("Base minimum bid price cannot be negative".into()),
+            Self::PerDepth { factor, .. } if !(factor > 0.0) => Err("Depth factor has to be positive".into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn minimum_price(&self, field: &Field, parent: &Metadata) -> f64 {
+        match *self {
+            Self::Flat(price) => price,
+            Self::PerArea(price) => price * to_f64(&(field.area() / parent.field.area())),
+            Self::PerDepth { base, factor } => base * factor.powi((parent.layer + 1) as i32),
+        }
+    }
+}
+
+/// Approximation of a positive value, precise enough for prices.
+fn to_f64(value: &BigFloat) -> f64 {
+    match value.convert_to_radix(Radix::Dec) {
+        Ok((_, digits, exponent)) => {
+            let digits = &digits[..digits.len().min(17)];
+            let mantissa = digits.iter().fold(0.0, |mantissa, digit| mantissa * 10.0 + *digit as f64);
+            mantissa * 10_f64.powi(exponent - digits.len() as i32)
+        }
+        Err(_) => 0.0,
+    }
+}
+
 impl Tokenizable for Field {
     fn from_token(token: Token) -> Result<Self, web3::contract::Error> {
         match token {
@@ -73,14 +278,17 @@ impl Tokenizable for Field {
 impl web3::contract::tokens::TokenizableItem for Field {}
 
 
+/// Token as returned by the contract, `(tokenId, owner, parentId, field, lockedOM, minimumBid, layer)`,
+/// plus `owned` and `selected` which only exist on the frontend.
+/// The contract stores no rendering parameters, every token is a region of the standard Mandelbrot set.
 #[derive(Clone, Debug)]
 pub struct Metadata {
     pub token_id: u128,
     pub owner: Address,
-    parent_id: u128,
+    pub parent_id: u128,
     pub field: Field,
-    pub locked_OM: f64,
-    pub minimum_price: f64,
+    pub locked_OM: OMAmount,
+    pub minimum_price: OMAmount,
     pub layer: u128,
     pub owned: bool,
     pub selected: bool,
@@ -102,8 +310,8 @@ impl Tokenizable for Metadata {
                     owner: Address::from_token(tokens[1].clone())?,
                     parent_id: U256::from_token(tokens[2].clone())?.as_u128(),
                     field: Field::from_token(tokens[3].clone())?,
-                    locked_OM: U256::from_token(tokens[4].clone())?.as_u128() as f64 / 10_f64.powi(18),
-                    minimum_price: U256::from_token(tokens[5].clone())?.as_u128() as f64 / 10_f64.powi(18),
+                    locked_OM: OMAmount::from_raw(U256::from_token(tokens[4].clone())?),
+                    minimum_price: OMAmount::from_raw(U256::from_token(tokens[5].clone())?),
                     layer: U256::from_token(tokens[6].clone())?.as_u128(),
                     owned: false,
                     selected: false,
@@ -119,8 +327,8 @@ impl Tokenizable for Metadata {
             self.owner.into_token(),
             self.parent_id.into_token(),
             self.field.into_token(),
-            U256::from(((self.locked_OM) * 10_f64.powi(18)) as u128).into_token(),
-            U256::from(((self.minimum_price) * 10_f64.powi(18)) as u128).into_token(),
+            self.locked_OM.raw().into_token(),
+            self.minimum_price.raw().into_token(),
             self.layer.into_token(),
         ])
     }
@@ -129,6 +337,27 @@ impl Tokenizable for Metadata {
 impl web3::contract::tokens::TokenizableItem for Metadata {}
 
 impl Metadata {
+    /// Token with both its raw ABI values and their decoded form, see [`node_export`].
+    pub fn to_json(&self) -> Value {
+        json!({
+            "raw": raw_json(&self.clone().into_token()),
+            "decoded": {
+                "token_id": self.token_id.to_string(),
+                "owner": format!("{:?}", self.owner),
+                "parent_id": self.parent_id.to_string(),
+                "field": {
+                    "x_min": self.field.x_min.to_string(),
+                    "y_min": self.field.y_min.to_string(),
+                    "x_max": self.field.x_max.to_string(),
+                    "y_max": self.field.y_max.to_string(),
+                },
+                "locked_OM": self.locked_OM.to_ether(),
+                "minimum_price": self.minimum_price.to_ether(),
+                "layer": self.layer.to_string(),
+            },
+        })
+    }
+
     pub fn to_frame(&self, color: mandelbrot_explorer::FrameColor) -> mandelbrot_explorer::Frame {
         mandelbrot_explorer::Frame {
             id: self.token_id,
@@ -138,19 +367,353 @@ impl Metadata {
                 y_min: self.field.y_min.clone(),
                 y_max: self.field.y_max.clone(),
             },
-            color: if self.selected {
-                mandelbrot_explorer::FrameColor::Green
-            } else {
-                if self.owned {
-                    match color {
-                        mandelbrot_explorer::FrameColor::Red => mandelbrot_explorer::FrameColor::Pink,
-                        mandelbrot_explorer::FrameColor::Yellow => mandelbrot_explorer::FrameColor::Lemon,
-                        _ => mandelbrot_explorer::FrameColor::LightBlue
-                    }
-                } else {
-                    color
+            color: if self.owned {
+                match color {
+                    mandelbrot_explorer::FrameColor::Red => mandelbrot_explorer::FrameColor::Pink,
+                    mandelbrot_explorer::FrameColor::Yellow => mandelbrot_explorer::FrameColor::Lemon,
+                    _ => mandelbrot_explorer::FrameColor::LightBlue
                 }
+            } else {
+                color
             },
         }
     }
 }
+
+
+/// Bid locking the most OM, the earliest one, i.e. with the lowest id, when several lock the same amount.
+pub fn highest_bid<'a>(bids: impl IntoIterator<Item = &'a Metadata>) -> Option<&'a Metadata> {
+    bids.into_iter().max_by(|bid_a, bid_b| {
+        bid_a.locked_OM.cmp(&bid_b.locked_OM)
+            .then(bid_b.token_id.cmp(&bid_a.token_id))
+    })
+}
+
+
+/// Version of the [`node_export`] schema, bumped on any incompatible change.
+pub const NODE_EXPORT_SCHEMA: &str = "mandelbrot-node/1";
+
+/// Snapshot of a token together with its children and open bids.
+///
+/// Every token is an object with `raw`, the ABI tuple `(tokenId, owner, parentId, field, lockedOM, minimumBid, layer)`
+/// where integers are decimal strings and addresses hex strings, and `decoded`, the same values as shown by the frontend.
+pub fn node_export(token: &Metadata, children: &[Metadata], bids: &[Metadata]) -> Value {
+    json!({
+        "schema": NODE_EXPORT_SCHEMA,
+        "token": token.to_json(),
+        "children": children.iter().map(Metadata::to_json).collect::<Vec<_>>(),
+        "bids": bids.iter().map(Metadata::to_json).collect::<Vec<_>>(),
+    })
+}
+
+pub const NODE_HISTORY_SCHEMA: &str = "mandelbrot-node-history/1";
+
+/// Children and open bids of a token as of the end of `block`.
+#[derive(Clone)]
+pub struct NodeSnapshot {
+    pub block: U64,
+    pub children: Vec<Metadata>,
+    pub bids: Vec<Metadata>,
+}
+
+impl NodeSnapshot {
+    /// Whether both snapshots hold the same tokens and bids with the same amounts.
+    pub fn same_state(&self, other: &Self) -> bool {
+        let key = |tokens: &[Metadata]| tokens.iter().map(|token| (token.token_id, token.locked_OM, token.minimum_price)).collect::<Vec<_>>();
+        key(&self.children) == key(&other.children) && key(&self.bids) == key(&other.bids)
+    }
+}
+
+/// Chronological snapshots of a token, each with tokens in the same format as `node_export`.
+pub fn node_history_export(token: &Metadata, snapshots: &[NodeSnapshot]) -> Value {
+    json!({
+        "schema": NODE_HISTORY_SCHEMA,
+        "token": token.to_json(),
+        "snapshots": snapshots.iter().map(|snapshot| json!({
+            "block": snapshot.block.to_string(),
+            "children": snapshot.children.iter().map(Metadata::to_json).collect::<Vec<_>>(),
+            "bids": snapshot.bids.iter().map(Metadata::to_json).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn raw_json(token: &Token) -> Value {
+    match token {
+        Token::Uint(value) | Token::Int(value) => Value::String(value.to_string()),
+        Token::Address(address) => Value::String(format!("{address:?}")),
+        Token::Tuple(tokens) => Value::Array(tokens.iter().map(raw_json).collect()),
+        token => Value::String(token.to_string()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    fn field(x_min: f64, y_min: f64, x_max: f64, y_max: f64) -> Field {
+        Field {
+            x_min: BigFloat::from(x_min),
+            y_min: BigFloat::from(y_min),
+            x_max: BigFloat::from(x_max),
+            y_max: BigFloat::from(y_max),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn fields_within_tolerance_match() {
+        let view = field(-1.0, -0.5, 0.0, 0.5);
+        assert!(view.approx_eq(&field(-1.0, -0.5, 0.0, 0.5), 0.001));
+        // 0.0005 off on a view 1.0 wide
+        assert!(view.approx_eq(&field(-0.9995, -0.5005, 0.0005, 0.5), 0.001));
+    }
+
+    #[wasm_bindgen_test]
+    fn near_miss_does_not_match() {
+        let view = field(-1.0, -0.5, 0.0, 0.5);
+        assert!(!view.approx_eq(&field(-0.998, -0.5, 0.0, 0.5), 0.001));
+        assert!(!view.approx_eq(&field(-1.0, -0.5, 0.0, 0.502), 0.001));
+    }
+
+    fn bid(token_id: u128, amount: f64) -> Metadata {
+        Metadata {
+            token_id,
+            owner: Address::zero(),
+            parent_id: 1,
+            field: field(0.0, 0.0, 1.0, 1.0),
+            locked_OM: OMAmount::from_ether(amount),
+            minimum_price: OMAmount::default(),
+            layer: 2,
+            owned: false,
+            selected: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn highest_bid_locks_the_most() {
+        let bids = [bid(2, 1.0), bid(3, 5.0), bid(4, 2.5)];
+        assert_eq!(highest_bid(&bids).map(|bid| bid.token_id), Some(3));
+    }
+
+    #[wasm_bindgen_test]
+    fn earliest_bid_wins_a_tie() {
+        let bids = [bid(4, 5.0), bid(2, 5.0), bid(3, 1.0)];
+        assert_eq!(highest_bid(&bids).map(|bid| bid.token_id), Some(2));
+        assert_eq!(highest_bid(bids.iter().rev()).map(|bid| bid.token_id), Some(2));
+    }
+
+    #[wasm_bindgen_test]
+    fn no_bids_have_no_highest_bid() {
+        assert!(highest_bid(&[]).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn node_export_follows_the_schema() {
+        let token = Metadata { token_id: 2, parent_id: 1, ..bid(2, 10.0) };
+        let exported = node_export(&token, &[bid(3, 1.0)], &[bid(4, 2.5)]);
+
+        assert_eq!(exported["schema"], NODE_EXPORT_SCHEMA);
+        let raw = exported["token"]["raw"].as_array().unwrap();
+        assert_eq!(raw.len(), 7);
+        assert_eq!(raw[0], "2");
+        assert_eq!(raw[1], format!("{:?}", Address::zero()));
+        assert_eq!(raw[2], "1");
+        assert_eq!(raw[3].as_array().unwrap().len(), 4);
+        assert!(raw[3].as_array().unwrap().iter().all(|coordinate| coordinate.as_str().unwrap().chars().all(|c| c.is_ascii_digit())));
+        assert_eq!(raw[4], "10000000000000000000");
+        assert_eq!(raw[5], "0");
+        assert_eq!(raw[6], "2");
+        assert_eq!(exported["token"]["decoded"]["token_id"], "2");
+        assert_eq!(exported["token"]["decoded"]["locked_OM"], 10.0);
+        assert_eq!(exported["token"]["decoded"]["field"]["x_max"], BigFloat::from(1.0).to_string());
+        assert_eq!(exported["children"].as_array().unwrap().len(), 1);
+        assert_eq!(exported["children"][0]["decoded"]["token_id"], "3");
+        assert_eq!(exported["bids"][0]["decoded"]["minimum_price"], 0.0);
+        assert_eq!(exported["bids"][0]["decoded"]["layer"], "2");
+    }
+
+    #[wasm_bindgen_test]
+    fn configured_token_is_shown_without_a_url_token() {
+        assert_eq!(initial_token_id(None, Some("42")), 42);
+        assert_eq!(initial_token_id(Some(7), Some("42")), 7);
+        assert_eq!(initial_token_id(None, Some("not a token")), ROOT_TOKEN_ID);
+        assert_eq!(initial_token_id(None, None), ROOT_TOKEN_ID);
+    }
+
+    }
+
+    #[wasm_bindgen_test]
+    fn grid_rule_snaps_the_field_to_the_nearest_cells() {
+        let rule = SubdivisionRule::Grid { columns: 4, rows: 4 };
+        let snapped = rule.snap(field(0.3, 0.1, 0.45, 0.4), &field(0.0, 0.0, 1.0, 1.0));
+        assert!(snapped.approx_eq(&field(0.25, 0.0, 0.5, 0.5), 1e-9));
+    }
+
+    #[wasm_bindgen_test]
+    fn grid_rule_keeps_at_least_one_cell() {
+        let rule = SubdivisionRule::Grid { columns: 4, rows: 2 };
+        let snapped = rule.snap(field(0.3, 0.3, 0.31, 0.31), &field(0.0, 0.0, 1.0, 1.0));
+        assert!(snapped.approx_eq(&field(0.25, 0.5, 0.5, 1.0), 1e-9));
+        let snapped = rule.snap(field(0.95, 0.9, 1.0, 1.0), &field(0.0, 0.0, 1.0, 1.0));
+        assert!(snapped.approx_eq(&field(0.75, 0.5, 1.0, 1.0), 1e-9));
+    }
+
+    #[wasm_bindgen_test]
+    fn free_rule_keeps_the_field() {
+        let snapped = SubdivisionRule::Free.snap(field(0.3, 0.1, 0.45, 0.4), &field(0.0, 0.0, 1.0, 1.0));
+        assert!(snapped.approx_eq(&field(0.3, 0.1, 0.45, 0.4), 1e-9));
+    }
+
+
+    #[wasm_bindgen_test]
+    fn minimum_price_follows_the_policy() {
+        let parent = Metadata { layer: 2, ..bid(2, 0.0) };
+        let quarter = field(0.0, 0.0, 0.5, 0.5);
+        assert_eq!(MinimumPricePolicy::Flat(3.0).minimum_price(&quarter, &parent), 3.0);
+        assert!((MinimumPricePolicy::PerArea(4.0).minimum_price(&quarter, &parent) - 1.0).abs() < 1e-9);
+        // minted NFT is one layer deeper than its parent
+        assert!((MinimumPricePolicy::PerDepth { base: 1.0, factor: 2.0 }.minimum_price(&quarter, &parent) - 8.0).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test]
+    fn policies_with_negative_prices_are_invalid() {
+        assert!(MinimumPricePolicy::Flat(0.0).validate().is_ok());
+        assert!(MinimumPricePolicy::PerDepth { base: 1.0, factor: 0.5 }.validate().is_ok());
+        assert!(MinimumPricePolicy::Flat(-1.0).validate().is_err());
+        assert!(MinimumPricePolicy::PerArea(f64::NAN).validate().is_err());
+        assert!(MinimumPricePolicy::PerDepth { base: -1.0, factor: 2.0 }.validate().is_err());
+        assert!(MinimumPricePolicy::PerDepth { base: 1.0, factor: 0.0 }.validate().is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn amounts_convert_exactly() {
+        assert_eq!(OMAmount::from_ether(1.0).raw(), U256::exp10(18));
+        assert_eq!(OMAmount::from_ether(0.1).raw(), U256::exp10(17));
+        assert_eq!(OMAmount::from_ether(1.25).raw(), U256::from(1_250_000_000_000_000_000u128));
+        assert_eq!(OMAmount::from_ether(0.000000000000000001).raw(), U256::one());
+        assert_eq!(OMAmount::from_ether(1000000.5).raw(), U256::from(1_000_000_500_000_000_000_000_000u128));
+    }
+
+    #[wasm_bindgen_test]
+    fn amounts_round_trip() {
+        for amount in [0.0, 0.1, 0.3, 1.0, 2.5, 123.456789, 1e-18, 1e6] {
+            assert_eq!(OMAmount::from_ether(amount).to_ether(), amount);
+        }
+        let raw = U256::from(123_456_789_000_000_000_000u128);
+        assert_eq!(OMAmount::from_raw(raw).raw(), raw);
+        assert_eq!(OMAmount::from_ether(OMAmount::from_raw(raw).to_ether()).raw(), raw);
+    }
+
+    #[wasm_bindgen_test]
+    fn digits_past_18_decimals_are_dropped() {
+        assert_eq!(OMAmount::from_ether(0.0000000000000000001).raw(), U256::zero());
+        assert_eq!(OMAmount::from_ether(0.0000000000000000019).raw(), U256::one());
+    }
+
+    #[wasm_bindgen_test]
+    fn negative_and_non_finite_amounts_are_zero() {
+        assert_eq!(OMAmount::from_ether(-1.0), OMAmount::default());
+        assert_eq!(OMAmount::from_ether(f64::NAN), OMAmount::default());
+        assert_eq!(OMAmount::from_ether(f64::INFINITY), OMAmount::default());
+    }
+
+    #[wasm_bindgen_test]
+    fn amounts_compare_by_raw_value() {
+        assert!(OMAmount::from_ether(0.3) > OMAmount::from_ether(0.1));
+        assert_eq!(OMAmount::from_ether(0.1 + 0.2), OMAmount::from_raw(U256::from(300_000_000_000_000_040u128)));
+    }
+
+    #[wasm_bindgen_test]
+    fn amounts_add_up_exactly() {
+        let amounts = [OMAmount::from_ether(0.1), OMAmount::from_ether(0.2), OMAmount::from_raw(U256::one())];
+        assert_eq!(amounts.into_iter().sum::<OMAmount>(), OMAmount::from_raw(U256::from(300_000_000_000_000_001u128)));
+        assert_eq!(OMAmount::from_raw(U256::MAX) + OMAmount::from_raw(U256::one()), OMAmount::from_raw(U256::MAX));
+    }
+
+    #[wasm_bindgen_test]
+    fn metadata_keeps_amounts_to_the_wei() {
+        let raw = U256::exp10(24) + U256::one();
+        let token = Metadata { locked_OM: OMAmount::from_raw(raw), minimum_price: OMAmount::from_raw(U256::one()), ..bid(2, 0.0) };
+        let decoded = Metadata::from_token(token.into_token()).unwrap();
+        assert_eq!((decoded.locked_OM.raw(), decoded.minimum_price.raw()), (raw, U256::one()));
+    }
+
+    #[wasm_bindgen_test]
+    fn portion_is_measured_from_the_top_left_corner() {
+        let [x, y, width, height] = field(0.25, 0.0, 0.5, 0.5).portion_of(&field(0.0, 0.0, 1.0, 1.0));
+        assert!((x - 0.25).abs() < 1e-9 && (y - 0.5).abs() < 1e-9);
+        assert!((width - 0.25).abs() < 1e-9 && (height - 0.5).abs() < 1e-9);
+    }
+
+    fn snapshot(block: u64, children: Vec<Metadata>, bids: Vec<Metadata>) -> NodeSnapshot {
+        NodeSnapshot { block: block.into(), children, bids }
+    }
+
+    #[wasm_bindgen_test]
+    fn snapshots_with_the_same_tokens_and_amounts_have_the_same_state() {
+        let a = snapshot(10, vec![bid(3, 1.0)], vec![bid(4, 2.0)]);
+        assert!(a.same_state(&snapshot(11, vec![bid(3, 1.0)], vec![bid(4, 2.0)])));
+        assert!(!a.same_state(&snapshot(11, vec![bid(3, 1.0)], vec![bid(4, 2.5)])));
+        assert!(!a.same_state(&snapshot(11, vec![bid(3, 1.0)], vec![])));
+        assert!(!a.same_state(&snapshot(11, vec![bid(3, 1.0), bid(5, 1.0)], vec![bid(4, 2.0)])));
+        let raised = Metadata { minimum_price: OMAmount::from_ether(1.0), ..bid(3, 1.0) };
+        assert!(!a.same_state(&snapshot(11, vec![raised], vec![bid(4, 2.0)])));
+    }
+
+    #[wasm_bindgen_test]
+    fn history_export_lists_snapshots_in_order() {
+        let token = bid(2, 0.0);
+        let export = node_history_export(&token, &[
+            snapshot(10, vec![], vec![bid(4, 2.0)]),
+            snapshot(12, vec![bid(3, 1.0)], vec![]),
+        ]);
+        assert_eq!(export["schema"], NODE_HISTORY_SCHEMA);
+        assert_eq!(export["token"]["decoded"]["token_id"], "2");
+        let snapshots = export["snapshots"].as_array().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0]["block"], "10");
+        assert_eq!(snapshots[0]["children"].as_array().unwrap().len(), 0);
+        assert_eq!(snapshots[0]["bids"][0]["decoded"]["token_id"], "4");
+        assert_eq!(snapshots[1]["block"], "12");
+        assert_eq!(snapshots[1]["children"][0]["decoded"]["token_id"], "3");
+        assert_eq!(snapshots[1]["children"][0]["decoded"]["locked_OM"], 1.0);
+    }
+
+
+    fn encode(value: f64) -> Token {
+        TokenizableBigFloat(BigFloat::from(value)).into_token()
+    }
+
+    #[wasm_bindgen_test]
+    fn coordinates_are_padded_to_fixed_point() {
+        let one = U256::one() << (FRACTION_HEX_DIGITS * 4);
+        assert_eq!(encode(1.0), Token::Uint(one));
+        assert_eq!(encode(2.0), Token::Uint(one * 2));
+        assert_eq!(encode(0.5), Token::Uint(one / 2));
+        assert_eq!(encode(15.0), Token::Uint(one * 15));
+        assert_eq!(encode(0.0), Token::Uint(U256::zero()));
+    }
+
+    #[wasm_bindgen_test]
+    fn digits_below_the_fixed_point_are_dropped() {
+        assert_eq!(encode(16f64.powi(-(FRACTION_HEX_DIGITS as i32) - 1)), Token::Uint(U256::zero()));
+        assert_eq!(encode(16f64.powi(-(FRACTION_HEX_DIGITS as i32))), Token::Uint(U256::one()));
+    }
+
+    #[wasm_bindgen_test]
+    fn coordinates_without_a_whole_hex_digit_are_invalid() {
+        let invalid = Token::Uint(U256::from(3) * U256::from(10).pow(U256::from(76)));
+        assert_eq!(encode(16.0), invalid);
+        assert_eq!(encode(100.0), invalid);
+    }
+
+    #[wasm_bindgen_test]
+    fn coordinates_round_trip() {
+        for value in [0.5, 1.0, 1.25, 2.1, 3.6] {
+            let decoded = TokenizableBigFloat::from_token(encode(value)).unwrap();
+            assert!(to_f64(&decoded) - value < 1e-15 && value - to_f64(&decoded) < 1e-15);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use eyre::{eyre, Result};
+use serde_json::json;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web3::types::Address;
+
+
+/// EIP-1193 error codes returned by providers that don't implement a method.
+const UNSUPPORTED_METHOD_CODES: [f64; 2] = [4200.0, -32601.0];
+
+
+/// Outcome of asking the wallet to track a token.
+#[derive(Clone, Copy, PartialEq)]
+pub enum WatchAsset {
+    Added,
+    Declined,
+    Unsupported,
+}
+
+
+/// Params of the `wallet_watchAsset` request, as specified by EIP-747.
+pub fn watch_asset_params(address: Address, symbol: &str, decimals: u8) -> serde_json::Value {
+    json!({
+        "type": "ERC20",
+        "options": {
+            "address": format!("{address:?}"),
+            "symbol": symbol,
+            "decimals": decimals,
+        },
+    })
+}
+
+/// Ask the injected provider to add the ERC20 token to the user's wallet.
+pub async fn watch_asset(address: Address, symbol: &str, decimals: u8) -> Result<WatchAsset> {
+    let window = web_sys::window().ok_or_else(|| eyre!("No window"))?;
+    let ethereum = js_sys::Reflect::get(&window, &"ethereum".into()).map_err(|_| eyre!("No provider"))?;
+    if ethereum.is_undefined() {
+        return Ok(WatchAsset::Unsupported)
+    }
+    let request = js_sys::Reflect::get(&ethereum, &"request".into())
+        .ok()
+        .and_then(|request| request.dyn_into::<js_sys::Function>().ok());
+    let Some(request) = request else {
+        return Ok(WatchAsset::Unsupported)
+    };
+
+    let arguments = json!({
+        "method": "wallet_watchAsset",
+        "params": watch_asset_params(address, symbol, decimals),
+    });
+    let arguments = js_sys::JSON::parse(&arguments.to_string()).map_err(|_| eyre!("Invalid request"))?;
+    let promise = request.call1(&ethereum, &arguments).map_err(|error| eyre!("{error:?}"))?;
+    match JsFuture::from(js_sys::Promise::from(promise)).await {
+        Ok(added) => Ok(if added.as_bool().unwrap_or(false) { WatchAsset::Added } else { WatchAsset::Declined }),
+        Err(error) => {
+            let code = js_sys::Reflect::get(&error, &"code".into()).ok().and_then(|code| code.as_f64());
+            match code {
+                Some(code) if UNSUPPORTED_METHOD_CODES.contains(&code) => Ok(WatchAsset::Unsupported),
+                Some(_) => Ok(WatchAsset::Declined),
+                None => Err(eyre!("{error:?}")),
+            }
+        }
+    }
+}
+
+
+/// Ask the injected provider to switch to `chain_id`, given in hex as in `Chain`. Whether the wallet complied
+/// only shows through the chain id it reports afterwards.
+pub async fn switch_chain(chain_id: &str) -> Result<()> {
+    let window = web_sys::window().ok_or_else(|| eyre!("No window"))?;
+    let ethereum = js_sys::Reflect::get(&window, &"ethereum".into()).map_err(|_| eyre!("No provider"))?;
+    let request = js_sys::Reflect::get(&ethereum, &"request".into())
+        .ok()
+        .and_then(|request| request.dyn_into::<js_sys::Function>().ok())
+        .ok_or_else(|| eyre!("No provider"))?;
+
+    let arguments = json!({
+        "method": "wallet_switchEthereumChain",
+        "params": [{ "chainId": chain_id }],
+    });
+    let arguments = js_sys::JSON::parse(&arguments.to_string()).map_err(|_| eyre!("Invalid request"))?;
+    let promise = request.call1(&ethereum, &arguments).map_err(|error| eyre!("{error:?}"))?;
+    JsFuture::from(js_sys::Promise::from(promise)).await.map_err(|error| eyre!("{error:?}"))?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn watch_asset_params_describe_the_token() {
+        let address = Address::repeat_byte(0xab);
+        assert_eq!(watch_asset_params(address, "wOM", 18), json!({
+            "type": "ERC20",
+            "options": {
+                "address": "0xabababababababababababababababababababab",
+                "symbol": "wOM",
+                "decimals": 18,
+            },
+        }));
+    }
+
+    #[wasm_bindgen_test]
+    async fn provider_without_watch_asset_is_unsupported() {
+        let window = web_sys::window().unwrap();
+        let reject = Closure::<dyn Fn(JsValue) -> js_sys::Promise>::new(|_| {
+            js_sys::Promise::reject(&js_sys::JSON::parse(r#"{"code": 4200, "message": "Unsupported method"}"#).unwrap())
+        });
+        let ethereum = js_sys::Object::new();
+        js_sys::Reflect::set(&ethereum, &"request".into(), reject.as_ref()).unwrap();
+        js_sys::Reflect::set(&window, &"ethereum".into(), &ethereum).unwrap();
+
+        let result = watch_asset(Address::repeat_byte(0xab), "wOM", 18).await;
+        js_sys::Reflect::delete_property(&window, &"ethereum".into()).unwrap();
+
+        assert!(matches!(result, Ok(WatchAsset::Unsupported)));
+    }
+}
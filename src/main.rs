@@ -9,6 +9,10 @@ use leptos::*;
 use components::App;
 use util::parse_url_query_string;
 
+// timers and the mock wallet live on the window, so tests need a browser
+#[cfg(test)]
+wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
 
 fn main() {
     let query_string = web_sys::window().unwrap().location().search().unwrap();
@@ -16,5 +20,8 @@ fn main() {
         .and_then(|x| x.parse().ok())
         .unwrap_or(log::Level::Error);
     console_log::init_with_level(level).expect("could not initialize logger");
-    mount_to_body(|| view! { <App/> })
+    spawn_local(async {
+        evm::abi::load().await;
+        mount_to_body(|| view! { <App/> })
+    });
 }
@@ -1,21 +1,290 @@
 use std::{
     collections::HashMap,
+    future::Future,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use leptos::*;
+use mandelbrot_explorer::BigFloat;
+use web3::types::Address;
 
-use crate::evm::{
-    contracts::ERC1155Contract,
-    types::Metadata,
+use crate::{
+    evm::{
+        contracts::{ERC1155Contract, TxStage, ERC1155_INTERFACE_ID, ERC1155_METADATA_URI_INTERFACE_ID},
+        types::{Metadata, OMAmount, ROOT_TOKEN_ID},
+    },
+    util::{load_local, store_local},
 };
 
 
+/// Readiness of the app, derived from the wallet, its network and the connected account.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AppState {
+    NoWallet,
+    /// Wallet is available but no account is connected yet.
+    Connecting,
+    WrongNetwork,
+    /// Spectator mode, nothing can be written to the chain.
+    ReadOnly,
+    Ready { address: Address },
+}
+
+impl AppState {
+    pub fn new(spectator: bool, wallet: bool, correct_network: Option<bool>, address: Option<Address>) -> Self {
+        match (spectator, wallet, correct_network, address) {
+            (true, _, _, _) => Self::ReadOnly,
+            (_, false, _, _) => Self::NoWallet,
+            (_, _, _, None) => Self::Connecting,
+            (_, _, Some(false), _) => Self::WrongNetwork,
+            // network is assumed to be correct until proven otherwise
+            (_, _, _, Some(address)) => Self::Ready { address },
+        }
+    }
+
+    pub fn address(&self) -> Option<Address> {
+        match self {
+            Self::Ready { address } => Some(*address),
+            _ => None,
+        }
+    }
+}
+
+
+/// Last position of the mouse over the canvas, as a fraction of the canvas size, which is also kept in css pixels.
+#[derive(Clone, Copy)]
+pub struct Pointer {
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+}
+
+
+/// Ancestors kept in the navigation history, the rest is fetched again when the user expands the breadcrumb.
+const MAX_NAV_HISTORY: usize = 8;
+
+const NODE_CACHE_CAPACITY: usize = 32;
+const DEFAULT_NODE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// How long fetched children and bids stay fresh, configurable in seconds through the `NODE_CACHE_TTL` env variable.
+fn node_cache_ttl() -> Duration {
+    option_env!("NODE_CACHE_TTL")
+        .and_then(|ttl| ttl.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_NODE_CACHE_TTL)
+}
+
+
+/// Children and bids of recently explored tokens, so that navigating back and forth doesn't fetch them again.
+/// Least recently fetched entries are evicted first.
+#[derive(Clone, Default)]
+pub struct NodeCache(Arc<Mutex<HashMap<u128, (Vec<Metadata>, Vec<Metadata>, f64)>>>);
+
+impl NodeCache {
+    /// Children and bids of `token_id`, unless they were never fetched or are older than the TTL.
+    pub fn get(&self, token_id: u128) -> Option<(Vec<Metadata>, Vec<Metadata>)> {
+        let now = js_sys::Date::now();
+        self.0.lock().unwrap().get(&token_id)
+            .filter(|(_, _, fetched_at)| now - fetched_at < node_cache_ttl().as_millis() as f64)
+            .map(|(children, bids, _)| (children.clone(), bids.clone()))
+    }
+
+    pub fn insert(&self, token_id: u128, children: Vec<Metadata>, bids: Vec<Metadata>) {
+        let mut cache = self.0.lock().unwrap();
+        if cache.len() >= NODE_CACHE_CAPACITY && !cache.contains_key(&token_id) {
+            let oldest = cache.iter()
+                .min_by(|(_, (_, _, a)), (_, (_, _, b))| a.total_cmp(b))
+                .map(|(token_id, _)| *token_id);
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(token_id, (children, bids, js_sys::Date::now()));
+    }
+
+    /// Has to be called whenever a transaction changes the children or bids of `token_id`.
+    pub fn invalidate(&self, token_id: u128) {
+        self.0.lock().unwrap().remove(&token_id);
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+
+/// Ancestry of `token_id`, leaf first, or the token alone when the ancestry can't be read,
+/// so that failing to resolve the ancestors doesn't send the user back to the root.
+async fn ancestry_or_token(erc1155_contract: &ERC1155Contract, token_id: u128) -> eyre::Result<Vec<Metadata>> {
+    match erc1155_contract.get_ancestry_metadata(token_id).await {
+        Ok(ancestry) => Ok(ancestry),
+        Err(error) => {
+            log::warn!("Could not read the ancestry of {}, showing it on its own: {}", token_id, error);
+            Ok(vec![erc1155_contract.get_metadata(token_id).await?])
+        }
+    }
+}
+
+
 #[derive(Clone)]
 pub struct ExplorerState {
+    /// Ancestry of the explored token, root first. Only the closest ancestors are kept for very deep tokens.
     pub nav_history: RwSignal<Vec<Metadata>>,
+    /// Number of ancestors left out of `nav_history`.
+    pub elided_ancestors: RwSignal<usize>,
     pub children: RwSignal<HashMap<u128, Metadata>>,
     pub bids: RwSignal<HashMap<u128, Metadata>>,
+    /// Distance in pixels around a frame that still counts as clicking on it.
+    pub frame_tolerance: RwSignal<f64>,
+    /// Keep track of the deepest loaded token that contains the center of the view.
+    pub track_position: RwSignal<bool>,
+    pub position: RwSignal<Option<u128>>,
+    /// Draw only the children owned by the user, ancestors and bids are always drawn.
+    pub owned_only: RwSignal<bool>,
+    pub cache: NodeCache,
+}
+
+impl Default for ExplorerState {
+    fn default() -> Self {
+        Self {
+            nav_history: create_rw_signal(Vec::new()),
+            elided_ancestors: create_rw_signal(0),
+            children: create_rw_signal(HashMap::new()),
+            bids: create_rw_signal(HashMap::new()),
+            frame_tolerance: create_rw_signal(4.0),
+            track_position: create_rw_signal(false),
+            position: create_rw_signal(None),
+            owned_only: create_rw_signal(false),
+            cache: NodeCache::default(),
+        }
+    }
+}
+
+impl ExplorerState {
+    /// Keep the explored token and its closest ancestors out of `ancestry`, which starts with the explored token.
+    pub fn set_ancestry(&self, ancestry: Vec<Metadata>) {
+        let elided_ancestors = ancestry.len().saturating_sub(MAX_NAV_HISTORY);
+        batch(|| {
+            self.nav_history.update(|nav_history| {
+                nav_history.clear();
+                nav_history.extend(ancestry.into_iter().take(MAX_NAV_HISTORY).rev());
+            });
+            self.elided_ancestors.set(elided_ancestors);
+        });
+    }
+
+    /// Query the ancestry, children and bids of the token, the latter two from the cache when they are in it,
+    /// and show them once all of them are in. Without an ancestry the token is shown on its own, see `ancestry_or_token`,
+    /// otherwise nothing changes when any of them fails.
+    pub async fn load(&self, erc1155_contract: &ERC1155Contract, progress: Progress, token_id: u128) -> eyre::Result<()> {
+        let (ancestry, children, bids) = if let Some((children, bids)) = self.cache.get(token_id) {
+            (progress.track(ancestry_or_token(erc1155_contract, token_id)).await?, children, bids)
+        } else {
+            let (ancestry, children, bids) = match progress.track(erc1155_contract.get_node(token_id)).await {
+                Ok(node) => node,
+                Err(error) => {
+                    log::warn!("Could not read {} at once, reading its parts separately: {}", token_id, error);
+                    let (ancestry, children, bids) = futures::join!(
+                        progress.track(ancestry_or_token(erc1155_contract, token_id)),
+                        progress.track(erc1155_contract.get_children_metadata(token_id)),
+                        progress.track(erc1155_contract.get_bids(token_id)),
+                    );
+                    (ancestry?, children?, bids?)
+                }
+            };
+            self.cache.insert(token_id, children.clone(), bids.clone());
+            (ancestry, children, bids)
+        };
+        batch(|| {
+            self.set_ancestry(ancestry);
+            self.children.update(|children_| {
+                children_.clear();
+                children_.extend(children.into_iter().map(|m| (m.token_id, m)));
+            });
+            self.bids.update(|bids_| {
+                bids_.clear();
+                bids_.extend(bids.into_iter().map(|bid| (bid.token_id, bid)));
+            });
+        });
+        Ok(())
+    }
+
+    /// Parent of the explored token, where going up leads.
+    pub fn parent(&self) -> Option<Metadata> {
+        self.nav_history.with_untracked(|nav_history| {
+            nav_history.len().checked_sub(2).map(|index| nav_history[index].clone())
+        })
+    }
+
+    /// Root token, only loaded when the explored token isn't too deep for it to be kept in `nav_history`.
+    pub fn root(&self) -> Option<Metadata> {
+        self.nav_history.with_untracked(|nav_history| {
+            nav_history.first().filter(|token| token.token_id == ROOT_TOKEN_ID).cloned()
+        })
+    }
+
+    /// Fetch the ancestors left out of `nav_history` again.
+    pub async fn expand_ancestry(&self, erc1155_contract: &ERC1155Contract) {
+        let token_id = self.nav_history.with_untracked(|nav_history| nav_history.last().map(|token| token.token_id));
+        if let Some(token_id) = token_id {
+            if let Ok(ancestry) = erc1155_contract.get_ancestry_metadata(token_id).await {
+                batch(|| {
+                    self.nav_history.set(ancestry.into_iter().rev().collect());
+                    self.elided_ancestors.set(0);
+                });
+            }
+        }
+    }
+
+    /// Re-query bids of the token without refetching its children. Does nothing unless the token is currently explored.
+    pub async fn reload_bids(&self, erc1155_contract: &ERC1155Contract, parent_id: u128) {
+        // bids being placed, deleted or approved is what changes the children and bids of a token
+        self.cache.invalidate(parent_id);
+        if self.nav_history.with_untracked(|nav_history| nav_history.last().map(|token| token.token_id)) != Some(parent_id) {
+            return
+        }
+        if let Ok(bids) = erc1155_contract.get_bids(parent_id).await {
+            self.bids.update(|bids_| {
+                let selected: Vec<u128> = bids_.values().filter(|bid| bid.selected).map(|bid| bid.token_id).collect();
+                bids_.clear();
+                bids_.extend(bids.into_iter().map(|mut bid| {
+                    bid.selected = selected.contains(&bid.token_id);
+                    (bid.token_id, bid)
+                }));
+            });
+        }
+    }
+
+    /// Deepest loaded token, among the children and ancestors of the explored one, whose field contains the point.
+    pub fn node_at_point(&self, x: impl Into<BigFloat>, y: impl Into<BigFloat>) -> Option<u128> {
+        let (x, y) = (x.into(), y.into());
+        self.children.with_untracked(|children| {
+            self.nav_history.with_untracked(|nav_history| {
+                children.values().chain(nav_history.iter())
+                    .filter(|token| token.field.contains(&x, &y))
+                    .max_by_key(|token| token.layer)
+                    .map(|token| token.token_id)
+            })
+        })
+    }
+}
+
+
+/// Take the bid out of each of `bids` for as long as `delete` runs, so it disappears right away,
+/// and put it back wherever it was if the deletion fails.
+pub async fn remove_bid_while<T>(bid_id: u128, bids: &[RwSignal<HashMap<u128, Metadata>>], delete: impl Future<Output = Option<T>>) -> Option<T> {
+    let removed: Vec<_> = bids.iter().map(|bids| bids.try_update(|bids| bids.remove(&bid_id)).flatten()).collect();
+    let result = delete.await;
+    if result.is_none() {
+        for (bids, bid) in bids.iter().zip(removed) {
+            if let Some(bid) = bid {
+                bids.update(|bids| {
+                    bids.insert(bid_id, bid);
+                });
+            }
+        }
+    }
+    result
 }
 
 
@@ -32,17 +301,306 @@ pub struct SalesState {
 }
 
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum LedgerAction {
+    Bid,
+    BidDeletion,
+    Approval,
+    Transfer,
+}
+
+impl LedgerAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Bid => "Bid",
+            Self::BidDeletion => "Bid deletion",
+            Self::Approval => "Approval",
+            Self::Transfer => "Transfer",
+        }
+    }
+}
+
+
+/// OM flow caused by a confirmed action, positive when OM was earned and negative when it was spent.
+#[derive(Clone)]
+pub struct LedgerEntry {
+    pub action: LedgerAction,
+    pub token_id: Option<u128>,
+    pub amount: f64,
+}
+
+
+/// OM spent and earned during this session, kept in memory only.
+#[derive(Clone, Copy)]
+pub struct Ledger(pub RwSignal<Vec<LedgerEntry>>);
+
+impl Ledger {
+    pub fn record(&self, action: LedgerAction, token_id: Option<u128>, amount: f64) {
+        self.0.update(|ledger| ledger.push(LedgerEntry { action, token_id, amount }));
+    }
+
+    /// Records the action once its transaction is mined, rejected or reverted transactions leave the ledger untouched.
+    pub fn record_when_confirmed(&self, stage: &TxStage, action: LedgerAction, token_id: Option<u128>, amount: f64) {
+        if *stage == TxStage::Confirmed {
+            self.record(action, token_id, amount);
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.0.with(|ledger| ledger.iter().map(|entry| entry.amount).sum())
+    }
+}
+
+
+/// Fetches currently in flight, counted from the first one started until all of them settle.
+#[derive(Clone, Copy)]
+pub struct Progress {
+    settled: RwSignal<usize>,
+    total: RwSignal<usize>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self {
+            settled: create_rw_signal(0),
+            total: create_rw_signal(0),
+        }
+    }
+
+    /// Await `future` while counting it in, failed and aborted fetches settle just like the successful ones.
+    pub async fn track<T>(&self, future: impl Future<Output = T>) -> T {
+        self.total.update(|total| *total += 1);
+        let _settle = Settle(*self);
+        future.await
+    }
+
+    fn settle(&self) {
+        batch(|| {
+            self.settled.update(|settled| *settled += 1);
+            if self.settled.get_untracked() == self.total.get_untracked() {
+                self.settled.set(0);
+                self.total.set(0);
+            }
+        });
+    }
+
+    /// Completed fraction, `None` when nothing is being fetched.
+    pub fn fraction(&self) -> Option<f64> {
+        let total = self.total.get();
+        (total > 0).then(|| self.settled.get() as f64 / total as f64)
+    }
+}
+
+
+/// Settles a tracked fetch once it is done or dropped.
+struct Settle(Progress);
+
+impl Drop for Settle {
+    fn drop(&mut self) {
+        self.0.settle();
+    }
+}
+
+
+/// Optional functionality of the deployed contract, detected through ERC165 at startup.
+/// Everything is assumed to be supported until detection says otherwise.
+#[derive(Clone, Copy, PartialEq)]
+pub struct ContractFeatures {
+    /// Required for OM to be wrapped into ERC20.
+    pub erc1155: bool,
+    pub metadata_uri: bool,
+}
+
+impl Default for ContractFeatures {
+    fn default() -> Self {
+        Self {
+            erc1155: true,
+            metadata_uri: true,
+        }
+    }
+}
+
+impl ContractFeatures {
+    /// Features updated with what the contract reports, detection failing says nothing about the contract so the
+    /// current value is kept in that case.
+    pub async fn detected(mut self, erc1155_contract: &ERC1155Contract) -> Self {
+        if let Ok(erc1155) = erc1155_contract.supports_interface(ERC1155_INTERFACE_ID).await {
+            self.erc1155 = erc1155;
+        }
+        if let Ok(metadata_uri) = erc1155_contract.supports_interface(ERC1155_METADATA_URI_INTERFACE_ID).await {
+            self.metadata_uri = metadata_uri;
+        }
+        self
+    }
+}
+
+
+const NOTES_KEY: &str = "notes";
+
+
+/// User's notes on tokens, persisted in local storage.
+#[derive(Clone, Copy)]
+pub struct Notes(pub RwSignal<HashMap<u128, String>>);
+
+impl Notes {
+    pub fn load() -> Self {
+        let notes = load_local(NOTES_KEY)
+            .and_then(|notes| Self::parse(&notes).ok())
+            .unwrap_or_default();
+        Self(create_rw_signal(notes))
+    }
+
+    pub fn get(&self, token_id: u128) -> Option<String> {
+        self.0.with(|notes| notes.get(&token_id).cloned())
+    }
+
+    /// Empty note removes it.
+    pub fn set(&self, token_id: u128, note: String) {
+        self.0.update(|notes| {
+            if note.is_empty() {
+                notes.remove(&token_id);
+            } else {
+                notes.insert(token_id, note);
+            }
+        });
+        self.save();
+    }
+
+    pub fn export(&self) -> String {
+        self.0.with(|notes| {
+            serde_json::json!({
+                "notes": notes.iter()
+                    .map(|(token_id, note)| (token_id.to_string(), serde_json::Value::String(note.clone())))
+                    .collect::<serde_json::Map<_, _>>(),
+            }).to_string()
+        })
+    }
+
+    /// Merge notes exported by `export`, imported notes replace existing ones for the same token.
+    pub fn import(&self, json: &str) -> eyre::Result<usize> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let imported = Self::parse(&value["notes"].to_string())?;
+        let count = imported.len();
+        self.0.update(|notes| notes.extend(imported));
+        self.save();
+        Ok(count)
+    }
+
+    fn parse(json: &str) -> eyre::Result<HashMap<u128, String>> {
+        let notes: HashMap<String, String> = serde_json::from_str(json)?;
+        Ok(notes.into_iter()
+            .filter_map(|(token_id, note)| Some((token_id.parse().ok()?, note)))
+            .collect())
+    }
+
+    fn save(&self) {
+        self.0.with_untracked(|notes| {
+            let notes: HashMap<String, &String> = notes.iter().map(|(token_id, note)| (token_id.to_string(), note)).collect();
+            if let Ok(notes) = serde_json::to_string(&notes) {
+                store_local(NOTES_KEY, &notes);
+            }
+        });
+    }
+}
+
+
+/// Runs the last of several calls made in quick succession, once `delay` has passed since it.
+#[derive(Clone, Copy)]
+pub struct Debounce {
+    pending: StoredValue<Option<TimeoutHandle>>,
+    delay: Duration,
+}
+
+impl Debounce {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            pending: store_value(None),
+            delay,
+        }
+    }
+
+    pub fn run(&self, f: impl FnOnce() + 'static) {
+        if let Some(handle) = self.pending.get_value() {
+            handle.clear();
+        }
+        if let Ok(handle) = set_timeout_with_handle(f, self.delay) {
+            self.pending.set_value(Some(handle));
+        }
+    }
+}
+
+
+pub const BALANCE_REFRESH_DELAY: Duration = Duration::from_secs(2);
+const BALANCE_CHANGE_DURATION: Duration = Duration::from_secs(10);
+const BALANCE_CHANGE_TOLERANCE: f64 = 0.000001;
+
+
+/// OM balance before and after an action, together with the change that the action was expected to cause.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BalanceChange {
+    pub before: f64,
+    pub after: f64,
+    pub expected: f64,
+}
+
+impl BalanceChange {
+    /// Compares `before`, read right before the action was submitted, with the balance of `address` once it is confirmed.
+    pub async fn since(erc1155_contract: &ERC1155Contract, address: Address, before: Option<OMAmount>, expected: f64) -> Option<Self> {
+        let before = before?;
+        let after = erc1155_contract.get_OM_balance(address).await.ok()?;
+        Some(Self { before: before.to_ether(), after: after.to_ether(), expected })
+    }
+
+    pub fn delta(&self) -> f64 {
+        self.after - self.before
+    }
+
+    pub fn unexpected(&self) -> bool {
+        (self.delta() - self.expected).abs() > BALANCE_CHANGE_TOLERANCE
+    }
+}
+
+
 #[derive(Clone)]
 pub struct State {
     pub mandelbrot: Arc<Mutex<mandelbrot_explorer::Interface>>,
+    pub pointer: StoredValue<Option<Pointer>>,
     pub address: Signal<Option<web3::types::Address>>,
+    /// OM balance of the connected address, kept up to date by the account drawer and after transactions moving OM.
+    pub OM_balance: RwSignal<f64>,
+    /// Pending read of the OM balance, see `refresh_OM_balance`.
+    pub balance_refresh: Debounce,
+    /// Last change of the OM balance caused by an action, shown for a while after the action is confirmed.
+    pub OM_change: RwSignal<Option<BalanceChange>>,
+    pub app_state: Memo<AppState>,
+    pub spectator: bool,
     pub erc1155_contract: ERC1155Contract,
     pub explorer: ExplorerState,
     pub inventory: InventoryState,
     pub sales: SalesState,
+    pub ledger: Ledger,
+    pub notes: Notes,
+    pub features: RwSignal<ContractFeatures>,
+    pub progress: Progress,
 }
 
 impl State {
+    pub async fn detect_features(&self) {
+        let features = self.features.get_untracked().detected(&self.erc1155_contract).await;
+        self.features.set(features);
+    }
+
+    /// Address to submit a transaction from, checked with the wallet at submission time since the connected
+    /// address may be stale. `None` disables the write, just like when no wallet is connected.
+    pub async fn sender(&self) -> Option<Address> {
+        self.address.get_untracked()?;
+        let account = self.erc1155_contract.get_account().await;
+        if account.is_some() && account != self.address.get_untracked() {
+            log::warn!("Wallet account {:?} differs from the connected address, submitting from the former", account);
+        }
+        account
+    }
+
     pub async fn reload_inventory(&self) {
         if let Some(address) = self.address.get_untracked() {
             if let Ok((tokens, bids)) = self.erc1155_contract.get_owned_items(address).await {
@@ -59,10 +617,44 @@ impl State {
         }
     }
 
+    /// See `ExplorerState::reload_bids`.
+    pub async fn reload_bids(&self, parent_id: u128) {
+        self.explorer.reload_bids(&self.erc1155_contract, parent_id).await;
+    }
+
+    /// Re-read the OM balance shortly after a transaction that moved OM, transactions in quick succession only cause a single read.
+    pub fn refresh_OM_balance(&self) {
+        let state = self.clone();
+        self.balance_refresh.run(move || spawn_local(async move { state.reload_OM_balance().await }));
+    }
+
+    /// Shows how the OM balance of `address` changed since `before`, see `BalanceChange::since`.
+    pub async fn show_OM_change(&self, address: Address, before: Option<OMAmount>, expected: f64) {
+        let Some(change) = BalanceChange::since(&self.erc1155_contract, address, before, expected).await else {
+            return
+        };
+        let OM_change = self.OM_change;
+        OM_change.set(Some(change));
+        set_timeout(move || OM_change.update(|OM_change| {
+            if *OM_change == Some(change) {
+                *OM_change = None;
+            }
+        }), BALANCE_CHANGE_DURATION);
+    }
+
+    /// Re-query the OM balance of the connected address.
+    pub async fn reload_OM_balance(&self) {
+        if let Some(address) = self.address.get_untracked() {
+            if let Ok(balance) = self.erc1155_contract.get_OM_balance(address).await {
+                self.OM_balance.set(balance.to_ether());
+            }
+        }
+    }
+
     pub async fn reload_sales(&self) {
         let bids = futures::future::join_all(self.inventory.tokens.get_untracked().keys().map(|token_id| {
             async move {
-                (*token_id, self.erc1155_contract.get_bids(*token_id).await)
+                (*token_id, self.progress.track(self.erc1155_contract.get_bids(*token_id)).await)
             }
         })).await.into_iter().map(|(token_id, result)| (
             token_id,
@@ -75,3 +667,541 @@ impl State {
         self.sales.bids.set(bids);
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, sync::atomic::{AtomicBool, Ordering}, time::Duration};
+
+    use futures::channel::oneshot;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_test::*;
+    use web3::{contract::tokens::Tokenizable, transports::{Either, Http}, types::Address};
+
+    use super::*;
+    use crate::{evm::{mock::{output, selector, MockProvider}, types::Field}, util::sleep};
+
+    fn token(token_id: u128, parent_id: u128) -> Metadata {
+        Metadata {
+            token_id,
+            owner: Address::zero(),
+            parent_id,
+            field: Field {
+                x_min: BigFloat::from(0.0),
+                y_min: BigFloat::from(0.0),
+                x_max: BigFloat::from(1.0),
+                y_max: BigFloat::from(1.0),
+            },
+            locked_OM: OMAmount::default(),
+            minimum_price: OMAmount::default(),
+            layer: 1,
+            owned: false,
+            selected: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn reloading_bids_does_not_refetch_children() {
+        let runtime = create_runtime();
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Array(vec![token(3, 1).into_token()])])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let explorer = ExplorerState::default();
+        explorer.nav_history.set(vec![token(1, 0)]);
+        explorer.children.set(HashMap::from([(2, token(2, 1))]));
+
+        explorer.reload_bids(&erc1155_contract, 1).await;
+        let calls: Vec<String> = provider.calldata("eth_call").iter().map(|calldata| calldata[..10].to_string()).collect();
+        assert_eq!(calls, vec![selector("getBids")]);
+        assert_eq!(explorer.bids.get_untracked().keys().collect::<Vec<_>>(), vec![&3]);
+        assert_eq!(explorer.children.get_untracked().keys().collect::<Vec<_>>(), vec![&2]);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn bids_of_tokens_not_explored_are_not_reloaded() {
+        let runtime = create_runtime();
+        let provider = MockProvider::new(|_, _| Ok(output(&[ethabi::Token::Array(vec![])])));
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let explorer = ExplorerState::default();
+        explorer.nav_history.set(vec![token(1, 0)]);
+
+        explorer.reload_bids(&erc1155_contract, 2).await;
+        assert!(provider.methods().is_empty());
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn ledger_total_is_the_net_of_recorded_actions() {
+        let runtime = create_runtime();
+        let ledger = Ledger(create_rw_signal(Vec::new()));
+        ledger.record(LedgerAction::Bid, Some(3), -2.5);
+        ledger.record(LedgerAction::Approval, Some(4), 4.0);
+        ledger.record(LedgerAction::Transfer, None, -1.0);
+        assert_eq!(ledger.0.with_untracked(|entries| entries.len()), 3);
+        assert_eq!(ledger.total(), 0.5);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn balance_change_matching_the_action_is_expected() {
+        let unwrap = BalanceChange { before: 1.5, after: 2.0, expected: 0.5 };
+        assert_eq!(unwrap.delta(), 0.5);
+        assert!(!unwrap.unexpected());
+        let wrap = BalanceChange { before: 2.0, after: 1.25, expected: -0.75 };
+        assert_eq!(wrap.delta(), -0.75);
+        assert!(!wrap.unexpected());
+    }
+
+    #[wasm_bindgen_test]
+    fn rounding_noise_is_tolerated() {
+        assert!(!BalanceChange { before: 0.1, after: 0.3, expected: 0.2 }.unexpected());
+    }
+
+    #[wasm_bindgen_test]
+    fn balance_change_differing_from_the_action_is_unexpected() {
+        assert!(BalanceChange { before: 1.0, after: 1.0, expected: 0.5 }.unexpected());
+        assert!(BalanceChange { before: 1.0, after: 1.6, expected: 0.5 }.unexpected());
+    }
+
+    #[wasm_bindgen_test]
+    async fn balance_change_compares_with_the_balance_once_confirmed() {
+        // a bid of 2 OM that also paid a minting fee of 0.25 OM the user wasn't told about
+        let provider = MockProvider::new(|method, _| match method {
+            "eth_call" => Ok(output(&[ethabi::Token::Uint(OMAmount::from_ether(7.75).raw())])),
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+
+        let change = BalanceChange::since(&erc1155_contract, Address::repeat_byte(1), Some(OMAmount::from_ether(10.0)), -2.0).await.unwrap();
+        assert_eq!(change, BalanceChange { before: 10.0, after: 7.75, expected: -2.0 });
+        assert!(change.unexpected());
+        // without the balance before the action there is nothing to compare with
+        assert!(BalanceChange::since(&erc1155_contract, Address::repeat_byte(1), None, -2.0).await.is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn only_confirmed_transactions_are_recorded() {
+        let runtime = create_runtime();
+        let ledger = Ledger(create_rw_signal(Vec::new()));
+        ledger.record_when_confirmed(&TxStage::Submitted(Default::default()), LedgerAction::Bid, Some(3), -2.5);
+        ledger.record_when_confirmed(&TxStage::Failed, LedgerAction::Bid, Some(3), -2.5);
+        assert_eq!(ledger.total(), 0.0);
+        ledger.record_when_confirmed(&TxStage::Confirmed, LedgerAction::Bid, Some(3), -2.5);
+        assert_eq!(ledger.total(), -2.5);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn note_is_persisted_by_token_id() {
+        let runtime = create_runtime();
+        Notes::load().set(7, "nice spiral here".to_string());
+        let notes = Notes::load();
+        assert_eq!(notes.get(7).as_deref(), Some("nice spiral here"));
+        assert!(notes.get(8).is_none());
+        notes.set(7, String::new());
+        assert!(Notes::load().get(7).is_none());
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn exported_notes_import_back() {
+        let runtime = create_runtime();
+        let notes = Notes(create_rw_signal(HashMap::from([(2, "first".to_string()), (3, "second".to_string())])));
+        let imported = Notes(create_rw_signal(HashMap::new()));
+        assert_eq!(imported.import(&notes.export()).unwrap(), 2);
+        assert_eq!(imported.get(2).as_deref(), Some("first"));
+        assert_eq!(imported.get(3).as_deref(), Some("second"));
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn imported_notes_replace_existing_ones_of_the_same_token() {
+        let runtime = create_runtime();
+        let notes = Notes(create_rw_signal(HashMap::from([(2, "old".to_string()), (3, "kept".to_string())])));
+        assert_eq!(notes.import(r#"{"notes": {"2": "new", "not an id": "ignored"}}"#).unwrap(), 1);
+        assert_eq!(notes.get(2).as_deref(), Some("new"));
+        assert_eq!(notes.get(3).as_deref(), Some("kept"));
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn invalid_notes_are_not_imported() {
+        let runtime = create_runtime();
+        let notes = Notes(create_rw_signal(HashMap::from([(2, "kept".to_string())])));
+        assert!(notes.import("not json").is_err());
+        assert!(notes.import(r#"{"notes": ["2"]}"#).is_err());
+        assert_eq!(notes.get(2).as_deref(), Some("kept"));
+        runtime.dispose();
+    }
+
+    /// Contract answering `supportsInterface` with whether the interface is in `supported`.
+    fn erc165_provider(supported: &'static [[u8; 4]]) -> MockProvider {
+        MockProvider::new(move |_, params| {
+            let calldata = params[0]["data"].as_str().unwrap().to_string();
+            let supported = supported.iter().any(|interface_id| {
+                calldata[10..18] == interface_id.iter().map(|byte| format!("{byte:02x}")).collect::<String>()
+            });
+            Ok(output(&[ethabi::Token::Bool(supported)]))
+        })
+    }
+
+    #[wasm_bindgen_test]
+    async fn features_follow_the_detected_interfaces() {
+        let provider = erc165_provider(&[ERC1155_INTERFACE_ID]);
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let features = ContractFeatures::default().detected(&erc1155_contract).await;
+        assert!(features == ContractFeatures { erc1155: true, metadata_uri: false });
+    }
+
+    #[wasm_bindgen_test]
+    async fn features_are_kept_when_detection_fails() {
+        let provider = MockProvider::new(|_, _| Err(serde_json::json!({ "code": -32000, "message": "execution reverted" })));
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let features = ContractFeatures::default().detected(&erc1155_contract).await;
+        assert!(features == ContractFeatures::default());
+    }
+
+    /// Ancestry of token `depth` as returned by the contract, the token first and the root last.
+    fn ancestry(depth: u128) -> Vec<Metadata> {
+        (1..=depth).rev().map(|token_id| token(token_id, token_id - 1)).collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn deep_ancestry_is_truncated_to_the_closest_ancestors() {
+        let runtime = create_runtime();
+        let explorer = ExplorerState::default();
+        explorer.set_ancestry(ancestry(12));
+        let token_ids: Vec<u128> = explorer.nav_history.get_untracked().iter().map(|token| token.token_id).collect();
+        assert_eq!(token_ids, (5..=12).collect::<Vec<_>>());
+        assert_eq!(explorer.elided_ancestors.get_untracked(), 4);
+
+        explorer.set_ancestry(ancestry(3));
+        assert_eq!(explorer.nav_history.get_untracked().len(), 3);
+        assert_eq!(explorer.elided_ancestors.get_untracked(), 0);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn expanding_restores_the_full_ancestry_of_the_explored_token() {
+        let runtime = create_runtime();
+        let provider = MockProvider::new(|_, _| Ok(output(&[ethabi::Token::Array(
+            ancestry(12).into_iter().map(|token| token.into_token()).collect()
+        )])));
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let explorer = ExplorerState::default();
+        explorer.set_ancestry(ancestry(12));
+
+        explorer.expand_ancestry(&erc1155_contract).await;
+        let token_ids: Vec<u128> = explorer.nav_history.get_untracked().iter().map(|token| token.token_id).collect();
+        assert_eq!(token_ids, (1..=12).collect::<Vec<_>>());
+        assert_eq!(explorer.elided_ancestors.get_untracked(), 0);
+        let calldata = provider.calldata("eth_call");
+        assert_eq!(calldata.len(), 1);
+        assert!(calldata[0].starts_with(&selector("getAncestryMetadata")));
+        // the explored token is addressed, not the closest ancestor kept
+        assert!(calldata[0].ends_with(&format!("{:064x}", 12)));
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn app_state_follows_the_wallet() {
+        let address = Address::repeat_byte(1);
+        assert!(AppState::new(true, true, Some(true), Some(address)) == AppState::ReadOnly);
+        assert!(AppState::new(false, false, None, None) == AppState::NoWallet);
+        assert!(AppState::new(false, true, None, None) == AppState::Connecting);
+        assert!(AppState::new(false, true, Some(false), None) == AppState::Connecting);
+        assert!(AppState::new(false, true, Some(false), Some(address)) == AppState::WrongNetwork);
+        assert!(AppState::new(false, true, None, Some(address)) == AppState::Ready { address });
+        assert!(AppState::new(false, true, Some(true), Some(address)) == AppState::Ready { address });
+    }
+
+    #[wasm_bindgen_test]
+    fn only_a_ready_app_has_an_address() {
+        let address = Address::repeat_byte(1);
+        assert_eq!(AppState::Ready { address }.address(), Some(address));
+        assert_eq!(AppState::WrongNetwork.address(), None);
+        assert_eq!(AppState::ReadOnly.address(), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn progress_completes_once_every_fetch_settles() {
+        let runtime = create_runtime();
+        let progress = Progress::new();
+        let (succeed, succeeding) = oneshot::channel::<()>();
+        let (fail, failing) = oneshot::channel::<()>();
+        spawn_local(async move { let _ = progress.track(succeeding).await; });
+        spawn_local(async move { let _ = progress.track(failing).await; });
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(progress.fraction(), Some(0.0));
+
+        // the sender going away fails the fetch
+        drop(fail);
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(progress.fraction(), Some(0.5));
+
+        succeed.send(()).unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(progress.fraction(), None);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn aborted_fetch_settles_without_completing() {
+        let runtime = create_runtime();
+        let progress = Progress::new();
+        let (_pending, never) = oneshot::channel::<()>();
+        let completed = Arc::new(AtomicBool::new(false));
+        let (fetch, abort_handle) = futures::future::abortable({
+            let completed = completed.clone();
+            async move {
+                let _ = progress.track(never).await;
+                completed.store(true, Ordering::SeqCst);
+            }
+        });
+        spawn_local(async move { let _ = fetch.await; });
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(progress.fraction(), Some(0.0));
+
+        abort_handle.abort();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(progress.fraction(), None);
+        assert!(!completed.load(Ordering::SeqCst));
+        runtime.dispose();
+    }
+
+    fn nested(token_id: u128, parent_id: u128, layer: u128, [x_min, y_min, x_max, y_max]: [f64; 4]) -> Metadata {
+        Metadata {
+            layer,
+            field: Field { x_min: x_min.into(), y_min: y_min.into(), x_max: x_max.into(), y_max: y_max.into() },
+            ..token(token_id, parent_id)
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn point_resolves_to_the_innermost_token() {
+        let runtime = create_runtime();
+        let explorer = ExplorerState::default();
+        explorer.nav_history.set(vec![nested(1, 0, 0, [0.0, 0.0, 1.0, 1.0]), nested(2, 1, 1, [0.0, 0.0, 0.5, 0.5])]);
+        explorer.children.set(HashMap::from([
+            (3, nested(3, 2, 2, [0.1, 0.1, 0.3, 0.3])),
+            (4, nested(4, 2, 2, [0.35, 0.35, 0.45, 0.45])),
+        ]));
+
+        assert_eq!(explorer.node_at_point(0.2, 0.2), Some(3));
+        assert_eq!(explorer.node_at_point(0.4, 0.4), Some(4));
+        assert_eq!(explorer.node_at_point(0.05, 0.45), Some(2));
+        assert_eq!(explorer.node_at_point(0.8, 0.8), Some(1));
+        assert_eq!(explorer.node_at_point(1.5, 0.5), None);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn cached_node_is_returned_until_invalidated() {
+        let cache = NodeCache::default();
+        assert!(cache.get(2).is_none());
+        cache.insert(2, vec![token(3, 2)], vec![token(4, 2)]);
+        let (children, bids) = cache.get(2).unwrap();
+        assert_eq!(children[0].token_id, 3);
+        assert_eq!(bids[0].token_id, 4);
+        cache.invalidate(2);
+        assert!(cache.get(2).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn cached_node_expires_after_the_ttl() {
+        let cache = NodeCache::default();
+        let expired = js_sys::Date::now() - node_cache_ttl().as_millis() as f64 - 1.0;
+        cache.0.lock().unwrap().insert(2, (vec![token(3, 2)], vec![], expired));
+        assert!(cache.get(2).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn least_recently_fetched_node_is_evicted_at_capacity() {
+        let cache = NodeCache::default();
+        let now = js_sys::Date::now();
+        for token_id in 0..NODE_CACHE_CAPACITY as u128 {
+            // the first token was fetched the longest ago
+            cache.0.lock().unwrap().insert(token_id, (vec![], vec![], now - 1000.0 + token_id as f64));
+        }
+        cache.insert(1, vec![token(5, 2)], vec![]);
+        assert!(cache.get(0).is_some(), "updating a cached token evicts nothing");
+        cache.insert(100, vec![], vec![]);
+        assert!(cache.get(0).is_none());
+        assert!(cache.get(2).is_some());
+        assert!(cache.get(100).is_some());
+        assert_eq!(cache.0.lock().unwrap().len(), NODE_CACHE_CAPACITY);
+    }
+
+    #[wasm_bindgen_test]
+    async fn debounced_calls_in_quick_succession_run_once() {
+        let runtime = create_runtime();
+        let runs = Rc::new(Cell::new(0));
+        let debounce = Debounce::new(Duration::from_millis(50));
+
+        for _ in 0..3 {
+            let runs = runs.clone();
+            debounce.run(move || runs.set(runs.get() + 1));
+        }
+        assert_eq!(runs.get(), 0);
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(runs.get(), 1);
+
+        let runs_ = runs.clone();
+        debounce.run(move || runs_.set(runs_.get() + 1));
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(runs.get(), 2);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn clearing_the_cache_reloads_the_children_and_bids_from_the_chain() {
+        let runtime = create_runtime();
+        let provider = MockProvider::new(|method, params| match method {
+            "eth_call" => {
+                let data = params[0]["data"].as_str().unwrap();
+                let tokens = if data.starts_with(&selector("getAncestryMetadata")) {
+                    vec![token(1, 0)]
+                } else if data.starts_with(&selector("getChildrenMetadata")) {
+                    vec![token(3, 1)]
+                } else {
+                    vec![token(4, 1)]
+                };
+                Ok(output(&[ethabi::Token::Array(tokens.into_iter().map(Tokenizable::into_token).collect())]))
+            }
+            _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+        });
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let explorer = ExplorerState::default();
+        explorer.cache.insert(1, vec![token(2, 1)], vec![]);
+
+        explorer.load(&erc1155_contract, Progress::new(), 1).await.unwrap();
+        assert_eq!(explorer.children.get_untracked().keys().collect::<Vec<_>>(), vec![&2]);
+        assert!(explorer.bids.get_untracked().is_empty());
+
+        explorer.cache.clear();
+        explorer.load(&erc1155_contract, Progress::new(), 1).await.unwrap();
+        assert_eq!(explorer.children.get_untracked().keys().collect::<Vec<_>>(), vec![&3]);
+        assert_eq!(explorer.bids.get_untracked().keys().collect::<Vec<_>>(), vec![&4]);
+        assert_eq!(explorer.nav_history.get_untracked().iter().map(|token| token.token_id).collect::<Vec<_>>(), vec![1]);
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn superseded_load_aborts_its_http_requests() {
+        let runtime = create_runtime();
+        // the node never answers, requests are kept to check their abort signal
+        let requests = js_sys::Array::new();
+        let fetch = Closure::<dyn Fn(JsValue) -> js_sys::Promise>::new({
+            let requests = requests.clone();
+            move |request: JsValue| {
+                requests.push(&request);
+                js_sys::Promise::new(&mut |_, _| {})
+            }
+        });
+        let global = js_sys::global();
+        let original_fetch = js_sys::Reflect::get(&global, &"fetch".into()).unwrap();
+        js_sys::Reflect::set(&global, &"fetch".into(), fetch.as_ref()).unwrap();
+        let aborted = |request: JsValue| js_sys::Reflect::get(&request, &"signal".into())
+            .and_then(|signal| js_sys::Reflect::get(&signal, &"aborted".into()))
+            .map(|aborted| aborted.is_truthy())
+            .unwrap_or_default();
+
+        let web3 = web3::Web3::new(Either::Right(Http::new("http://127.0.0.1:8545").unwrap()));
+        let erc1155_contract = ERC1155Contract::new(&web3, true, Arc::new(|_| {}));
+        let explorer = ExplorerState::default();
+        let (load, abort_handle) = futures::future::abortable(async move {
+            let _ = explorer.load(&erc1155_contract, Progress::new(), 2).await;
+        });
+        spawn_local(async move { let _ = load.await; });
+        sleep(Duration::from_millis(10)).await;
+        assert!(requests.length() > 0);
+        assert!(!requests.iter().any(aborted));
+
+        abort_handle.abort();
+        sleep(Duration::from_millis(10)).await;
+        js_sys::Reflect::set(&global, &"fetch".into(), &original_fetch).unwrap();
+        assert!(requests.iter().all(aborted));
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn token_without_a_readable_ancestry_is_shown_on_its_own() {
+        let runtime = create_runtime();
+        let metadata_reverts = Arc::new(AtomicBool::new(false));
+        let provider = MockProvider::new({
+            let metadata_reverts = metadata_reverts.clone();
+            move |method, params| {
+                let data = params[0]["data"].as_str().unwrap_or_default();
+                let reverted = Err(serde_json::json!({ "code": 3, "message": "execution reverted", "data": "0x12345678" }));
+                match method {
+                    "eth_call" if data.starts_with(&selector("getAncestryMetadata")) => reverted,
+                    "eth_call" if data.starts_with(&selector("getMetadata")) && metadata_reverts.load(Ordering::SeqCst) => reverted,
+                    "eth_call" if data.starts_with(&selector("getMetadata")) => Ok(output(&[token(5, 1).into_token()])),
+                    "eth_call" if data.starts_with(&selector("getChildrenMetadata")) => Ok(output(&[ethabi::Token::Array(vec![token(6, 5).into_token()])])),
+                    "eth_call" => Ok(output(&[ethabi::Token::Array(vec![])])),
+                    _ => Err(serde_json::json!({ "code": -32601, "message": "unsupported" })),
+                }
+            }
+        });
+        let erc1155_contract = ERC1155Contract::new(provider.web3(), false, Arc::new(|_| {}));
+        let explorer = ExplorerState::default();
+
+        explorer.load(&erc1155_contract, Progress::new(), 5).await.unwrap();
+        assert_eq!(explorer.nav_history.get_untracked().iter().map(|token| token.token_id).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(explorer.children.get_untracked().keys().collect::<Vec<_>>(), vec![&6]);
+
+        // without the token itself there is nothing to show
+        metadata_reverts.store(true, Ordering::SeqCst);
+        explorer.cache.clear();
+        explorer.nav_history.set(vec![]);
+        assert!(explorer.load(&erc1155_contract, Progress::new(), 5).await.is_err());
+        assert!(explorer.nav_history.get_untracked().is_empty());
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    fn going_up_leads_to_the_parent_and_home_to_a_loaded_root() {
+        let runtime = create_runtime();
+        let explorer = ExplorerState::default();
+        explorer.nav_history.set(vec![token(ROOT_TOKEN_ID, 0), token(2, ROOT_TOKEN_ID), token(3, 2)]);
+        assert_eq!(explorer.parent().map(|token| token.token_id), Some(2));
+        assert_eq!(explorer.root().map(|token| token.token_id), Some(ROOT_TOKEN_ID));
+
+        explorer.nav_history.set(vec![token(ROOT_TOKEN_ID, 0)]);
+        assert!(explorer.parent().is_none());
+
+        // ancestors of a deep token left out of the history
+        explorer.nav_history.set(vec![token(7, 6), token(8, 7)]);
+        assert_eq!(explorer.parent().map(|token| token.token_id), Some(7));
+        assert!(explorer.root().is_none());
+        runtime.dispose();
+    }
+
+    #[wasm_bindgen_test]
+    async fn bid_is_removed_while_deleted_and_restored_when_that_fails() {
+        let runtime = create_runtime();
+        let inventory = create_rw_signal(HashMap::from([(4, token(4, 2)), (5, token(5, 2))]));
+        let explorer = create_rw_signal(HashMap::from([(5, token(5, 2))]));
+
+        let (sender, receiver) = oneshot::channel::<Option<()>>();
+        let deletion = remove_bid_while(4, &[inventory, explorer], async move { receiver.await.unwrap() });
+        let check = async {
+            assert_eq!(inventory.get_untracked().keys().collect::<Vec<_>>(), vec![&5]);
+            sender.send(None).unwrap();
+        };
+        let (deleted, ()) = futures::join!(deletion, check);
+        assert!(deleted.is_none());
+        let mut restored = inventory.get_untracked().into_keys().collect::<Vec<_>>();
+        restored.sort();
+        assert_eq!(restored, vec![4, 5]);
+        assert_eq!(explorer.get_untracked().keys().collect::<Vec<_>>(), vec![&5]);
+
+        assert!(remove_bid_while(5, &[inventory, explorer], async { Some(()) }).await.is_some());
+        assert_eq!(inventory.get_untracked().keys().collect::<Vec<_>>(), vec![&4]);
+        assert!(explorer.get_untracked().is_empty());
+        runtime.dispose();
+    }
+}
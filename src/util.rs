@@ -1,4 +1,6 @@
-use leptos::SignalGetUntracked;
+use std::time::Duration;
+
+use leptos::{set_timeout, SignalGetUntracked};
 use leptos_router::use_query_map;
 
 
@@ -21,10 +23,125 @@ pub fn parse_url_query_string<'a>(query: &'a str, search_key: &str) -> Option<&'
 }
 
 
-pub fn preserve_log_level(uri: String) -> String {
-    if let Some(log_level) = use_query_map().get_untracked().get("RUST_LOG") {
-        format!("{uri}?RUST_LOG={log_level}")
-    } else {
+/// Query parameters that have to survive navigation between routes.
+const PRESERVED_QUERY_PARAMS: [&str; 3] = ["RUST_LOG", "spectator", "debug"];
+
+pub fn preserve_query(uri: String) -> String {
+    let query_map = use_query_map().get_untracked();
+    let query: Vec<String> = PRESERVED_QUERY_PARAMS.iter()
+        .filter_map(|key| query_map.get(*key).map(|value| format!("{key}={value}")))
+        .collect();
+    if query.is_empty() {
         uri
+    } else {
+        format!("{uri}?{}", query.join("&"))
+    }
+}
+
+
+/// Spectator mode is requested with `?spectator=true` and hides every control that writes to the chain.
+pub fn is_spectator() -> bool {
+    let query_string = web_sys::window().unwrap().location().search().unwrap();
+    parse_url_query_string(&query_string, "spectator") == Some("true")
+}
+
+
+/// Debug mode is requested with `?debug=true` and shows the internal state of the explorer.
+pub fn is_debug() -> bool {
+    let query_string = web_sys::window().unwrap().location().search().unwrap();
+    parse_url_query_string(&query_string, "debug") == Some("true")
+}
+
+
+/// Value persisted under `key` in the browser's local storage.
+pub fn load_local(key: &str) -> Option<String> {
+    web_sys::window().unwrap().local_storage().ok().flatten()?.get_item(key).ok().flatten()
+}
+
+pub fn store_local(key: &str, value: &str) {
+    if let Ok(Some(storage)) = web_sys::window().unwrap().local_storage() {
+        let _ = storage.set_item(key, value);
+    }
+}
+
+
+/// Copy text to the system clipboard, silently ignored when the browser does not expose one.
+pub fn copy_to_clipboard(text: &str) {
+    if let Some(clipboard) = web_sys::window().unwrap().navigator().clipboard() {
+        let _ = clipboard.write_text(text);
+    }
+}
+
+
+pub async fn sleep(duration: Duration) {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    set_timeout(move || {
+        let _ = sender.send(());
+    }, duration);
+    let _ = receiver.await;
+}
+
+
+const LOCALE_KEY: &str = "locale";
+
+/// Locale chosen by the user, empty when following the browser.
+pub fn locale_setting() -> String {
+    load_local(LOCALE_KEY).unwrap_or_default()
+}
+
+/// Locale used to display numbers, the one chosen by the user or else the browser's.
+pub fn locale() -> String {
+    Some(locale_setting())
+        .filter(|locale| !locale.is_empty())
+        .or_else(|| web_sys::window()?.navigator().language())
+        .unwrap_or_else(|| "en-US".into())
+}
+
+/// Empty `locale` goes back to the browser's one.
+pub fn set_locale(locale: &str) {
+    store_local(LOCALE_KEY, locale);
+}
+
+/// Number formatted for display in the user's locale. Values sent to the chain or put into URLs must not go through this.
+pub fn format_number(value: f64, maximum_fraction_digits: u32) -> String {
+    format_number_in(&locale(), value, maximum_fraction_digits)
+}
+
+fn format_number_in(locale: &str, value: f64, maximum_fraction_digits: u32) -> String {
+    let options = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&options, &"maximumFractionDigits".into(), &maximum_fraction_digits.into());
+    let locales = js_sys::Array::of1(&locale.into());
+    let format = js_sys::Intl::NumberFormat::new(&locales, &options).format();
+    format.call1(&wasm_bindgen::JsValue::UNDEFINED, &value.into())
+        .ok()
+        .and_then(|formatted| formatted.as_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// OM amount formatted for display.
+pub fn format_amount(value: f64) -> String {
+    format_number(value, 4)
+}
+
+/// Uniswap swap from ETH into wOM, which can then be unwrapped into OM.
+pub fn uniswap_link() -> String {
+    format!("https://app.uniswap.org/#/swap?inputCurrency=ETH&outputCurrency={}", env!("ERC20_CONTRACT_ADDRESS"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn numbers_are_formatted_for_the_locale() {
+        let value = 1234.5;
+        assert_eq!(format_number_in("en-US", value, 4), "1,234.5");
+        assert_eq!(format_number_in("de-DE", value, 4), "1.234,5");
+        assert_eq!(format_number_in("en-US", 0.123456, 4), "0.1235");
+        // machine values keep the locale independent formatting
+        assert_eq!(value.to_string(), "1234.5");
     }
 }